@@ -22,7 +22,25 @@ fn main() {
         Box::new(tools::zip::ZipTool {}),
         Box::new(tools::patch::PatchTool {}),
         Box::new(tools::reserialize::ReserializeTool {}),
-        Box::new(tools::chatbroadcast::ChatBroadcastTool {})
+        Box::new(tools::chatbroadcast::ChatBroadcastTool {}),
+        Box::new(tools::vox::VoxTool {}),
+        Box::new(tools::botmesh::BotMeshTool {}),
+        Box::new(tools::render::RenderTool {}),
+        Box::new(tools::botdiff::BotDiffTool {}),
+        Box::new(tools::botmerge::BotMergeTool {}),
+        Box::new(tools::bottext::BotTextTool {}),
+        Box::new(tools::botsize::BotSizeTool {}),
+        Box::new(tools::sharecode::ShareCodeTool {}),
+        Box::new(tools::botpaint::BotPaintTool {}),
+        Box::new(tools::botcluster::BotClusterTool {}),
+        Box::new(tools::catalog::CatalogTool {}),
+        Box::new(tools::botgen::BotGenTool {}),
+        Box::new(tools::garage::GarageTool {}),
+        Box::new(tools::botedit::BotEditTool {}),
+        Box::new(tools::remap::RemapTool {}),
+        Box::new(tools::statdiff::StatDiffTool {}),
+        Box::new(tools::statcsv::StatCsvTool {}),
+        Box::new(tools::statlint::StatLintTool {})
     );
 
     let matched = tools.iter().find(|&x| x.as_ref().command() == tool);
@@ -1,4 +1,5 @@
 use procelio_files::files::robot;
+use std::convert::TryFrom;
 use std::io::{Read, Write};
 
 pub struct BotBinTool {
@@ -11,10 +12,14 @@ impl super::ProcelioCLITool for BotBinTool {
     }
 
     fn usage(&self) {
-        println!("path/to/json.json [path/to/bin]");
+        println!("path/to/json.json [path/to/bin] [--v5] [--zstd] [--schema path/to/schema.json] [--metadata path/to/metadata.json] [--controls path/to/controls.json]");
         println!("    converts a json robot file (at given path) to the binary representation");
         println!("    if no path/to/bin is supplied, defaults to 'path/to/json.robot'");
         println!("    either way, the resultant file is suitable for being served");
+        println!("    --schema encodes decoded extra data \"fields\" back to bytes (see dump --schema)");
+        println!("    --metadata encodes decoded \"metadata_fields\" back to a number (see dump --metadata)");
+        println!("    --controls reads rewires written with input names (see dump --controls)");
+        println!("    --v5 writes the compact version 5 layout instead of version 4; --zstd also compresses it");
     }
 
     fn tool(&self, args: Vec<String>) {
@@ -23,13 +28,28 @@ impl super::ProcelioCLITool for BotBinTool {
 }
 
 
-fn tool_impl(args: Vec<String>) {
+fn tool_impl(mut args: Vec<String>) {
+    let schema = match super::take_schema_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(s) => s.unwrap_or_default()
+    };
+    let metadata = match super::take_metadata_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(m) => m
+    };
+    let controls = match super::take_controls_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(c) => c
+    };
+    let compress = args.iter().any(|x| x == "--zstd");
+    let v5 = compress || args.iter().any(|x| x == "--v5");
+    let args: Vec<&String> = args.iter().filter(|x| *x != "--v5" && *x != "--zstd").collect();
     let arg = &args[0];
     let source = std::path::Path::new(&arg);
     let dst = args.get(1);
     let destination = match dst {
         None => source.with_extension("robot"),
-        Some(e) => std::path::PathBuf::from(e)
+        Some(e) => std::path::PathBuf::from(*e)
     };
 
     let file = std::fs::File::open(source);
@@ -44,11 +64,16 @@ fn tool_impl(args: Vec<String>) {
         println!("Could not read {}: {}", source.display(), e);
         return;
     }
-    let botfile : robot::JsonRobot = serde_json::from_slice(&file_contents).unwrap();
-    let binarybotfile : robot::Robot = botfile.into();
-    let res = binarybotfile.compile();
+    let mut botfile : robot::JsonRobot = match serde_json::from_slice(&file_contents) {
+        Err(e) => { println!("Unable to parse {}: {}", source.display(), e); return; },
+        Ok(b) => b
+    };
+    let res = botfile.encode_extra(&schema)
+        .and_then(|_| metadata.as_ref().map_or(Ok(()), |m| botfile.encode_metadata(m)))
+        .and_then(|_| botfile.resolve_controls(&controls))
+        .and_then(|_| robot::Robot::try_from(botfile)).and_then(|x| if v5 { x.compile_v5(compress) } else { x.compile() });
     match res {
-        Err(e) => { println!("Unable to compile robotfile: {}", e)},
+        Err(e) => { println!("Unable to compile robotfile: {}", e); return; },
         Ok(data) => {
             let ff = std::fs::File::create(destination);
             if ff.is_err() {
@@ -0,0 +1,142 @@
+use procelio_files::files::robot::Robot;
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+pub struct BotClusterTool {
+
+}
+
+impl super::ProcelioCLITool for BotClusterTool {
+    fn command(&self) -> &'static str {
+        "botcluster"
+    }
+
+    fn usage(&self) {
+        println!("path/to/dir [--threshold 0.9] [--ignore-color] [--json]");
+        println!("    groups the .robot files under a directory into clusters of near-identical bots");
+        println!("    similarity is the share of parts in common after the best translation (0..1);");
+        println!("    bots join a cluster if they are at least --threshold similar to any member");
+        println!("    fingerprints ignore part order and position; --ignore-color also ignores paint");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+#[derive(Serialize)]
+struct ClusterMember {
+    path: PathBuf,
+    name: String,
+    parts: usize,
+    fingerprint: String
+}
+
+#[derive(Serialize)]
+struct Cluster {
+    members: Vec<ClusterMember>,
+    min_similarity: f64 // lowest similarity between linked members
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    parent[i] = root;
+    root
+}
+
+fn tool_impl(args: Vec<String>) {
+    let mut dir = None;
+    let mut threshold = 0.9f64;
+    let mut ignore_color = false;
+    let mut json = false;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--threshold" => match iter.next().and_then(|x| x.parse().ok()).filter(|x: &f64| (0.0..=1.0).contains(x)) {
+                Some(t) => threshold = t,
+                None => { println!("--threshold needs a number from 0 to 1"); return; }
+            },
+            "--ignore-color" => ignore_color = true,
+            "--json" => json = true,
+            _ => dir = Some(arg)
+        }
+    }
+    let Some(dir) = dir else {
+        println!("botcluster needs a directory");
+        return;
+    };
+
+    let mut bots: Vec<(PathBuf, Robot, String)> = Vec::new();
+    for entry in walkdir::WalkDir::new(&dir).into_iter().flatten() {
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().is_none_or(|x| x != "robot") {
+            continue;
+        }
+        match std::fs::read(path).and_then(|x| Robot::try_from(x.as_slice())) {
+            Err(e) => eprintln!("Skipping {}: {}", path.display(), e),
+            Ok(bot) => {
+                let fingerprint = bot.fingerprint(ignore_color);
+                bots.push((path.to_owned(), bot, fingerprint));
+            }
+        }
+    }
+
+    // single-linkage: union every pair that clears the threshold
+    let mut parent: Vec<usize> = (0..bots.len()).collect();
+    let mut link_min: Vec<f64> = vec!(1.0; bots.len());
+    for i in 0..bots.len() {
+        for j in (i + 1)..bots.len() {
+            let (a, b) = (&bots[i], &bots[j]);
+            let score = if a.2 == b.2 {
+                1.0
+            } else {
+                // the Jaccard score can't beat the ratio of part counts
+                let (na, nb) = (a.1.parts.len().max(1) as f64, b.1.parts.len().max(1) as f64);
+                if na.min(nb) / na.max(nb) < threshold {
+                    continue;
+                }
+                a.1.similarity(&b.1, ignore_color)
+            };
+            if score >= threshold {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                let lowest = link_min[ri].min(link_min[rj]).min(score);
+                parent[rj] = ri;
+                link_min[ri] = lowest;
+            }
+        }
+    }
+
+    let mut clusters: std::collections::BTreeMap<usize, Vec<usize>> = std::collections::BTreeMap::new();
+    for i in 0..bots.len() {
+        let root = find(&mut parent, i);
+        clusters.entry(root).or_default().push(i);
+    }
+    let clusters: Vec<Cluster> = clusters.into_iter().filter(|x| x.1.len() > 1).map(|(root, members)| Cluster {
+        min_similarity: link_min[root],
+        members: members.into_iter().map(|i| ClusterMember {
+            path: bots[i].0.clone(),
+            name: String::from_utf8_lossy(&bots[i].1.bot_name).into_owned(),
+            parts: bots[i].1.parts.len(),
+            fingerprint: bots[i].2.clone()
+        }).collect()
+    }).collect();
+
+    if json {
+        match serde_json::to_string_pretty(&clusters) {
+            Err(e) => println!("Unable to serialize: {}", e),
+            Ok(s) => println!("{}", s)
+        }
+        return;
+    }
+    for (i, cluster) in clusters.iter().enumerate() {
+        println!("cluster {} ({} bots, similarity >= {:.2})", i + 1, cluster.members.len(), cluster.min_similarity);
+        for m in cluster.members.iter() {
+            println!("    {}  {:>6} parts  {}  {}", &m.fingerprint[..12], m.parts, m.name, m.path.display());
+        }
+    }
+    println!("{} bots, {} clusters of similar bots", bots.len(), clusters.len());
+}
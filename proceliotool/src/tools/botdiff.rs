@@ -0,0 +1,52 @@
+use procelio_files::files::robot::Robot;
+use std::convert::TryFrom;
+
+pub struct BotDiffTool {
+
+}
+
+impl super::ProcelioCLITool for BotDiffTool {
+    fn command(&self) -> &'static str {
+        "botdiff"
+    }
+
+    fn usage(&self) {
+        println!("path/to/old.robot path/to/new.robot [--json]");
+        println!("    compares two binary robots part by part (matched by position)");
+        println!("    reports added/removed/changed parts, cosmetics, input rewiring, name and metadata");
+        println!("    --json prints the diff as JSON instead of text");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+fn load(path: &str) -> Option<Robot> {
+    match std::fs::read(path).and_then(|x| Robot::try_from(x.as_slice())) {
+        Err(e) => { println!("Unable to load robot {}: {}", path, e); None },
+        Ok(b) => Some(b)
+    }
+}
+
+fn tool_impl(args: Vec<String>) {
+    let json = args.iter().any(|x| x == "--json");
+    let paths: Vec<&String> = args.iter().filter(|x| *x != "--json").collect();
+    if paths.len() != 2 {
+        println!("botdiff needs exactly two robot files");
+        return;
+    }
+    let (Some(old), Some(new)) = (load(paths[0]), load(paths[1])) else {
+        return;
+    };
+
+    let diff = old.diff(&new);
+    if json {
+        match serde_json::to_string_pretty(&diff) {
+            Err(e) => println!("Unable to serialize: {}", e),
+            Ok(s) => println!("{}", s)
+        }
+    } else {
+        print!("{}", diff);
+    }
+}
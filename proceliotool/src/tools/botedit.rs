@@ -0,0 +1,128 @@
+use procelio_files::files::robot::{parse_edits, Robot, RobotEdit};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+pub struct BotEditTool {
+
+}
+
+impl super::ProcelioCLITool for BotEditTool {
+    fn command(&self) -> &'static str {
+        "botedit"
+    }
+
+    fn usage(&self) {
+        println!("path/to/bot.robot [more files or dirs...] [--op \"EDIT\"]... [--ops path/to/edits.txt] [--dry-run]");
+        println!("    applies edits in order to binary robots in place, keeping input rewiring");
+        println!("    v5 files are written back as v5 (compressed if they were); older versions are upgraded to v4");
+        println!("    directories are searched for .robot files; --dry-run shows what would change without writing");
+        println!("    edits (one per --op, or one per line in the --ops file; # starts a comment line):");
+        println!("      rename NAME | delete X Y Z | swap A B | cosmetic ID PART_ON");
+        println!("      metadata N (decimal or 0x hex)");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+// Returns whether the file was (or, with dry_run, would be) rewritten
+fn edit_file(path: &Path, edits: &[RobotEdit], dry_run: bool) -> Result<bool, std::io::Error> {
+    let original = std::fs::read(path)?;
+    let mut bot = Robot::try_from(original.as_slice())?;
+    println!("{}", path.display());
+    for edit in edits.iter() {
+        let change = bot.apply_edit(edit)?;
+        println!("    {}: {}", edit, change);
+    }
+    let data = bot.compile_like(&original)?;
+    if data == original {
+        println!("    unchanged");
+        return Ok(false);
+    }
+    if !dry_run {
+        std::fs::write(path, data)?;
+    }
+    Ok(true)
+}
+
+fn tool_impl(args: Vec<String>) {
+    let mut edits = Vec::new();
+    let mut paths = Vec::new();
+    let mut dry_run = false;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        let res = match arg.as_str() {
+            "--dry-run" => { dry_run = true; Ok(()) },
+            "--op" => match iter.next() {
+                None => Err("--op needs an edit".to_owned()),
+                Some(op) => op.parse::<RobotEdit>().map(|x| edits.push(x)).map_err(|e| format!("--op {}: {}", op, e))
+            },
+            "--ops" => match iter.next() {
+                None => Err("--ops needs a file".to_owned()),
+                Some(file) => std::fs::read_to_string(&file).and_then(|x| parse_edits(&x))
+                    .map(|x| edits.extend(x)).map_err(|e| format!("{}: {}", file, e))
+            },
+            _ => { paths.push(PathBuf::from(arg)); Ok(()) }
+        };
+        if let Err(e) = res {
+            println!("{}", e);
+            return;
+        }
+    }
+    if edits.is_empty() || paths.is_empty() {
+        println!("botedit needs at least one robot and one edit");
+        return;
+    }
+
+    let mut files = Vec::new();
+    for path in paths.iter() {
+        if path.is_dir() {
+            files.extend(walkdir::WalkDir::new(path).into_iter().flatten()
+                .filter(|x| x.file_type().is_file() && x.path().extension().is_some_and(|e| e == "robot"))
+                .map(|x| x.into_path()));
+        } else {
+            files.push(path.clone());
+        }
+    }
+    let mut failed = 0;
+    let mut written = 0;
+    for file in files.iter() {
+        // a file is written only if every edit applied, so a failure never leaves it half edited
+        match edit_file(file, &edits, dry_run) {
+            Err(e) => {
+                println!("Unable to edit {}: {}", file.display(), e);
+                failed += 1;
+            },
+            Ok(true) => written += 1,
+            Ok(false) => {}
+        }
+    }
+    println!("{} {} of {} robots{}", if dry_run { "Would edit" } else { "Edited" }, written, files.len(),
+        if failed > 0 { format!(", {failed} failed") } else { String::new() });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_run_leaves_the_file_alone() {
+        let json = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/../robotfile.json")).unwrap();
+        let bot = Robot::try_from(serde_json::from_slice::<procelio_files::files::robot::JsonRobot>(&json).unwrap()).unwrap();
+        let original = bot.compile().unwrap();
+        let path = std::env::temp_dir().join(format!("botedit-test-{}.robot", std::process::id()));
+        std::fs::write(&path, &original).unwrap();
+        let edits = vec!(RobotEdit::Rename("Edited".to_owned()));
+
+        assert!(edit_file(&path, &edits, true).unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), original);
+        assert!(edit_file(&path, &edits, false).unwrap());
+        let edited = std::fs::read(&path).unwrap();
+        assert_eq!(Robot::try_from(edited.as_slice()).unwrap().bot_name, b"Edited");
+        // nothing left to change, so nothing is counted or written
+        assert!(!edit_file(&path, &edits, false).unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), edited);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+use procelio_files::files::robot::{BotRng, GeneratorConfig, Robot};
+use procelio_files::files::stats::statfile::StatsFile;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+pub struct BotGenTool {
+
+}
+
+impl super::ProcelioCLITool for BotGenTool {
+    fn command(&self) -> &'static str {
+        "botgen"
+    }
+
+    fn usage(&self) {
+        println!("path/to/file.stats path/to/output/dir [--count 1] [--seed 0] [--parts 60] [--cpu N]");
+        println!("         [--kit 6] [--colors 3] [--cosmetics 1] [--asymmetric] [--v5]");
+        println!("    generates connected robots with a core from the blocks and cosmetics in a statfile");
+        println!("    --parts is the target part count, --cpu caps the summed cpuCost, --kit sets how many");
+        println!("    block types each bot uses; the same seed and options always give the same bots");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    value.and_then(|x| x.parse().ok()).ok_or_else(|| format!("{flag} needs a number"))
+}
+
+fn tool_impl(args: Vec<String>) {
+    let mut config = GeneratorConfig::default();
+    let mut count = 1usize;
+    let mut seed = 0u64;
+    let mut v5 = false;
+    let mut paths = Vec::new();
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        let res = match arg.as_str() {
+            "--count" => number(&arg, iter.next()).map(|x| count = x),
+            "--seed" => number(&arg, iter.next()).map(|x| seed = x),
+            "--parts" => number(&arg, iter.next()).map(|x| config.parts = x),
+            "--cpu" => number(&arg, iter.next()).map(|x| config.cpu_budget = Some(x)),
+            "--kit" => number(&arg, iter.next()).map(|x| config.kit_size = x),
+            "--colors" => number(&arg, iter.next()).map(|x| config.colors = x),
+            "--cosmetics" => number(&arg, iter.next()).map(|x| config.cosmetics = x),
+            "--asymmetric" => { config.symmetric = false; Ok(()) },
+            "--v5" => { v5 = true; Ok(()) },
+            _ => { paths.push(arg); Ok(()) }
+        };
+        if let Err(e) = res {
+            println!("{}", e);
+            return;
+        }
+    }
+    if paths.len() != 2 {
+        println!("botgen needs a statfile and an output directory");
+        return;
+    }
+    let stats = match std::fs::read(&paths[0]).and_then(|x| StatsFile::try_from(x.as_slice())) {
+        Err(e) => { println!("Unable to load statfile {}: {}", paths[0], e); return; },
+        Ok(s) => s
+    };
+    let out = PathBuf::from(&paths[1]);
+    if let Err(e) = std::fs::create_dir_all(&out) {
+        println!("Unable to create {}: {}", out.display(), e);
+        return;
+    }
+
+    // every bot gets its own seed, drawn from the run's seed
+    let mut seeds = BotRng::new(seed);
+    let mut parts = 0;
+    for i in 0..count {
+        let bot_seed = seeds.next_u64();
+        let res = Robot::generate(&stats, &config, bot_seed)
+            .and_then(|x| { parts += x.parts.len(); if v5 { x.compile_v5(false) } else { x.compile() } })
+            .and_then(|x| std::fs::write(out.join(format!("gen-{i:05}.robot")), x));
+        if let Err(e) = res {
+            println!("Unable to generate robot {}: {}", i, e);
+            return;
+        }
+    }
+    println!("Generated {} robots ({} parts) in {}", count, parts, out.display());
+}
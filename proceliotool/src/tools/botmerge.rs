@@ -0,0 +1,79 @@
+use procelio_files::files::robot::Robot;
+use std::convert::TryFrom;
+
+pub struct BotMergeTool {
+
+}
+
+impl super::ProcelioCLITool for BotMergeTool {
+    fn command(&self) -> &'static str {
+        "botmerge"
+    }
+
+    fn usage(&self) {
+        println!("path/to/base path/to/ours path/to/theirs [-o path/to/out] [--json]");
+        println!("    three-way merges two edited copies of a binary robot, matching parts by position");
+        println!("    the result keeps the file version of 'ours' (v5 stays v5)");
+        println!("    writes the result over 'ours' unless -o is given; conflicting cells keep 'ours' and are listed");
+        println!("    exits with status 1 on conflicts, so it works as a git merge driver:");
+        println!("      .gitattributes:  *.robot merge=procelio-robot");
+        println!("      .git/config:     [merge \"procelio-robot\"] driver = proceliotool botmerge %O %A %B");
+        println!("    --json prints the conflict list as JSON");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        std::process::exit(tool_impl(args))
+    }
+}
+
+// The robot and the bytes it was read from
+fn load(path: &str) -> Option<(Robot, Vec<u8>)> {
+    match std::fs::read(path).and_then(|x| Robot::try_from(x.as_slice()).map(|b| (b, x))) {
+        Err(e) => { println!("Unable to load robot {}: {}", path, e); None },
+        Ok(b) => Some(b)
+    }
+}
+
+fn tool_impl(args: Vec<String>) -> i32 {
+    let mut json = false;
+    let mut output: Option<String> = None;
+    let mut paths = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--json" {
+            json = true;
+        } else if arg == "-o" {
+            output = args.next();
+        } else {
+            paths.push(arg);
+        }
+    }
+    if paths.len() != 3 {
+        println!("botmerge needs base, ours and theirs robot files");
+        return 2;
+    }
+    let (Some((base, _)), Some((ours, ours_data)), Some((theirs, _))) = (load(&paths[0]), load(&paths[1]), load(&paths[2])) else {
+        return 2;
+    };
+
+    let merged = Robot::merge3(&base, &ours, &theirs);
+    let destination = output.unwrap_or(paths[1].clone());
+    let written = merged.robot.compile_like(&ours_data).and_then(|data| std::fs::write(&destination, data));
+    if let Err(e) = written {
+        println!("Unable to save merged robot {}: {}", destination, e);
+        return 2;
+    }
+
+    if json {
+        match serde_json::to_string_pretty(&merged.conflicts) {
+            Err(e) => println!("Unable to serialize: {}", e),
+            Ok(s) => println!("{}", s)
+        }
+    } else {
+        for conflict in merged.conflicts.iter() {
+            println!("CONFLICT {}", conflict);
+        }
+        println!("Merged into {} with {} conflicts", destination, merged.conflicts.len());
+    }
+    if merged.conflicts.is_empty() { 0 } else { 1 }
+}
@@ -0,0 +1,316 @@
+use procelio_files::files::robot::{Robot, Part};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub struct BotMeshTool {
+
+}
+
+impl super::ProcelioCLITool for BotMeshTool {
+    fn command(&self) -> &'static str {
+        "botmesh"
+    }
+
+    fn usage(&self) {
+        println!("path/to/bot.robot [path/to/out.glb|.gltf|.obj] [--meshes path/to/dir]");
+        println!("    exports a binary robot as a 3D scene, one tinted mesh per part");
+        println!("    output format follows the extension (default .glb); .gltf writes a .bin alongside, .obj writes a .mtl alongside");
+        println!("    --meshes loads [part id].obj from the given folder, otherwise every part is a unit cube");
+        println!("    (part shapes aren't stored in robot files, so there is no built-in prism or other shape)");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+// Triangle soup in glTF space (right-handed, Y-up, one unit per robot cell)
+#[derive(Clone)]
+pub struct Geometry {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>
+}
+
+impl Geometry {
+    fn push_triangle(&mut self, a: [f32; 3], b: [f32; 3], c: [f32; 3]) {
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+        let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt().max(f32::EPSILON);
+        let n = [n[0] / len, n[1] / len, n[2] / len];
+        for p in [a, b, c] {
+            self.indices.push(self.positions.len() as u32);
+            self.positions.push(p);
+            self.normals.push(n);
+        }
+    }
+
+    pub fn cube() -> Geometry {
+        let mut geom = Geometry { positions: Vec::new(), normals: Vec::new(), indices: Vec::new() };
+        // each face as 4 corners, counter-clockwise seen from outside
+        let faces: [[[f32; 3]; 4]; 6] = [
+            [[0.5, -0.5, -0.5], [0.5, 0.5, -0.5], [0.5, 0.5, 0.5], [0.5, -0.5, 0.5]],
+            [[-0.5, -0.5, 0.5], [-0.5, 0.5, 0.5], [-0.5, 0.5, -0.5], [-0.5, -0.5, -0.5]],
+            [[-0.5, 0.5, -0.5], [-0.5, 0.5, 0.5], [0.5, 0.5, 0.5], [0.5, 0.5, -0.5]],
+            [[-0.5, -0.5, 0.5], [-0.5, -0.5, -0.5], [0.5, -0.5, -0.5], [0.5, -0.5, 0.5]],
+            [[-0.5, -0.5, 0.5], [0.5, -0.5, 0.5], [0.5, 0.5, 0.5], [-0.5, 0.5, 0.5]],
+            [[0.5, -0.5, -0.5], [-0.5, -0.5, -0.5], [-0.5, 0.5, -0.5], [0.5, 0.5, -0.5]]
+        ];
+        for f in faces.iter() {
+            geom.push_triangle(f[0], f[1], f[2]);
+            geom.push_triangle(f[0], f[2], f[3]);
+        }
+        geom
+    }
+
+    // Minimal OBJ reader: "v" and "f" lines only, polygons are fan-triangulated and flat shaded
+    pub fn from_obj(text: &str) -> Result<Geometry, String> {
+        let mut verts: Vec<[f32; 3]> = Vec::new();
+        let mut geom = Geometry { positions: Vec::new(), normals: Vec::new(), indices: Vec::new() };
+        for (lineno, line) in text.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let coords: Vec<f32> = tokens.take(3).map(|x| x.parse::<f32>()).collect::<Result<_, _>>()
+                        .map_err(|e| format!("line {}: {}", lineno + 1, e))?;
+                    if coords.len() != 3 {
+                        return Err(format!("line {}: vertex needs 3 coordinates", lineno + 1));
+                    }
+                    verts.push([coords[0], coords[1], coords[2]]);
+                },
+                Some("f") => {
+                    let face = tokens.map(|x| {
+                        let idx: i64 = x.split('/').next().unwrap_or("").parse().map_err(|_| format!("line {}: bad face index {}", lineno + 1, x))?;
+                        let resolved = if idx < 0 { verts.len() as i64 + idx } else { idx - 1 };
+                        verts.get(resolved as usize).copied().ok_or_else(|| format!("line {}: face index {} out of range", lineno + 1, idx))
+                    }).collect::<Result<Vec<[f32; 3]>, String>>()?;
+                    for i in 2..face.len() {
+                        geom.push_triangle(face[0], face[i - 1], face[i]);
+                    }
+                },
+                _ => {}
+            }
+        }
+        if geom.indices.is_empty() {
+            return Err("no faces".to_owned());
+        }
+        Ok(geom)
+    }
+}
+
+// Where a part's mesh ends up in glTF space: the game's Z axis points the other way.
+// The rotation byte isn't decoded yet (see docs/robotfile_v4.md), so parts are drawn unrotated
+fn part_offset(part: &Part) -> [f32; 3] {
+    [part.pos_x as f32, part.pos_y as f32, -(part.pos_z as f32)]
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn load_meshes(bot: &Robot, mesh_dir: Option<&Path>) -> HashMap<u32, Geometry> {
+    let mut meshes = HashMap::new();
+    for part in bot.parts.iter() {
+        if meshes.contains_key(&part.id) {
+            continue;
+        }
+        let loaded = mesh_dir.map(|d| d.join(format!("{}.obj", part.id)))
+            .filter(|p| p.is_file())
+            .and_then(|p| match std::fs::read_to_string(&p).map_err(|e| e.to_string()).and_then(|s| Geometry::from_obj(&s)) {
+                Err(e) => { println!("Unable to load {}, using a cube: {}", p.display(), e); None },
+                Ok(g) => Some(g)
+            });
+        meshes.insert(part.id, loaded.unwrap_or_else(Geometry::cube));
+    }
+    meshes
+}
+
+fn to_obj(bot: &Robot, meshes: &HashMap<u32, Geometry>, mtl_name: &str) -> (String, String) {
+    let mut obj = format!("# {}\nmtllib {}\n", String::from_utf8_lossy(&bot.bot_name), mtl_name);
+    let mut mtl = String::new();
+    let mut materials: HashMap<[u8; 4], usize> = HashMap::new();
+    let mut vertex_base = 1;
+    for (i, part) in bot.parts.iter().enumerate() {
+        let color = [part.color_r, part.color_g, part.color_b, part.alpha_channel];
+        let next = materials.len();
+        let mat = *materials.entry(color).or_insert_with(|| {
+            mtl.push_str(&format!("newmtl color{}\nKd {:.4} {:.4} {:.4}\nd {:.4}\n\n", next,
+                color[0] as f32 / 255.0, color[1] as f32 / 255.0, color[2] as f32 / 255.0, color[3] as f32 / 255.0));
+            next
+        });
+        let geom = &meshes[&part.id];
+        let t = part_offset(part);
+        obj.push_str(&format!("o part{}_{}\nusemtl color{}\n", i, part.id, mat));
+        for p in geom.positions.iter() {
+            let w: Vec<f32> = (0..3).map(|r| p[r] + t[r]).collect();
+            obj.push_str(&format!("v {} {} {}\n", w[0], w[1], w[2]));
+        }
+        for tri in geom.indices.chunks(3) {
+            obj.push_str(&format!("f {} {} {}\n", tri[0] as usize + vertex_base, tri[1] as usize + vertex_base, tri[2] as usize + vertex_base));
+        }
+        vertex_base += geom.positions.len();
+    }
+    (obj, mtl)
+}
+
+// Builds the glTF JSON document and its single binary buffer
+fn to_gltf(bot: &Robot, meshes: &HashMap<u32, Geometry>, buffer_uri: Option<&str>) -> (serde_json::Value, Vec<u8>) {
+    let mut bin: Vec<u8> = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut geometry_accessors: HashMap<u32, (usize, usize, usize)> = HashMap::new();
+
+    let mut ids: Vec<&u32> = meshes.keys().collect();
+    ids.sort();
+    for id in ids {
+        let geom = &meshes[id];
+        let mut add_view = |bytes: Vec<u8>, target: u32| {
+            let view = serde_json::json!({ "buffer": 0, "byteOffset": bin.len(), "byteLength": bytes.len(), "target": target });
+            bin.extend(bytes);
+            buffer_views.push(view);
+            buffer_views.len() - 1
+        };
+        let pos_view = add_view(geom.positions.iter().flatten().flat_map(|x| x.to_le_bytes()).collect(), 34962);
+        let norm_view = add_view(geom.normals.iter().flatten().flat_map(|x| x.to_le_bytes()).collect(), 34962);
+        let idx_view = add_view(geom.indices.iter().flat_map(|x| x.to_le_bytes()).collect(), 34963);
+
+        let min: Vec<f32> = (0..3).map(|i| geom.positions.iter().map(|p| p[i]).fold(f32::INFINITY, f32::min)).collect();
+        let max: Vec<f32> = (0..3).map(|i| geom.positions.iter().map(|p| p[i]).fold(f32::NEG_INFINITY, f32::max)).collect();
+        accessors.push(serde_json::json!({ "bufferView": pos_view, "componentType": 5126, "count": geom.positions.len(), "type": "VEC3", "min": min, "max": max }));
+        accessors.push(serde_json::json!({ "bufferView": norm_view, "componentType": 5126, "count": geom.normals.len(), "type": "VEC3" }));
+        accessors.push(serde_json::json!({ "bufferView": idx_view, "componentType": 5125, "count": geom.indices.len(), "type": "SCALAR" }));
+        geometry_accessors.insert(*id, (accessors.len() - 3, accessors.len() - 2, accessors.len() - 1));
+    }
+
+    let mut materials = Vec::new();
+    let mut material_index: HashMap<[u8; 4], usize> = HashMap::new();
+    let mut gltf_meshes = Vec::new();
+    let mut mesh_index: HashMap<(u32, usize), usize> = HashMap::new();
+    let mut nodes = vec!(serde_json::json!({ "name": String::from_utf8_lossy(&bot.bot_name), "children": (1..=bot.parts.len()).collect::<Vec<usize>>() }));
+    for (i, part) in bot.parts.iter().enumerate() {
+        let color = [part.color_r, part.color_g, part.color_b, part.alpha_channel];
+        let mat = *material_index.entry(color).or_insert_with(|| {
+            materials.push(serde_json::json!({
+                "name": format!("#{:02x}{:02x}{:02x}{:02x}", color[0], color[1], color[2], color[3]),
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [srgb_to_linear(color[0]), srgb_to_linear(color[1]), srgb_to_linear(color[2]), color[3] as f32 / 255.0],
+                    "metallicFactor": 0.0
+                },
+                "alphaMode": if color[3] == 255 { "OPAQUE" } else { "BLEND" }
+            }));
+            materials.len() - 1
+        });
+        let (pos, norm, idx) = geometry_accessors[&part.id];
+        let mesh = *mesh_index.entry((part.id, mat)).or_insert_with(|| {
+            gltf_meshes.push(serde_json::json!({
+                "name": format!("part{}", part.id),
+                "primitives": [{ "attributes": { "POSITION": pos, "NORMAL": norm }, "indices": idx, "material": mat }]
+            }));
+            gltf_meshes.len() - 1
+        });
+        nodes.push(serde_json::json!({ "name": format!("part{}_{}", i, part.id), "mesh": mesh, "translation": part_offset(part) }));
+    }
+
+    let mut buffer = serde_json::json!({ "byteLength": bin.len() });
+    if let Some(uri) = buffer_uri {
+        buffer["uri"] = serde_json::Value::from(uri);
+    }
+    let doc = serde_json::json!({
+        "asset": { "version": "2.0", "generator": "proceliotool botmesh" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": nodes,
+        "meshes": gltf_meshes,
+        "materials": materials,
+        "accessors": accessors,
+        "bufferViews": buffer_views,
+        "buffers": [buffer]
+    });
+    (doc, bin)
+}
+
+fn to_glb(doc: &serde_json::Value, mut bin: Vec<u8>) -> Result<Vec<u8>, std::io::Error> {
+    let mut json = serde_json::to_vec(doc)?;
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+    let mut out = Vec::new();
+    out.write_all(b"glTF")?;
+    out.write_all(&u32::to_le_bytes(2))?;
+    out.write_all(&u32::to_le_bytes((12 + 8 + json.len() + 8 + bin.len()) as u32))?;
+    out.write_all(&u32::to_le_bytes(json.len() as u32))?;
+    out.write_all(b"JSON")?;
+    out.write_all(&json)?;
+    out.write_all(&u32::to_le_bytes(bin.len() as u32))?;
+    out.write_all(b"BIN\0")?;
+    out.write_all(&bin)?;
+    Ok(out)
+}
+
+fn write_outputs(bot: &Robot, meshes: &HashMap<u32, Geometry>, destination: &Path) -> Result<(), std::io::Error> {
+    // an empty scene has no bounds to write
+    if bot.parts.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "the robot has no parts"));
+    }
+    let ext = destination.extension().map(|x| x.to_string_lossy().to_ascii_lowercase()).unwrap_or_default();
+    match ext.as_str() {
+        "obj" => {
+            let mtl_path = destination.with_extension("mtl");
+            let mtl_name = mtl_path.file_name().map(|x| x.to_string_lossy().into_owned()).unwrap_or_default();
+            let (obj, mtl) = to_obj(bot, meshes, &mtl_name);
+            std::fs::write(destination, obj)?;
+            std::fs::write(mtl_path, mtl)
+        },
+        "gltf" => {
+            let bin_path = destination.with_extension("bin");
+            let bin_name = bin_path.file_name().map(|x| x.to_string_lossy().into_owned()).unwrap_or_default();
+            let (doc, bin) = to_gltf(bot, meshes, Some(&bin_name));
+            std::fs::write(destination, serde_json::to_string_pretty(&doc)?)?;
+            std::fs::write(bin_path, bin)
+        },
+        _ => {
+            let (doc, bin) = to_gltf(bot, meshes, None);
+            std::fs::write(destination, to_glb(&doc, bin)?)
+        }
+    }
+}
+
+fn tool_impl(args: Vec<String>) {
+    let mut mesh_dir: Option<PathBuf> = None;
+    let mut paths = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--meshes" {
+            mesh_dir = args.next().map(PathBuf::from);
+        } else {
+            paths.push(arg);
+        }
+    }
+    let Some(source) = paths.first() else {
+        println!("No input file given");
+        return;
+    };
+    let source = Path::new(source);
+    let destination = match paths.get(1) {
+        None => source.with_extension("glb"),
+        Some(e) => PathBuf::from(e)
+    };
+
+    let bot = match std::fs::read(source).and_then(|x| Robot::try_from(x.as_slice())) {
+        Err(e) => { println!("Unable to load robot {}: {}", source.display(), e); return; },
+        Ok(b) => b
+    };
+    let meshes = load_meshes(&bot, mesh_dir.as_deref());
+    if let Err(e) = write_outputs(&bot, &meshes, &destination) {
+        println!("Unable to save {}: {}", destination.display(), e);
+        return;
+    }
+    println!("Exported {} parts to {}", bot.parts.len(), destination.display());
+}
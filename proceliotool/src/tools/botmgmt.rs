@@ -1,6 +1,6 @@
 use std::io::{Read, Write, BufRead};
 use serde::{Serialize, Deserialize};
-use procelio_files::files::robot::Robot;
+use procelio_files::files::robot::{Robot, ControlNames};
 use std::convert::{TryFrom};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -31,6 +31,21 @@ fn display(count: &str, bot: &Robot) -> String {
     format!("{}{}{}{}", s1, name, std::iter::repeat_n(' ', 41 - s1.len() - name.len() - ct.len()).collect::<String>(), ct)
 }
 
+fn print_controls(bot: &Robot, names: &ControlNames) {
+    let controls = bot.controls();
+    if controls.bindings.is_empty() {
+        println!("{}: default controls", toutf8(&bot.bot_name));
+    } else {
+        println!("{}: input rewiring", toutf8(&bot.bot_name));
+        for (input, action) in controls.bindings.iter() {
+            println!("    {} -> {}", names.label(*input), names.label(*action));
+        }
+    }
+    for problem in controls.problems(names) {
+        println!("    warning: {}", problem);
+    }
+}
+
 pub struct BotMgmtTool {
 
 }
@@ -41,10 +56,11 @@ impl super::ProcelioCLITool for BotMgmtTool {
     }
 
     fn usage(&self) {
-        println!("(--user [userID]) (--read [readToken]) (--write [writeToken]) (--autobuy)");
+        println!("(--user [userID]) (--read [readToken]) (--write [writeToken]) (--autobuy) (--controls [path/to/controls.json])");
         println!("    Procelio development bot manager tool");
         println!("    - If on windows and have played the game, user+tokens login.session automatically)");
         println!("    - autobuy disabled if flag not provided");
+        println!("    - controls names input ids when showing rewires, otherwise they print as numbers");
     }
 
     fn tool(&self, args: Vec<String>) {
@@ -58,6 +74,7 @@ pub fn tool_impl(args: Vec<String>) {
     let mut read_token: String = "".to_owned();
     let mut write_token: String = "".to_owned();
     let mut autobuy: bool = false;
+    let mut control_names = ControlNames::default();
     if std::env::consts::OS == "windows" {
         let mut path = dirs::home_dir().unwrap();
         path.push("AppData");
@@ -86,6 +103,9 @@ pub fn tool_impl(args: Vec<String>) {
             write_token = args.next().unwrap();
         } else if arg == "--autobuy" {
             autobuy = true;
+        } else if arg == "--controls" {
+            let path = args.next().unwrap();
+            control_names = ControlNames::load(std::path::Path::new(&path)).unwrap();
         }
     }
 
@@ -161,6 +181,7 @@ pub fn tool_impl(args: Vec<String>) {
         println!("   | clear [#]");
         println!("   | download [#]");
         println!("   | upload [A] [#]");
+        println!("   | controls [#]");
         println!("   | quit");
         print!("> "); std::io::stdout().flush().unwrap();
         let mut buf = String::new();
@@ -205,6 +226,17 @@ pub fn tool_impl(args: Vec<String>) {
             println!("Saved to {}", format!("{}.bot",toutf8(&serv.bot_name)));
             std::fs::write(format!("{}.bot",toutf8(&serv.bot_name)), serv.compile().unwrap()).unwrap();
         }
+        if buf.starts_with("controls") {
+            let serv = if let Some(b) = data.get(1).and_then(|x|x.parse::<u32>().ok()).and_then(|x|server_bots.get(x as usize)) {
+                b
+            } else {
+                println!("Bad command!");
+                continue;
+            };
+            print_controls(serv, &control_names);
+            print!("(enter to continue) "); std::io::stdout().flush().unwrap();
+            let _ = std::io::stdin().read_line(&mut String::new());
+        }
         if buf.starts_with("upload") {
             let num = if let Some(n) = data.get(2).and_then(|x|x.parse::<u32>().ok()) {
                 if n as usize > server_bots.len() {
@@ -0,0 +1,131 @@
+use procelio_files::files::robot::{Robot, Rgb};
+use std::convert::TryFrom;
+
+pub struct BotPaintTool {
+
+}
+
+impl super::ProcelioCLITool for BotPaintTool {
+    fn command(&self) -> &'static str {
+        "botpaint"
+    }
+
+    fn usage(&self) {
+        println!("path/to/bot.robot palette [--json]");
+        println!("    lists the bot's colours (#rrggbbaa) with how many parts use each");
+        println!("path/to/bot.robot map RRGGBB=RRGGBB [RRGGBB=RRGGBB...] [--tolerance N] [-o out.robot]");
+        println!("    repaints parts within N (per channel, default 0) of a source colour");
+        println!("path/to/bot.robot part ID RRGGBB [-o out.robot]");
+        println!("    repaints every part of the given type");
+        println!("path/to/bot.robot team --slots RRGGBB[,RRGGBB...] RRGGBB [RRGGBB...] [-o out.robot]");
+        println!("    swaps each slot (placeholder) colour for the team colour in the same position");
+        println!("    there can be fewer team colours than slots, but not more");
+        println!("    edits overwrite the input unless -o is given; alpha is never changed");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+fn parse_rgb(s: &str) -> Option<Rgb> {
+    let bytes = hex::decode(s.trim_start_matches('#')).ok()?;
+    <[u8; 3]>::try_from(bytes.as_slice()).ok()
+}
+
+fn parse_all(args: &[String]) -> Option<Vec<Rgb>> {
+    args.iter().map(|x| parse_rgb(x)).collect()
+}
+
+fn tool_impl(args: Vec<String>) {
+    if args.len() < 2 {
+        println!("botpaint needs a robot file and an operation");
+        return;
+    }
+    let mut rest: Vec<String> = Vec::new();
+    let mut output: Option<String> = None;
+    let mut tolerance = 0u8;
+    let mut json = false;
+    let mut slots: Option<Vec<Rgb>> = None;
+    let mut iter = args[2..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" => output = iter.next().cloned(),
+            "--json" => json = true,
+            "--slots" => match iter.next().and_then(|x| parse_all(&x.split(',').map(|x| x.to_owned()).collect::<Vec<_>>())) {
+                Some(s) if !s.is_empty() => slots = Some(s),
+                _ => { println!("--slots needs comma-separated RRGGBB colours"); return; }
+            },
+            "--tolerance" => match iter.next().and_then(|x| x.parse().ok()) {
+                Some(t) => tolerance = t,
+                None => { println!("--tolerance needs a number from 0 to 255"); return; }
+            },
+            _ => rest.push(arg.clone())
+        }
+    }
+
+    let path = &args[0];
+    let original = match std::fs::read(path) {
+        Err(e) => { println!("Unable to load robot {}: {}", path, e); return; },
+        Ok(data) => data
+    };
+    let mut bot = match Robot::try_from(original.as_slice()) {
+        Err(e) => { println!("Unable to load robot {}: {}", path, e); return; },
+        Ok(b) => b
+    };
+
+    let changed = match args[1].as_str() {
+        "palette" => {
+            let palette = bot.palette();
+            if json {
+                match serde_json::to_string_pretty(&palette) {
+                    Err(e) => println!("Unable to serialize: {}", e),
+                    Ok(s) => println!("{}", s)
+                }
+            } else {
+                for usage in palette.iter() {
+                    println!("#{} {}", hex::encode(usage.color), usage.count);
+                }
+            }
+            return;
+        },
+        "map" => {
+            let mapping: Option<Vec<(Rgb, Rgb)>> = rest.iter().map(|x| {
+                let (from, to) = x.split_once('=')?;
+                Some((parse_rgb(from)?, parse_rgb(to)?))
+            }).collect();
+            match mapping {
+                Some(m) if !m.is_empty() => bot.remap_colors(&m, tolerance),
+                _ => { println!("map needs one or more RRGGBB=RRGGBB pairs"); return; }
+            }
+        },
+        "part" => {
+            let id = rest.first().and_then(|x| x.parse::<u32>().ok());
+            let color = rest.get(1).and_then(|x| parse_rgb(x));
+            match (id, color) {
+                (Some(id), Some(color)) if rest.len() == 2 => bot.recolor_parts(id, color),
+                _ => { println!("part needs a part id and an RRGGBB colour"); return; }
+            }
+        },
+        "team" => {
+            let Some(slots) = slots else {
+                println!("team needs --slots with the placeholder colours");
+                return;
+            };
+            match parse_all(&rest) {
+                Some(team) if !team.is_empty() => match bot.apply_team_colors(&slots, &team) {
+                    Err(e) => { println!("{}", e); return; },
+                    Ok(n) => n
+                },
+                _ => { println!("team needs one or more RRGGBB colours"); return; }
+            }
+        },
+        other => { println!("Unknown botpaint operation {}", other); return; }
+    };
+
+    let destination = output.unwrap_or_else(|| path.clone());
+    match bot.compile_like(&original).and_then(|x| std::fs::write(&destination, x)) {
+        Err(e) => println!("Unable to save {}: {}", destination, e),
+        Ok(_) => println!("Repainted {} parts, wrote {}", changed, destination)
+    }
+}
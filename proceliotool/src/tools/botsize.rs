@@ -0,0 +1,89 @@
+use procelio_files::files::robot::Robot;
+use std::convert::TryFrom;
+
+pub struct BotSizeTool {
+
+}
+
+impl super::ProcelioCLITool for BotSizeTool {
+    fn command(&self) -> &'static str {
+        "botsize"
+    }
+
+    fn usage(&self) {
+        println!("path/to/garage [more paths...]");
+        println!("    benchmarks robot file sizes: every .robot file in the given files/directories is");
+        println!("    re-encoded as v4, v5 and v5 + zstd, checked to read back unchanged, and the sizes compared");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+#[derive(Default)]
+struct Totals {
+    bots: usize,
+    parts: usize,
+    v4: usize,
+    v5: usize,
+    zstd: usize
+}
+
+fn percent(size: usize, base: usize) -> f64 {
+    if base == 0 { 0.0 } else { 100.0 * size as f64 / base as f64 }
+}
+
+fn measure(path: &std::path::Path, totals: &mut Totals) -> Result<(), std::io::Error> {
+    let data = std::fs::read(path)?;
+    let bot = Robot::try_from(data.as_slice())?;
+    let v4 = bot.compile()?;
+    let v5 = bot.compile_v5(false)?;
+    let zstd = bot.compile_v5(true)?;
+    for encoded in [&v5, &zstd] {
+        let back = Robot::try_from(encoded.as_slice())?;
+        if back.compile()? != v4 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "v5 round trip changed the robot"));
+        }
+    }
+    println!("{:>7} {:>8} {:>8} ({:>5.1}%) {:>8} ({:>5.1}%)  {}", bot.parts.len(), v4.len(),
+        v5.len(), percent(v5.len(), v4.len()), zstd.len(), percent(zstd.len(), v4.len()), path.display());
+    totals.bots += 1;
+    totals.parts += bot.parts.len();
+    totals.v4 += v4.len();
+    totals.v5 += v5.len();
+    totals.zstd += zstd.len();
+    Ok(())
+}
+
+fn tool_impl(args: Vec<String>) {
+    if args.is_empty() {
+        println!("botsize needs at least one robot file or directory");
+        return;
+    }
+    println!("{:>7} {:>8} {:>17} {:>17}  file", "parts", "v4", "v5", "v5+zstd");
+    let mut totals = Totals::default();
+    for arg in args.iter() {
+        for entry in walkdir::WalkDir::new(arg) {
+            let entry = match entry {
+                Err(e) => { println!("Unable to read {}: {}", arg, e); continue; },
+                Ok(e) => e
+            };
+            let path = entry.path();
+            if !entry.file_type().is_file() || path.extension().is_none_or(|x| x != "robot") {
+                continue;
+            }
+            if let Err(e) = measure(path, &mut totals) {
+                println!("Unable to measure {}: {}", path.display(), e);
+            }
+        }
+    }
+    if totals.bots == 0 {
+        println!("No robots found");
+        return;
+    }
+    println!("{} robots, {} parts", totals.bots, totals.parts);
+    println!("v4:      {} bytes ({:.1} per part)", totals.v4, totals.v4 as f64 / totals.parts.max(1) as f64);
+    println!("v5:      {} bytes ({:.1} per part, {:.1}% of v4)", totals.v5, totals.v5 as f64 / totals.parts.max(1) as f64, percent(totals.v5, totals.v4));
+    println!("v5+zstd: {} bytes ({:.1} per part, {:.1}% of v4)", totals.zstd, totals.zstd as f64 / totals.parts.max(1) as f64, percent(totals.zstd, totals.v4));
+}
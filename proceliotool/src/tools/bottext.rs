@@ -0,0 +1,51 @@
+use procelio_files::files::robot::{Robot, ROBOT_MAGIC_NUMBER};
+use std::convert::TryFrom;
+use std::path::Path;
+
+pub struct BotTextTool {
+
+}
+
+impl super::ProcelioCLITool for BotTextTool {
+    fn command(&self) -> &'static str {
+        "bottext"
+    }
+
+    fn usage(&self) {
+        println!("path/to/input [path/to/output]");
+        println!("    converts a binary robot to the line-based text format, or a text robot back to binary");
+        println!("    the direction is picked from the input's contents");
+        println!("    default output is the input with a .txt (binary input) or .robot (text input) extension");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+fn tool_impl(args: Vec<String>) {
+    if args.is_empty() || args.len() > 2 {
+        println!("bottext needs an input file and optionally an output file");
+        return;
+    }
+    let input = Path::new(&args[0]);
+    let data = match std::fs::read(input) {
+        Err(e) => { println!("Unable to read {}: {}", input.display(), e); return; },
+        Ok(d) => d
+    };
+
+    let is_binary = data.len() >= 4 && u32::from_be_bytes([data[0], data[1], data[2], data[3]]) == ROBOT_MAGIC_NUMBER;
+    let (output, result) = if is_binary {
+        let out = args.get(1).map(|x| Path::new(x).to_owned()).unwrap_or_else(|| input.with_extension("txt"));
+        (out, Robot::try_from(data.as_slice()).map(|x| x.to_text().into_bytes()))
+    } else {
+        let out = args.get(1).map(|x| Path::new(x).to_owned()).unwrap_or_else(|| input.with_extension("robot"));
+        let text = String::from_utf8_lossy(&data);
+        (out, Robot::from_text(&text).and_then(|x| x.compile()))
+    };
+
+    match result.and_then(|x| std::fs::write(&output, x)) {
+        Err(e) => println!("Unable to convert {}: {}", input.display(), e),
+        Ok(_) => println!("Wrote {}", output.display())
+    }
+}
@@ -0,0 +1,314 @@
+use procelio_files::files::robot::Robot;
+use procelio_files::files::stats::statfile::StatsFile;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+pub struct CatalogTool {
+
+}
+
+impl super::ProcelioCLITool for CatalogTool {
+    fn command(&self) -> &'static str {
+        "catalog"
+    }
+
+    fn usage(&self) {
+        println!("index path/to/dir [--stats path/to/file.stats] [--index path/to/index.json]");
+        println!("    indexes every .robot file under the directory (name, parts, cost, fingerprint, mtime)");
+        println!("    only new or modified files are parsed again; the index defaults to dir/{}", INDEX_FILE);
+        println!("query path/to/dir [TERM...] [--stats ...] [--index ...] [--json] [--sort FIELD]");
+        println!("    refreshes the index, then lists bots matching every term:");
+        println!("      parts>200  cost<=5000  cosmetics=0    (fields: parts, cost, cosmetics, mtime; ops: = != < <= > >=)");
+        println!("      contains:57  cosmetic:432             (has a part / cosmetic with that id)");
+        println!("      name~tank  name=Tank  path~garage     (~ is a case-insensitive substring)");
+        println!("      fingerprint:3fa9                      (fingerprint prefix)");
+        println!("      prefix a term with ! to negate it; --sort takes name, parts, cost, mtime or path");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+const INDEX_FILE: &str = ".robot-catalog.json";
+const INDEX_VERSION: u32 = 2; // older indexes are rebuilt
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CatalogEntry {
+    path: PathBuf, // relative to the indexed directory
+    name: String,
+    parts: usize,
+    cosmetics: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cost: Option<i64>,
+    fingerprint: String,
+    part_ids: Vec<u32>,
+    cosmetic_ids: Vec<u32>,
+    mtime: u64, // unix seconds
+    mtime_nanos: u32, // sub-second part, so edits within the same second are noticed
+    size: u64
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CatalogIndex {
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stats_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stats_hash: Option<String>, // hash of the statfile the costs were computed from
+    entries: BTreeMap<PathBuf, CatalogEntry>
+}
+
+#[derive(Default)]
+struct RefreshCounts {
+    added: usize,
+    updated: usize,
+    removed: usize,
+    unchanged: usize,
+    failed: usize
+}
+
+// (unix seconds, nanoseconds)
+fn mtime_of(meta: &std::fs::Metadata) -> (u64, u32) {
+    meta.modified().ok()
+        .and_then(|x| x.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|x| (x.as_secs(), x.subsec_nanos())).unwrap_or((0, 0))
+}
+
+fn load_index(path: &Path) -> CatalogIndex {
+    std::fs::read(path).ok()
+        .and_then(|x| serde_json::from_slice::<CatalogIndex>(&x).ok())
+        .filter(|x| x.version == INDEX_VERSION)
+        .unwrap_or(CatalogIndex { version: INDEX_VERSION, ..Default::default() })
+}
+
+fn index_bot(root: &Path, rel: &Path, meta: &std::fs::Metadata, stats: Option<&StatsFile>) -> Result<CatalogEntry, std::io::Error> {
+    let bot = Robot::try_from(std::fs::read(root.join(rel))?.as_slice())?;
+    let mut part_ids: Vec<u32> = bot.parts.iter().map(|x| x.id).collect();
+    part_ids.sort();
+    part_ids.dedup();
+    let mut cosmetic_ids: Vec<u32> = bot.cosmetics.iter().map(|x| x.id).collect();
+    cosmetic_ids.sort();
+    cosmetic_ids.dedup();
+    Ok(CatalogEntry {
+        path: rel.to_owned(),
+        name: String::from_utf8_lossy(&bot.bot_name).into_owned(),
+        parts: bot.parts.len(),
+        cosmetics: bot.cosmetics.len(),
+        cost: stats.map(|x| bot.cost(x)),
+        fingerprint: bot.fingerprint(false),
+        part_ids,
+        cosmetic_ids,
+        mtime: mtime_of(meta).0,
+        mtime_nanos: mtime_of(meta).1,
+        size: meta.len()
+    })
+}
+
+// Re-parses only files whose size or mtime changed (or everything, if the statfile changed)
+fn refresh(index: &mut CatalogIndex, root: &Path, stats: Option<(&StatsFile, String)>) -> RefreshCounts {
+    let mut counts = RefreshCounts::default();
+    let stats_hash = stats.as_ref().map(|x| x.1.clone());
+    let stats_changed = stats_hash != index.stats_hash;
+    index.stats_hash = stats_hash;
+
+    let mut seen = std::collections::HashSet::new();
+    for entry in walkdir::WalkDir::new(root).into_iter().flatten() {
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().is_none_or(|x| x != "robot") {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else { continue; };
+        let rel = path.strip_prefix(root).unwrap_or(path).to_owned();
+        seen.insert(rel.clone());
+        let existing = index.entries.get(&rel);
+        if !stats_changed && existing.is_some_and(|x| (x.mtime, x.mtime_nanos) == mtime_of(&meta) && x.size == meta.len()) {
+            counts.unchanged += 1;
+            continue;
+        }
+        match index_bot(root, &rel, &meta, stats.as_ref().map(|x| x.0)) {
+            Err(e) => {
+                eprintln!("Skipping {}: {}", path.display(), e);
+                index.entries.remove(&rel);
+                counts.failed += 1;
+            },
+            Ok(e) => {
+                if index.entries.insert(rel, e).is_some() { counts.updated += 1; } else { counts.added += 1; }
+            }
+        }
+    }
+    let before = index.entries.len();
+    index.entries.retain(|k, _| seen.contains(k));
+    counts.removed = before - index.entries.len();
+    counts
+}
+
+enum Term {
+    Compare(String, String, i64), // field, op, value
+    Contains(u32),
+    Cosmetic(u32),
+    Text(String, bool, String), // field, exact, value
+    Fingerprint(String)
+}
+
+fn parse_term(term: &str) -> Result<Term, String> {
+    if let Some(id) = term.strip_prefix("contains:") {
+        return id.parse().map(Term::Contains).map_err(|_| format!("bad part id in '{term}'"));
+    }
+    if let Some(id) = term.strip_prefix("cosmetic:") {
+        return id.parse().map(Term::Cosmetic).map_err(|_| format!("bad cosmetic id in '{term}'"));
+    }
+    if let Some(fp) = term.strip_prefix("fingerprint:") {
+        return Ok(Term::Fingerprint(fp.to_lowercase()));
+    }
+    for field in ["name", "path"] {
+        if let Some(rest) = term.strip_prefix(field) {
+            if let Some(v) = rest.strip_prefix('~') {
+                return Ok(Term::Text(field.to_owned(), false, v.to_lowercase()));
+            }
+            if let Some(v) = rest.strip_prefix('=') {
+                return Ok(Term::Text(field.to_owned(), true, v.to_owned()));
+            }
+        }
+    }
+    for field in ["parts", "cost", "cosmetics", "mtime"] {
+        if let Some(rest) = term.strip_prefix(field) {
+            for op in ["<=", ">=", "!=", "<", ">", "="] {
+                if let Some(v) = rest.strip_prefix(op) {
+                    return v.parse().map(|v| Term::Compare(field.to_owned(), op.to_owned(), v)).map_err(|_| format!("bad number in '{term}'"));
+                }
+            }
+        }
+    }
+    Err(format!("unrecognised query term '{term}'"))
+}
+
+fn matches(term: &Term, e: &CatalogEntry) -> bool {
+    match term {
+        Term::Compare(field, op, value) => {
+            let actual = match field.as_str() {
+                "parts" => Some(e.parts as i64),
+                "cosmetics" => Some(e.cosmetics as i64),
+                "mtime" => Some(e.mtime as i64),
+                _ => e.cost
+            };
+            let Some(actual) = actual else { return false; };
+            match op.as_str() {
+                "<=" => actual <= *value,
+                ">=" => actual >= *value,
+                "!=" => actual != *value,
+                "<" => actual < *value,
+                ">" => actual > *value,
+                _ => actual == *value
+            }
+        },
+        Term::Contains(id) => e.part_ids.binary_search(id).is_ok(),
+        Term::Cosmetic(id) => e.cosmetic_ids.binary_search(id).is_ok(),
+        Term::Fingerprint(fp) => e.fingerprint.starts_with(fp.as_str()),
+        Term::Text(field, exact, value) => {
+            let text = if field == "name" { e.name.clone() } else { e.path.display().to_string() };
+            if *exact { text == *value } else { text.to_lowercase().contains(value.as_str()) }
+        }
+    }
+}
+
+fn tool_impl(args: Vec<String>) {
+    let mut iter = args.into_iter();
+    let Some(mode) = iter.next().filter(|x| x == "index" || x == "query") else {
+        println!("catalog needs 'index' or 'query'");
+        return;
+    };
+    let mut dir = None;
+    let mut index_path = None;
+    let mut stats_path = None;
+    let mut json = false;
+    let mut sort = "path".to_owned();
+    let mut terms = Vec::new();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--index" => index_path = iter.next().map(PathBuf::from),
+            "--stats" => stats_path = iter.next().map(|p| std::fs::canonicalize(&p).unwrap_or(PathBuf::from(p))),
+            "--sort" => sort = iter.next().unwrap_or_default(),
+            "--json" => json = true,
+            _ if dir.is_none() => dir = Some(PathBuf::from(arg)),
+            _ => terms.push(arg)
+        }
+    }
+    let Some(dir) = dir else {
+        println!("catalog needs a directory");
+        return;
+    };
+    if mode == "index" && !terms.is_empty() {
+        println!("catalog index takes no query terms");
+        return;
+    }
+    let mut parsed = Vec::new();
+    for term in terms.iter() {
+        let (negate, body) = match term.strip_prefix('!') {
+            Some(b) => (true, b),
+            None => (false, term.as_str())
+        };
+        match parse_term(body) {
+            Err(e) => { println!("{}", e); return; },
+            Ok(t) => parsed.push((negate, t))
+        }
+    }
+
+    let index_path = index_path.unwrap_or_else(|| dir.join(INDEX_FILE));
+    let mut index = load_index(&index_path);
+    // the index remembers its statfile, so later runs keep pricing bots without --stats
+    let stats_path = stats_path.or_else(|| index.stats_path.clone());
+    let stats = match &stats_path {
+        None => None,
+        Some(p) => match std::fs::read(p).and_then(|x| StatsFile::try_from(x.as_slice()).map(|s| (s, x))) {
+            Err(e) => { println!("Unable to load statfile {}: {}", p.display(), e); return; },
+            Ok((s, raw)) => {
+                use md5::Digest;
+                Some((s, hex::encode(md5::Md5::digest(&raw))))
+            }
+        }
+    };
+    index.stats_path = stats_path;
+    let counts = refresh(&mut index, &dir, stats.as_ref().map(|x| (&x.0, x.1.clone())));
+    match serde_json::to_vec(&index).map_err(std::io::Error::other).and_then(|x| std::fs::write(&index_path, x)) {
+        Err(e) => println!("Unable to save index {}: {}", index_path.display(), e),
+        Ok(_) if mode == "index" => {
+            println!("{} robots indexed ({} added, {} updated, {} removed, {} unchanged, {} unreadable)", index.entries.len(),
+                counts.added, counts.updated, counts.removed, counts.unchanged, counts.failed);
+            return;
+        },
+        Ok(_) => {}
+    }
+
+    let mut results: Vec<&CatalogEntry> = index.entries.values()
+        .filter(|e| parsed.iter().all(|(negate, t)| matches(t, e) != *negate))
+        .collect();
+    match sort.as_str() {
+        "name" => results.sort_by_key(|x| x.name.to_lowercase()),
+        "parts" => results.sort_by_key(|x| std::cmp::Reverse(x.parts)),
+        "cost" => results.sort_by_key(|x| std::cmp::Reverse(x.cost)),
+        "mtime" => results.sort_by_key(|x| std::cmp::Reverse(x.mtime)),
+        "path" => {},
+        _ => { println!("Unknown sort field '{}'", sort); return; }
+    }
+
+    if json {
+        match serde_json::to_string_pretty(&results) {
+            Err(e) => println!("Unable to serialize: {}", e),
+            Ok(s) => println!("{}", s)
+        }
+        return;
+    }
+    println!("{:<24} {:>6} {:>8} {:<12} {:<19} path", "name", "parts", "cost", "fingerprint", "modified");
+    for e in results.iter() {
+        let name: String = e.name.chars().take(24).collect();
+        let cost = e.cost.map(|x| x.to_string()).unwrap_or_else(|| "-".to_owned());
+        let modified = time::OffsetDateTime::from_unix_timestamp(e.mtime as i64)
+            .map(|d| format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", d.year(), u8::from(d.month()), d.day(), d.hour(), d.minute(), d.second()))
+            .unwrap_or_default();
+        println!("{:<24} {:>6} {:>8} {:<12} {:<19} {}", name, e.parts, cost, e.fingerprint.get(..12).unwrap_or(&e.fingerprint), modified, e.path.display());
+    }
+    println!("{} of {} robots match", results.len(), index.entries.len());
+}
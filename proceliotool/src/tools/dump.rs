@@ -1,9 +1,10 @@
+use procelio_files::files::garage::{Garage, JsonGarage};
 use procelio_files::files::inventory::Inventory;
 use procelio_files::files::inventory::JsonInventory;
 use procelio_files::files::localization::localization::Translation;
 use procelio_files::files::robot::JsonRobot;
 use procelio_files::files::robot::Robot;
-use procelio_files::files::stats::statfile::StatsFile;
+use procelio_files::files::stats::statfile::{JsonStatsFile, StatsFile};
 use procelio_files::files::tech::TechTree;
 use procelio_files::files::*;
 use serde::Serialize;
@@ -23,8 +24,12 @@ impl super::ProcelioCLITool for DumpTool {
     }
 
     fn usage(&self) {
-        println!("path/to/file");
+        println!("path/to/file [--schema path/to/schema.json] [--flags path/to/flags.json] [--controls path/to/controls.json] [--metadata path/to/metadata.json]");
         println!("    reads the given binary file and tries to print a JSON-deserialized form of it");
+        println!("    --schema decodes robot extra data and statfile cosmetic configs into named fields");
+        println!("    --flags names statfile flags using extra flag definitions");
+        println!("    --controls names robot input ids in rewires and their warnings ({{\"6\": \"fire1\", ...}})");
+        println!("    --metadata decodes robot metadata into named fields using that bit layout");
     }
 
     fn tool(&self, args: Vec<String>) {
@@ -49,7 +54,23 @@ fn dump<T: for<'a> TryFrom<&'a [u8]>, A: Serialize, N>(mut br: BufReader<File>,
     }
 }
 
-fn tool_impl(args: Vec<String>) {
+fn tool_impl(mut args: Vec<String>) {
+    let registry = match super::take_flags_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(r) => r
+    };
+    let schema = match super::take_schema_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(s) => s
+    };
+    let controls = match super::take_controls_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(c) => c
+    };
+    let metadata = match super::take_metadata_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(m) => m
+    };
     let file = &args[0];
     let path = std::path::Path::new(&file);
     let file = std::fs::File::open(path);
@@ -69,13 +90,30 @@ fn tool_impl(args: Vec<String>) {
     let magicnum = u32::from_be_bytes(magicnum);
     match magicnum {
         stats::statfile::STATFILE_MAGIC_NUMBER => {
-            dump(br, |x: StatsFile| x);
+            dump(br, |x: StatsFile| {
+                let mut json = JsonStatsFile::from_stats(&x, &registry);
+                if let Some(schema) = &schema {
+                    json.decode_configs(schema).iter().for_each(|x| eprintln!("{}", x));
+                }
+                json
+            });
         },
         inventory::INVENTORY_MAGIC_NUMBER => {
             dump(br, |x: Inventory| JsonInventory::from(&x));
         },
         robot::ROBOT_MAGIC_NUMBER => {
-            dump(br, |x: Robot| JsonRobot::from(x));
+            dump(br, |x: Robot| {
+                x.controls().problems(&controls).iter().for_each(|p| eprintln!("input rewire: {}", p));
+                let mut json = JsonRobot::from(x);
+                json.name_controls(&controls);
+                if let Some(schema) = &schema {
+                    json.decode_extra(schema).iter().for_each(|x| eprintln!("{}", x));
+                }
+                if let Some(problem) = metadata.as_ref().and_then(|m| json.decode_metadata(m)) {
+                    eprintln!("{}", problem);
+                }
+                json
+            });
         },
         localization::localization::LOCALIZATION_MAGIC_NUMBER => {
             dump(br, |x: Translation| x);
@@ -83,6 +121,9 @@ fn tool_impl(args: Vec<String>) {
         tech::TECHTREE_MAGIC_NUMBER => {
             dump(br, |x: TechTree| x);
         },
-        _ => { println!("Invalid filetype! Only supports [stats, inventory, robot, translation, tech]");}
+        garage::GARAGE_MAGIC_NUMBER => {
+            dump(br, |x: Garage| JsonGarage::from(x));
+        },
+        _ => { println!("Invalid filetype! Only supports [stats, inventory, robot, translation, tech, garage]");}
     }
 }
\ No newline at end of file
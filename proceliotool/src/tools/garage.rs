@@ -0,0 +1,138 @@
+use procelio_files::files::garage::{Garage, GarageEntry, JsonGarage};
+use procelio_files::files::robot::Robot;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+pub struct GarageTool {
+
+}
+
+impl super::ProcelioCLITool for GarageTool {
+    fn command(&self) -> &'static str {
+        "garage"
+    }
+
+    fn usage(&self) {
+        println!("pack path/to/out.garage path/to/bot.robot [more bots...] [--tag TAG]...");
+        println!("    bundles robots into a garage file, in slots 0, 1, 2...; every bot gets the given tags");
+        println!("    the display name is the bot's name and the creation time the file's modification time");
+        println!("unpack path/to/file.garage path/to/dir");
+        println!("    writes every bot as dir/slot-NNN.robot");
+        println!("list path/to/file.garage");
+        println!("compile path/to/garage.json [path/to/out.garage]");
+        println!("    converts the JSON form (see dump) back to a garage file");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+fn millis_of(path: &Path) -> i64 {
+    std::fs::metadata(path).and_then(|x| x.modified()).ok()
+        .or_else(|| Some(std::time::SystemTime::now()))
+        .and_then(|x| x.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|x| x.as_millis() as i64).unwrap_or(0)
+}
+
+fn load(path: &str) -> Result<Garage, String> {
+    std::fs::read(path).and_then(|x| Garage::try_from(x.as_slice())).map_err(|e| format!("Unable to load garage {}: {}", path, e))
+}
+
+fn save(garage: &Garage, path: &Path) -> Result<(), String> {
+    garage.compile().and_then(|x| std::fs::write(path, x)).map_err(|e| format!("Unable to save garage {}: {}", path.display(), e))
+}
+
+fn pack(args: Vec<String>) -> Result<String, String> {
+    let mut tags = Vec::new();
+    let mut paths = Vec::new();
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--tag" {
+            tags.push(iter.next().ok_or("--tag needs a value")?);
+        } else {
+            paths.push(arg);
+        }
+    }
+    if paths.len() < 2 {
+        return Err("garage pack needs an output file and at least one robot".to_owned());
+    }
+    let out = PathBuf::from(paths.remove(0));
+    let mut garage = Garage::default();
+    for (slot, path) in paths.iter().enumerate() {
+        let slot = u16::try_from(slot).map_err(|_| "A garage holds at most 65536 bots".to_owned())?;
+        let robot = std::fs::read(path).and_then(|x| Robot::try_from(x.as_slice()))
+            .map_err(|e| format!("Unable to load robot {}: {}", path, e))?;
+        garage.entries.push(GarageEntry {
+            slot,
+            display_name: String::from_utf8_lossy(&robot.bot_name).into_owned(),
+            tags: tags.clone(),
+            created: millis_of(Path::new(path)),
+            robot
+        });
+    }
+    save(&garage, &out)?;
+    Ok(format!("Packed {} robots into {}", garage.entries.len(), out.display()))
+}
+
+fn unpack(args: Vec<String>) -> Result<String, String> {
+    let [file, dir] = args.as_slice() else {
+        return Err("garage unpack needs a garage file and an output directory".to_owned());
+    };
+    let garage = load(file)?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("Unable to create {}: {}", dir, e))?;
+    for entry in garage.entries.iter() {
+        let path = Path::new(dir).join(format!("slot-{:03}.robot", entry.slot));
+        entry.robot.compile().and_then(|x| std::fs::write(&path, x))
+            .map_err(|e| format!("Unable to write {}: {}", path.display(), e))?;
+    }
+    Ok(format!("Unpacked {} robots into {}", garage.entries.len(), dir))
+}
+
+fn list(args: Vec<String>) -> Result<String, String> {
+    let [file] = args.as_slice() else {
+        return Err("garage list needs a garage file".to_owned());
+    };
+    let garage = load(file)?;
+    println!("{:>5} {:<24} {:>6} {:<19} tags", "slot", "name", "parts", "created");
+    for entry in garage.entries.iter() {
+        let created = time::OffsetDateTime::from_unix_timestamp(entry.created.div_euclid(1000))
+            .map(|d| format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", d.year(), u8::from(d.month()), d.day(), d.hour(), d.minute(), d.second()))
+            .unwrap_or_default();
+        let name: String = entry.display_name.chars().take(24).collect();
+        println!("{:>5} {:<24} {:>6} {:<19} {}", entry.slot, name, entry.robot.parts.len(), created, entry.tags.join(", "));
+    }
+    Ok(format!("{} robots", garage.entries.len()))
+}
+
+fn compile(args: Vec<String>) -> Result<String, String> {
+    let Some(source) = args.first().map(PathBuf::from) else {
+        return Err("garage compile needs a JSON file".to_owned());
+    };
+    let destination = args.get(1).map(PathBuf::from).unwrap_or_else(|| source.with_extension("garage"));
+    let json: JsonGarage = std::fs::read(&source).map_err(|e| e.to_string())
+        .and_then(|x| serde_json::from_slice(&x).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Unable to read {}: {}", source.display(), e))?;
+    let garage = Garage::try_from(json).map_err(|e| format!("Unable to compile garage: {}", e))?;
+    save(&garage, &destination)?;
+    Ok(format!("Garage successfully compiled to {}", destination.display()))
+}
+
+fn tool_impl(mut args: Vec<String>) {
+    if args.is_empty() {
+        println!("garage needs one of pack, unpack, list or compile");
+        return;
+    }
+    let mode = args.remove(0);
+    let res = match mode.as_str() {
+        "pack" => pack(args),
+        "unpack" => unpack(args),
+        "list" => list(args),
+        "compile" => compile(args),
+        _ => Err(format!("Unknown garage command '{}'", mode))
+    };
+    match res {
+        Err(e) => println!("{}", e),
+        Ok(msg) => println!("{}", msg)
+    }
+}
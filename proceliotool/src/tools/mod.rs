@@ -11,6 +11,24 @@ pub mod langbin;
 pub mod techbin;
 pub mod botmgmt;
 pub mod chatbroadcast;
+pub mod vox;
+pub mod botmesh;
+pub mod render;
+pub mod botdiff;
+pub mod botmerge;
+pub mod bottext;
+pub mod botsize;
+pub mod sharecode;
+pub mod botpaint;
+pub mod botcluster;
+pub mod catalog;
+pub mod botgen;
+pub mod garage;
+pub mod botedit;
+pub mod remap;
+pub mod statdiff;
+pub mod statcsv;
+pub mod statlint;
 
 pub trait ProcelioCLITool {
     fn command(&self) -> &'static str;
@@ -18,4 +36,60 @@ pub trait ProcelioCLITool {
     fn usage(&self);
 
     fn tool(&self, args: Vec<String>);
-}
\ No newline at end of file
+}
+
+// Removes "--schema path" from args and loads that schema registry (None if the flag is absent)
+pub fn take_schema_arg(args: &mut Vec<String>) -> Result<Option<procelio_files::files::schema::SchemaRegistry>, String> {
+    let Some(i) = args.iter().position(|x| x == "--schema") else {
+        return Ok(None);
+    };
+    if i + 1 >= args.len() {
+        return Err("--schema needs a path".to_owned());
+    }
+    let path = args.remove(i + 1);
+    args.remove(i);
+    procelio_files::files::schema::SchemaRegistry::load(std::path::Path::new(&path))
+        .map(Some).map_err(|e| format!("Unable to load schema {}: {}", path, e))
+}
+
+// Removes "--metadata path" from args and loads that metadata bit layout (None if the flag is absent)
+pub fn take_metadata_arg(args: &mut Vec<String>) -> Result<Option<procelio_files::files::robot::MetadataLayout>, String> {
+    let Some(i) = args.iter().position(|x| x == "--metadata") else {
+        return Ok(None);
+    };
+    if i + 1 >= args.len() {
+        return Err("--metadata needs a path".to_owned());
+    }
+    let path = args.remove(i + 1);
+    args.remove(i);
+    procelio_files::files::robot::MetadataLayout::load(std::path::Path::new(&path))
+        .map(Some).map_err(|e| format!("Unable to load metadata layout {}: {}", path, e))
+}
+
+// Removes "--controls path" from args and loads those input names; no names without it
+pub fn take_controls_arg(args: &mut Vec<String>) -> Result<procelio_files::files::robot::ControlNames, String> {
+    let Some(i) = args.iter().position(|x| x == "--controls") else {
+        return Ok(Default::default());
+    };
+    if i + 1 >= args.len() {
+        return Err("--controls needs a path".to_owned());
+    }
+    let path = args.remove(i + 1);
+    args.remove(i);
+    procelio_files::files::robot::ControlNames::load(std::path::Path::new(&path))
+        .map_err(|e| format!("Unable to load control names {}: {}", path, e))
+}
+
+// Removes "--flags path" from args and loads those flag definitions on top of the built-in flags
+pub fn take_flags_arg(args: &mut Vec<String>) -> Result<procelio_files::files::stats::flags::FlagRegistry, String> {
+    let Some(i) = args.iter().position(|x| x == "--flags") else {
+        return Ok(Default::default());
+    };
+    if i + 1 >= args.len() {
+        return Err("--flags needs a path".to_owned());
+    }
+    let path = args.remove(i + 1);
+    args.remove(i);
+    procelio_files::files::stats::flags::FlagRegistry::load(std::path::Path::new(&path))
+        .map_err(|e| format!("Unable to load flag definitions {}: {}", path, e))
+}
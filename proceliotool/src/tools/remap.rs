@@ -0,0 +1,138 @@
+use procelio_files::files::inventory::{Inventory, INVENTORY_MAGIC_NUMBER};
+use procelio_files::files::remap::{RemapReport, RemapTable};
+use procelio_files::files::robot::{Robot, ROBOT_MAGIC_NUMBER};
+use procelio_files::files::tech::{TechTree, TECHTREE_MAGIC_NUMBER};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+pub struct RemapTool {
+
+}
+
+impl super::ProcelioCLITool for RemapTool {
+    fn command(&self) -> &'static str {
+        "remap"
+    }
+
+    fn usage(&self) {
+        println!("path/to/remap.json path/to/files [more files or dirs...] [--report path/to/report.json] [--dry-run]");
+        println!("    renumbers or deletes part ids in .robot, .inventory and .tech files, in place");
+        println!("    directories are searched for files with those extensions; the file type comes from its magic number");
+        println!("    every change and the refunds owed for deleted parts are written to --report (default remap-report.json)");
+        println!("    remap.json: {{\"parts\": [{{\"from\": 12, \"to\": 112}}, {{\"from\": 13, \"to\": 113, \"rotation\": {{\"0\": 4}}, \"color\": [255, 0, 0]}},");
+        println!("                          {{\"from\": 14, \"delete\": true, \"refund\": 500}}]}}");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+#[derive(Serialize, Default)]
+struct Report {
+    dry_run: bool,
+    files: BTreeMap<PathBuf, RemapReport>,
+    failed: BTreeMap<PathBuf, String>,
+    total: RemapReport
+}
+
+fn remap_file(table: &RemapTable, path: &Path, dry_run: bool) -> Result<RemapReport, std::io::Error> {
+    let original = std::fs::read(path)?;
+    let magic = original.get(0..4).map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]])).unwrap_or(0);
+    let (report, data) = match magic {
+        ROBOT_MAGIC_NUMBER => {
+            let mut bot = Robot::try_from(original.as_slice())?;
+            let report = table.apply_robot(&mut bot);
+            (report, bot.compile_like(&original)?)
+        },
+        INVENTORY_MAGIC_NUMBER => {
+            let mut inv = Inventory::try_from(original.as_slice())?;
+            let report = table.apply_inventory(&mut inv);
+            (report, inv.compile()?)
+        },
+        TECHTREE_MAGIC_NUMBER => {
+            let mut tree = TechTree::try_from(original.as_slice())?;
+            let report = table.apply_tech(&mut tree);
+            (report, tree.compile()?)
+        },
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "not a robot, inventory or tech file"))
+    };
+    // untouched files are left byte for byte as they were
+    if !dry_run && !report.is_empty() {
+        std::fs::write(path, data)?;
+    }
+    Ok(report)
+}
+
+fn tool_impl(args: Vec<String>) {
+    let mut report_path = PathBuf::from("remap-report.json");
+    let mut dry_run = false;
+    let mut paths = Vec::new();
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--report" => match iter.next() {
+                None => { println!("--report needs a file"); return; },
+                Some(p) => report_path = PathBuf::from(p)
+            },
+            _ => paths.push(PathBuf::from(arg))
+        }
+    }
+    if paths.len() < 2 {
+        println!("remap needs a remap table and at least one file or directory");
+        return;
+    }
+    let table_path = paths.remove(0);
+    let table: RemapTable = match std::fs::read(&table_path).map_err(|e| e.to_string())
+        .and_then(|x| serde_json::from_slice(&x).map_err(|e| e.to_string())) {
+        Err(e) => { println!("Unable to read remap table {}: {}", table_path.display(), e); return; },
+        Ok(t) => t
+    };
+    if let Err(e) = table.validate() {
+        println!("Invalid remap table: {}", e);
+        return;
+    }
+
+    let mut files = Vec::new();
+    for path in paths.iter() {
+        if path.is_dir() {
+            files.extend(walkdir::WalkDir::new(path).into_iter().flatten()
+                .filter(|x| x.file_type().is_file() && x.path().extension().is_some_and(|e| e == "robot" || e == "inventory" || e == "tech"))
+                .map(|x| x.into_path()));
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    let mut report = Report { dry_run, ..Default::default() };
+    for file in files.iter() {
+        match remap_file(&table, file, dry_run) {
+            Err(e) => {
+                println!("Unable to remap {}: {}", file.display(), e);
+                report.failed.insert(file.clone(), e.to_string());
+            },
+            Ok(r) if r.is_empty() => {},
+            Ok(r) => {
+                let remapped: i64 = r.remapped.values().sum();
+                let deleted: i64 = r.deleted.values().sum();
+                println!("{}: {} remapped, {} deleted, refund {}", file.display(), remapped, deleted, r.refund);
+                for (id, n) in r.remapped.iter() {
+                    *report.total.remapped.entry(*id).or_default() += n;
+                }
+                for (id, n) in r.deleted.iter() {
+                    *report.total.deleted.entry(*id).or_default() += n;
+                }
+                report.total.refund += r.refund;
+                report.files.insert(file.clone(), r);
+            }
+        }
+    }
+    match serde_json::to_vec_pretty(&report).map_err(std::io::Error::other).and_then(|x| std::fs::write(&report_path, x)) {
+        Err(e) => println!("Unable to write report {}: {}", report_path.display(), e),
+        Ok(_) => println!("{} of {} files {}changed, {} failed; refunds owed: {}; report written to {}", report.files.len(), files.len(),
+            if dry_run { "would be " } else { "" }, report.failed.len(), report.total.refund, report_path.display())
+    }
+}
@@ -0,0 +1,294 @@
+use procelio_files::files::robot::Robot;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use image::{Rgba, RgbaImage};
+
+pub struct RenderTool {
+
+}
+
+impl super::ProcelioCLITool for RenderTool {
+    fn command(&self) -> &'static str {
+        "render"
+    }
+
+    fn usage(&self) {
+        println!("path/to/bot.robot [path/to/out.png] [--size W[xH]] [--yaw DEG] [--pitch DEG] [--background RRGGBB[AA]] [--sheet N]");
+        println!("    renders an orthographic thumbnail of a binary robot on the CPU");
+        println!("    defaults: 256x256, yaw 45, pitch 30, transparent background, output at path/to/bot.png");
+        println!("    --sheet renders N evenly spaced yaw angles into one contact sheet (each cell --size large)");
+        println!("    views are at most {MAX_SIZE} pixels a side, sheets at most {MAX_SHEET} views and {MAX_SHEET_SIDE} pixels a side");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+const SUPERSAMPLE: u32 = 2;
+const MAX_SIZE: u32 = 4096;
+const MAX_SHEET: u32 = 64;
+const MAX_SHEET_SIDE: u32 = 16384;
+const AMBIENT: f32 = 0.45;
+const EDGE_SHADE: f32 = 0.8;
+const EDGE_WIDTH: f32 = 0.06; // fraction of a face
+
+#[derive(Clone, Copy)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub yaw: f32, // degrees about the vertical axis
+    pub pitch: f32, // degrees looking down onto the bot
+    pub background: Rgba<u8>
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings { width: 256, height: 256, yaw: 45.0, pitch: 30.0, background: Rgba([0, 0, 0, 0]) }
+    }
+}
+
+// One visible cube face: corner + two edges in view space, plus its flat colour
+struct Face {
+    origin: [f32; 3],
+    edge_u: [f32; 3],
+    edge_v: [f32; 3],
+    color: [f32; 3]
+}
+
+// normal, corner offset and the two edges spanning a cube face, in world space
+type Side = ([i32; 3], [f32; 3], [f32; 3], [f32; 3]);
+
+fn view_transform(p: [f32; 3], yaw: f32, pitch: f32) -> [f32; 3] {
+    let (sy, cy) = yaw.to_radians().sin_cos();
+    let (sp, cp) = pitch.to_radians().sin_cos();
+    let x = p[0] * cy - p[2] * sy;
+    let z = p[0] * sy + p[2] * cy;
+    // view looks down +z; pitch tips the top of the bot towards the camera
+    [x, p[1] * cp + z * sp, -p[1] * sp + z * cp]
+}
+
+fn collect_faces(bot: &Robot, settings: &RenderSettings) -> Vec<Face> {
+    let occupied: std::collections::HashSet<(i8, i8, i8)> = bot.parts.iter()
+        .filter(|x| x.alpha_channel > 0)
+        .map(|x| (x.pos_x, x.pos_y, x.pos_z)).collect();
+    let light = {
+        let l = [-0.4f32, 1.0, -0.6];
+        let len = (l[0] * l[0] + l[1] * l[1] + l[2] * l[2]).sqrt();
+        [l[0] / len, l[1] / len, l[2] / len]
+    };
+    let sides: [Side; 6] = [
+        ([1, 0, 0], [0.5, -0.5, -0.5], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+        ([-1, 0, 0], [-0.5, -0.5, -0.5], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0, 1, 0], [-0.5, 0.5, -0.5], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0, -1, 0], [-0.5, -0.5, -0.5], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0, 0, 1], [-0.5, -0.5, 0.5], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0, 0, -1], [-0.5, -0.5, -0.5], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0])
+    ];
+
+    let mut faces = Vec::new();
+    for part in bot.parts.iter().filter(|x| x.alpha_channel > 0) {
+        let center = [part.pos_x as f32, part.pos_y as f32, part.pos_z as f32];
+        for (normal, corner, u, v) in sides.iter() {
+            let neighbour = (part.pos_x as i32 + normal[0], part.pos_y as i32 + normal[1], part.pos_z as i32 + normal[2]);
+            let hidden = match (i8::try_from(neighbour.0), i8::try_from(neighbour.1), i8::try_from(neighbour.2)) {
+                (Ok(x), Ok(y), Ok(z)) => occupied.contains(&(x, y, z)),
+                _ => false
+            };
+            let view_normal = view_transform([normal[0] as f32, normal[1] as f32, normal[2] as f32], settings.yaw, settings.pitch);
+            if hidden || view_normal[2] >= 0.0 {
+                continue;
+            }
+            let lambert = (normal[0] as f32 * light[0] + normal[1] as f32 * light[1] + normal[2] as f32 * light[2]).max(0.0);
+            let shade = AMBIENT + (1.0 - AMBIENT) * lambert;
+            faces.push(Face {
+                origin: view_transform([center[0] + corner[0], center[1] + corner[1], center[2] + corner[2]], settings.yaw, settings.pitch),
+                edge_u: view_transform(*u, settings.yaw, settings.pitch),
+                edge_v: view_transform(*v, settings.yaw, settings.pitch),
+                color: [part.color_r as f32 * shade, part.color_g as f32 * shade, part.color_b as f32 * shade]
+            });
+        }
+    }
+    faces
+}
+
+fn too_large(width: u32, height: u32) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{width}x{height} is too large to render"))
+}
+
+// Renders one view of the bot. Every part is drawn as a unit cube in its own colour
+pub fn render(bot: &Robot, settings: &RenderSettings) -> Result<RgbaImage, std::io::Error> {
+    if settings.width > MAX_SIZE || settings.height > MAX_SIZE {
+        return Err(too_large(settings.width, settings.height));
+    }
+    // with MAX_SIZE the supersampled buffer (and every pixel index) fits in a u32
+    let width = settings.width * SUPERSAMPLE;
+    let height = settings.height * SUPERSAMPLE;
+    let mut img = RgbaImage::from_pixel(width, height, settings.background);
+    let faces = collect_faces(bot, settings);
+    if faces.is_empty() {
+        return Ok(image::imageops::resize(&img, settings.width, settings.height, image::imageops::FilterType::Triangle));
+    }
+
+    let corners = faces.iter().flat_map(|f| [f.origin, [f.origin[0] + f.edge_u[0] + f.edge_v[0], f.origin[1] + f.edge_u[1] + f.edge_v[1], 0.0],
+        [f.origin[0] + f.edge_u[0], f.origin[1] + f.edge_u[1], 0.0], [f.origin[0] + f.edge_v[0], f.origin[1] + f.edge_v[1], 0.0]]);
+    let (mut min_x, mut max_x, mut min_y, mut max_y) = (f32::MAX, f32::MIN, f32::MAX, f32::MIN);
+    for c in corners {
+        min_x = min_x.min(c[0]);
+        max_x = max_x.max(c[0]);
+        min_y = min_y.min(c[1]);
+        max_y = max_y.max(c[1]);
+    }
+    let margin = 0.05 * width.min(height) as f32;
+    let scale = ((width as f32 - 2.0 * margin) / (max_x - min_x)).min((height as f32 - 2.0 * margin) / (max_y - min_y));
+    let offset_x = width as f32 / 2.0 - scale * (min_x + max_x) / 2.0;
+    let offset_y = height as f32 / 2.0 + scale * (min_y + max_y) / 2.0;
+    let to_screen = |p: [f32; 3]| [p[0] * scale + offset_x, offset_y - p[1] * scale];
+
+    let mut depth = vec!(f32::MAX; (width * height) as usize);
+    for face in faces.iter() {
+        let o = to_screen(face.origin);
+        let u = [face.edge_u[0] * scale, -face.edge_u[1] * scale];
+        let v = [face.edge_v[0] * scale, -face.edge_v[1] * scale];
+        let det = u[0] * v[1] - u[1] * v[0];
+        if det.abs() < 1e-6 {
+            continue;
+        }
+        let xs = [o[0], o[0] + u[0], o[0] + v[0], o[0] + u[0] + v[0]];
+        let ys = [o[1], o[1] + u[1], o[1] + v[1], o[1] + u[1] + v[1]];
+        let x0 = xs.iter().cloned().fold(f32::MAX, f32::min).floor().max(0.0) as u32;
+        let x1 = (xs.iter().cloned().fold(f32::MIN, f32::max).ceil() as u32).min(width);
+        let y0 = ys.iter().cloned().fold(f32::MAX, f32::min).floor().max(0.0) as u32;
+        let y1 = (ys.iter().cloned().fold(f32::MIN, f32::max).ceil() as u32).min(height);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                // solve pixel = o + a*u + b*v for the face parameters a, b
+                let dx = px as f32 + 0.5 - o[0];
+                let dy = py as f32 + 0.5 - o[1];
+                let a = (dx * v[1] - dy * v[0]) / det;
+                let b = (u[0] * dy - u[1] * dx) / det;
+                if !(0.0..=1.0).contains(&a) || !(0.0..=1.0).contains(&b) {
+                    continue;
+                }
+                let z = face.origin[2] + a * face.edge_u[2] + b * face.edge_v[2];
+                let idx = (py * width + px) as usize;
+                if z >= depth[idx] {
+                    continue;
+                }
+                depth[idx] = z;
+                let inner = EDGE_WIDTH..=1.0 - EDGE_WIDTH;
+                let edge = !inner.contains(&a) || !inner.contains(&b);
+                let k = if edge { EDGE_SHADE } else { 1.0 };
+                img.put_pixel(px, py, Rgba([(face.color[0] * k) as u8, (face.color[1] * k) as u8, (face.color[2] * k) as u8, 255]));
+            }
+        }
+    }
+    Ok(image::imageops::resize(&img, settings.width, settings.height, image::imageops::FilterType::Triangle))
+}
+
+// Renders `count` views spaced evenly around the bot into a near-square grid
+pub fn render_sheet(bot: &Robot, settings: &RenderSettings, count: u32) -> Result<RgbaImage, std::io::Error> {
+    if count > MAX_SHEET {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("A sheet holds at most {MAX_SHEET} views, not {count}")));
+    }
+    let count = count.max(1);
+    let cols = (count as f32).sqrt().ceil() as u32;
+    let rows = count.div_ceil(cols);
+    let (width, height) = match (cols.checked_mul(settings.width), rows.checked_mul(settings.height)) {
+        (Some(w), Some(h)) if w <= MAX_SHEET_SIDE && h <= MAX_SHEET_SIDE => (w, h),
+        _ => return Err(too_large(settings.width.saturating_mul(cols), settings.height.saturating_mul(rows)))
+    };
+    let mut sheet = RgbaImage::from_pixel(width, height, settings.background);
+    for i in 0..count {
+        let view = RenderSettings { yaw: settings.yaw + 360.0 * i as f32 / count as f32, ..*settings };
+        let cell = render(bot, &view)?;
+        image::imageops::overlay(&mut sheet, &cell, ((i % cols) * settings.width).into(), ((i / cols) * settings.height).into());
+    }
+    Ok(sheet)
+}
+
+fn parse_color(s: &str) -> Option<Rgba<u8>> {
+    let s = s.trim_start_matches('#');
+    if s.eq_ignore_ascii_case("transparent") {
+        return Some(Rgba([0, 0, 0, 0]));
+    }
+    let bytes = hex::decode(s).ok()?;
+    match bytes.len() {
+        3 => Some(Rgba([bytes[0], bytes[1], bytes[2], 255])),
+        4 => Some(Rgba([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        _ => None
+    }
+}
+
+fn tool_impl(args: Vec<String>) {
+    let mut settings = RenderSettings::default();
+    let mut sheet: Option<u32> = None;
+    let mut paths = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let ok = match arg.as_str() {
+            "--size" => args.next().and_then(|x| {
+                let mut dims = x.split('x').map(|d| d.parse::<u32>().ok().filter(|d| (1..=MAX_SIZE).contains(d)));
+                let w = dims.next()??;
+                let h = dims.next().unwrap_or(Some(w))?;
+                settings.width = w;
+                settings.height = h;
+                Some(())
+            }),
+            "--yaw" => args.next().and_then(|x| x.parse().ok()).map(|x| settings.yaw = x),
+            "--pitch" => args.next().and_then(|x| x.parse().ok()).map(|x| settings.pitch = x),
+            "--background" => args.next().and_then(|x| parse_color(&x)).map(|x| settings.background = x),
+            "--sheet" => args.next().and_then(|x| x.parse().ok()).filter(|x| *x <= MAX_SHEET).map(|x| sheet = Some(x)),
+            _ => { paths.push(arg.clone()); Some(()) }
+        };
+        if ok.is_none() {
+            println!("Bad value for {}", arg);
+            return;
+        }
+    }
+    let Some(source) = paths.first() else {
+        println!("No input file given");
+        return;
+    };
+    let source = Path::new(source);
+    let destination = match paths.get(1) {
+        None => source.with_extension("png"),
+        Some(e) => PathBuf::from(e)
+    };
+
+    let bot = match std::fs::read(source).and_then(|x| Robot::try_from(x.as_slice())) {
+        Err(e) => { println!("Unable to load robot {}: {}", source.display(), e); return; },
+        Ok(b) => b
+    };
+    let img = match sheet {
+        Some(n) => render_sheet(&bot, &settings, n),
+        None => render(&bot, &settings)
+    };
+    let img = match img {
+        Err(e) => { println!("Unable to render {}: {}", source.display(), e); return; },
+        Ok(i) => i
+    };
+    if let Err(e) = img.save(&destination) {
+        println!("Unable to save {}: {}", destination.display(), e);
+        return;
+    }
+    println!("Rendered {} to {}", source.display(), destination.display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_renders_are_errors() {
+        let bot = Robot::new();
+        let small = RenderSettings { width: 8, height: 4, ..Default::default() };
+        assert_eq!(render(&bot, &small).unwrap().dimensions(), (8, 4));
+        assert_eq!(render_sheet(&bot, &small, 5).unwrap().dimensions(), (24, 8));
+        let huge = RenderSettings { width: u32::MAX / 2 + 1, ..small };
+        assert!(render(&bot, &huge).is_err());
+        assert!(render_sheet(&bot, &huge, 4).is_err());
+        assert!(render_sheet(&bot, &RenderSettings { width: MAX_SIZE, height: MAX_SIZE, ..small }, 17).is_err());
+        assert!(render_sheet(&bot, &small, MAX_SHEET + 1).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use procelio_files::files::robot::Robot;
+use std::convert::TryFrom;
+
+pub struct ShareCodeTool {
+
+}
+
+impl super::ProcelioCLITool for ShareCodeTool {
+    fn command(&self) -> &'static str {
+        "sharecode"
+    }
+
+    fn usage(&self) {
+        println!("encode path/to/bot.robot [--wrap N]");
+        println!("    prints a share code for the robot, optionally wrapped every N characters");
+        println!("decode (CODE | path/to/code.txt) [path/to/out.robot]");
+        println!("    turns a share code back into a binary robot (default: <robot name>.robot)");
+        println!("    whitespace and line breaks inside the code are ignored");
+        println!("report path/to/garage [more paths...]");
+        println!("    lists the share code length of every .robot file, with totals");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+fn load(path: &std::path::Path) -> Result<Robot, std::io::Error> {
+    std::fs::read(path).and_then(|x| Robot::try_from(x.as_slice()))
+}
+
+fn encode(args: &[String]) {
+    let Some(path) = args.first() else {
+        println!("sharecode encode needs a robot file");
+        return;
+    };
+    let wrap = match args.iter().position(|x| x == "--wrap").map(|i| args.get(i + 1).map(|x| x.parse::<usize>())) {
+        None => None,
+        Some(Some(Ok(n))) if n > 0 => Some(n),
+        _ => { println!("--wrap needs a positive number"); return; }
+    };
+    match load(std::path::Path::new(path)).and_then(|x| x.to_share_code()) {
+        Err(e) => println!("Unable to encode {}: {}", path, e),
+        Ok(code) => match wrap {
+            None => println!("{}", code),
+            Some(n) => code.as_bytes().chunks(n).for_each(|x| println!("{}", String::from_utf8_lossy(x)))
+        }
+    }
+}
+
+fn decode(args: &[String]) {
+    let Some(input) = args.first() else {
+        println!("sharecode decode needs a code or a file containing one");
+        return;
+    };
+    let code = if std::path::Path::new(input).is_file() {
+        match std::fs::read_to_string(input) {
+            Err(e) => { println!("Unable to read {}: {}", input, e); return; },
+            Ok(s) => s
+        }
+    } else {
+        input.clone()
+    };
+    let bot = match Robot::from_share_code(&code) {
+        Err(e) => { println!("Unable to decode share code: {}", e); return; },
+        Ok(b) => b
+    };
+    let output = match args.get(1) {
+        Some(o) => std::path::PathBuf::from(o),
+        None => {
+            let name: String = String::from_utf8_lossy(&bot.bot_name).chars()
+                .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+            std::path::PathBuf::from(if name.is_empty() { "robot".to_owned() } else { name }).with_extension("robot")
+        }
+    };
+    match bot.compile().and_then(|x| std::fs::write(&output, x)) {
+        Err(e) => println!("Unable to save {}: {}", output.display(), e),
+        Ok(_) => println!("Wrote {} ({} parts)", output.display(), bot.parts.len())
+    }
+}
+
+fn report(args: &[String]) {
+    if args.is_empty() {
+        println!("sharecode report needs at least one robot file or directory");
+        return;
+    }
+    println!("{:>7} {:>8} {:>8}  file", "parts", "v4", "code");
+    let (mut bots, mut code_total, mut longest) = (0usize, 0usize, 0usize);
+    for arg in args.iter() {
+        for entry in walkdir::WalkDir::new(arg).into_iter().flatten() {
+            let path = entry.path();
+            if !entry.file_type().is_file() || path.extension().is_none_or(|x| x != "robot") {
+                continue;
+            }
+            let res = load(path).and_then(|bot| Ok((bot.compile()?.len(), bot.to_share_code()?, bot.parts.len())));
+            match res {
+                Err(e) => println!("Unable to encode {}: {}", path.display(), e),
+                Ok((v4, code, parts)) => {
+                    println!("{:>7} {:>8} {:>8}  {}", parts, v4, code.len(), path.display());
+                    bots += 1;
+                    code_total += code.len();
+                    longest = longest.max(code.len());
+                }
+            }
+        }
+    }
+    if bots == 0 {
+        println!("No robots found");
+        return;
+    }
+    println!("{} robots: average code {} characters, longest {}", bots, code_total / bots, longest);
+}
+
+fn tool_impl(args: Vec<String>) {
+    match args.first().map(|x| x.as_str()) {
+        Some("encode") => encode(&args[1..]),
+        Some("decode") => decode(&args[1..]),
+        Some("report") => report(&args[1..]),
+        _ => println!("sharecode needs one of: encode, decode, report")
+    }
+}
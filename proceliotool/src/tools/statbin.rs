@@ -11,10 +11,16 @@ impl super::ProcelioCLITool for StatBinTool {
     }
 
     fn usage(&self) {
-        println!("path/to/json.json [path/to/bin]");
+        println!("path/to/json.json [path/to/bin] [--schema path/to/schema.json] [--flags path/to/flags.json] [--keep-names]");
         println!("    converts a json partstats file (at given path) to the binary representation");
+        println!("    --schema encodes cosmetic \"config_fields\" back to config bytes");
+        println!("    --flags adds flag definitions (see docs/flag_definitions.md); unknown flag names and");
+        println!("    values outside a flag's range are reported");
+        println!("    writes statfile version 4; --keep-names writes version 5 instead, which keeps names and flag");
+        println!("    spellings so dump gives the JSON back, but only proceliotool can read it (don't serve it)");
+        println!("    \"templates\", \"extends\" and flag expressions are resolved first (see docs/statfile_templates.md)");
         println!("    if no path/to/bin is supplied, defaults to 'path/to/json.stats'");
-        println!("    either way, the resultant file is suitable for being served");
+        println!("    without --keep-names, the resultant file is suitable for being served");
     }
 
     fn tool(&self, args: Vec<String>) {
@@ -22,8 +28,17 @@ impl super::ProcelioCLITool for StatBinTool {
     }
 }
 
-fn tool_impl(args: Vec<String>) {
-    let mut args = args.into_iter();
+fn tool_impl(mut args: Vec<String>) {
+    let registry = match super::take_flags_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(r) => r
+    };
+    let schema = match super::take_schema_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(s) => s.unwrap_or_default()
+    };
+    let keep_names = args.iter().any(|x| x == "--keep-names");
+    let mut args = args.into_iter().filter(|x| x != "--keep-names");
     let arg = args.next().unwrap();
     let source = std::path::Path::new(&arg);
     let dst = args.next();
@@ -44,9 +59,20 @@ fn tool_impl(args: Vec<String>) {
         println!("Could not read {}: {}", source.display(), e);
         return;
     }
-    let statfile : statfile::JsonStatsFile = serde_json::from_slice(&file_contents).unwrap();
-    let binarystatfile : statfile::StatsFile = statfile.into();
-    let res = binarystatfile.compile();
+    let parsed = serde_json::from_slice(&file_contents).map_err(std::io::Error::from)
+        .and_then(|x| statfile::JsonStatsFile::resolve(x, &registry));
+    let mut statfile = match parsed {
+        Err(e) => { println!("Unable to read {}: {}", source.display(), e); return; },
+        Ok(s) => s
+    };
+    if let Err(e) = statfile.encode_configs(&schema) {
+        println!("Unable to compile statfile: {}", e);
+        return;
+    }
+    statfile.unknown_flags(&registry).iter().for_each(|x| println!("Warning: {} (dropped)", x));
+    let binarystatfile = statfile::StatsFile::from_json(statfile, &registry);
+    binarystatfile.check_ranges(&registry).iter().for_each(|x| println!("Warning: {}", x));
+    let res = if keep_names { binarystatfile.compile_with_names() } else { binarystatfile.compile() };
     match res {
         Err(e) => { println!("Unable to compile statfile: {}", e)},
         Ok(data) => {
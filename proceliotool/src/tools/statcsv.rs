@@ -0,0 +1,84 @@
+use procelio_files::files::stats::csv::StatsCsv;
+use procelio_files::files::stats::flags::FlagRegistry;
+use procelio_files::files::stats::statfile::{JsonStatsFile, StatsFile, STATFILE_MAGIC_NUMBER};
+use std::convert::TryFrom;
+use std::path::Path;
+
+pub struct StatCsvTool {
+
+}
+
+impl super::ProcelioCLITool for StatCsvTool {
+    fn command(&self) -> &'static str {
+        "statcsv"
+    }
+
+    fn usage(&self) {
+        println!("export path/to/statfile path/to/dir [--flags path/to/flags.json]");
+        println!("    writes blocks.csv, attacks.csv and cosmetics.csv: id, name, then a column per flag");
+        println!("    the statfile may be binary (.stats) or JSON");
+        println!("import path/to/dir path/to/output [--flags path/to/flags.json] [--keep-names]");
+        println!("    reads those CSVs back; every flag column must be a known flag name");
+        println!("    output ending in .stats is compiled to statfile version 4 (version 5 with --keep-names,");
+        println!("    which only proceliotool reads); anything else is written as statfile JSON");
+        println!("    exporting the result again gives the same CSVs");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+fn load(path: &str, registry: &FlagRegistry) -> Result<JsonStatsFile, String> {
+    let data = std::fs::read(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    if data.get(0..4) == Some(&STATFILE_MAGIC_NUMBER.to_be_bytes()) {
+        StatsFile::try_from(data.as_slice()).map(|x| JsonStatsFile::from_stats(&x, registry)).map_err(|e| format!("Unable to load statfile {}: {}", path, e))
+    } else {
+        serde_json::from_slice(&data).map_err(std::io::Error::from)
+            .and_then(|x| JsonStatsFile::resolve(x, registry)).map_err(|e| format!("Unable to load statfile JSON {}: {}", path, e))
+    }
+}
+
+fn export(source: &str, dir: &str, registry: &FlagRegistry) -> Result<String, String> {
+    let stats = load(source, registry)?;
+    stats.unknown_flags(registry).iter().for_each(|x| println!("Warning: {} (import will reject it)", x));
+    stats.to_csv(registry).write_dir(Path::new(dir)).map_err(|e| format!("Unable to write {}: {}", dir, e))?;
+    Ok(format!("Exported {} blocks, {} attacks and {} cosmetics to {}", stats.blocks.len(), stats.attacks.len(), stats.cosmetics.len(), dir))
+}
+
+fn import(dir: &str, destination: &str, keep_names: bool, registry: &FlagRegistry) -> Result<String, String> {
+    let csv = StatsCsv::read_dir(Path::new(dir)).map_err(|e| format!("Unable to read {}: {}", dir, e))?;
+    let stats = JsonStatsFile::from_csv(&csv, registry).map_err(|e| format!("Unable to import: {}", e))?;
+    let counts = format!("{} blocks, {} attacks and {} cosmetics", stats.blocks.len(), stats.attacks.len(), stats.cosmetics.len());
+    let data = if Path::new(destination).extension().is_some_and(|x| x == "stats") {
+        if stats.cosmetics.iter().any(|x| x.config_fields.is_some()) {
+            return Err("config_fields can't be compiled here; import to JSON and use statbin --schema".to_owned());
+        }
+        let binary = StatsFile::from_json(stats, registry);
+        binary.check_ranges(registry).iter().for_each(|x| println!("Warning: {}", x));
+        let data = if keep_names { binary.compile_with_names() } else { binary.compile() };
+        data.map_err(|e| format!("Unable to compile statfile: {}", e))?
+    } else {
+        serde_json::to_vec_pretty(&stats).map_err(|e| format!("Unable to serialize: {}", e))?
+    };
+    std::fs::write(destination, data).map_err(|e| format!("Unable to write {}: {}", destination, e))?;
+    Ok(format!("Imported {} to {}", counts, destination))
+}
+
+fn tool_impl(mut args: Vec<String>) {
+    let registry = match super::take_flags_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(r) => r
+    };
+    let keep_names = args.iter().any(|x| x == "--keep-names");
+    args.retain(|x| x != "--keep-names");
+    let result = match args.iter().map(|x| x.as_str()).collect::<Vec<_>>().as_slice() {
+        ["export", source, dir] => export(source, dir, &registry),
+        ["import", dir, destination] => import(dir, destination, keep_names, &registry),
+        _ => Err("statcsv needs 'export statfile dir' or 'import dir output'".to_owned())
+    };
+    match result {
+        Err(e) => println!("{}", e),
+        Ok(msg) => println!("{}", msg)
+    }
+}
@@ -0,0 +1,142 @@
+use procelio_files::files::stats::diff::{EntityChange, FlagChange, StatsDiff};
+use procelio_files::files::stats::flags::FlagRegistry;
+use procelio_files::files::stats::statfile::{JsonStatsFile, StatsFile, STATFILE_MAGIC_NUMBER};
+use std::convert::TryFrom;
+
+pub struct StatDiffTool {
+
+}
+
+impl super::ProcelioCLITool for StatDiffTool {
+    fn command(&self) -> &'static str {
+        "statdiff"
+    }
+
+    fn usage(&self) {
+        println!("path/to/old path/to/new [--format markdown|csv|json] [--min-abs N] [--min-pct P] [--flags path/to/flags.json]");
+        println!("    compares two statfiles (binary .stats or statfile JSON) flag by flag");
+        println!("    lists added/removed blocks, attacks and cosmetics and every changed flag with its");
+        println!("    absolute and percentage change; markdown is the default");
+        println!("    --min-abs / --min-pct hide changes smaller than N (absolute) or P percent");
+        println!("    names come from statfile JSON or version 5 binaries (statbin --keep-names); version 4 has none");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+fn load(path: &str, registry: &FlagRegistry) -> Result<StatsFile, String> {
+    let data = std::fs::read(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    if data.get(0..4) == Some(&STATFILE_MAGIC_NUMBER.to_be_bytes()) {
+        StatsFile::try_from(data.as_slice()).map_err(|e| format!("Unable to load statfile {}: {}", path, e))
+    } else {
+        serde_json::from_slice(&data).map_err(std::io::Error::from)
+            .and_then(|x| JsonStatsFile::resolve(x, registry)).map(|x| StatsFile::from_json(x, registry)).map_err(|e| format!("Unable to load statfile JSON {}: {}", path, e))
+    }
+}
+
+fn value(v: Option<i32>) -> String {
+    v.map(|x| x.to_string()).unwrap_or_else(|| "-".to_owned())
+}
+
+fn delta(c: &FlagChange) -> String {
+    c.delta().map(|x| format!("{x:+}")).unwrap_or_default()
+}
+
+fn percent(c: &FlagChange) -> String {
+    c.percent().map(|x| format!("{x:+.1}%")).unwrap_or_default()
+}
+
+fn section_title(section: &str) -> String {
+    let mut title = section[..1].to_uppercase();
+    title.push_str(&section[1..]);
+    title + "s"
+}
+
+fn markdown(diff: &StatsDiff) {
+    for section in ["block", "attack", "cosmetic"] {
+        let pick = |list: &[EntityChange]| list.iter().filter(|x| x.section == section).map(|x| format!("{} {}", x.id, x.name).trim().to_owned()).collect::<Vec<_>>();
+        let (added, removed, configs) = (pick(&diff.added), pick(&diff.removed), pick(&diff.configs_changed));
+        let changed: Vec<&FlagChange> = diff.changed.iter().filter(|x| x.section == section).collect();
+        if added.is_empty() && removed.is_empty() && configs.is_empty() && changed.is_empty() {
+            continue;
+        }
+        println!("## {}\n", section_title(section));
+        if !changed.is_empty() {
+            println!("| id | name | flag | old | new | change | % |");
+            println!("|---:|---|---|---:|---:|---:|---:|");
+            for c in changed.iter() {
+                println!("| {} | {} | {} | {} | {} | {} | {} |", c.id, c.name, c.flag, value(c.old), value(c.new), delta(c), percent(c));
+            }
+            println!();
+        }
+        for (label, list) in [("Added", added), ("Removed", removed), ("Config changed", configs)] {
+            if !list.is_empty() {
+                println!("{}: {}\n", label, list.join(", "));
+            }
+        }
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) { format!("\"{}\"", text.replace('"', "\"\"")) } else { text.to_owned() }
+}
+
+fn csv(diff: &StatsDiff) {
+    println!("section,id,name,change,flag,old,new,delta,percent");
+    for c in diff.changed.iter() {
+        let kind = match (c.old, c.new) { (None, _) => "flag added", (_, None) => "flag removed", _ => "changed" };
+        println!("{},{},{},{},{},{},{},{},{}", c.section, c.id, csv_field(&c.name), kind, csv_field(&c.flag),
+            c.old.map(|x| x.to_string()).unwrap_or_default(), c.new.map(|x| x.to_string()).unwrap_or_default(),
+            c.delta().map(|x| x.to_string()).unwrap_or_default(), c.percent().map(|x| format!("{x:.2}")).unwrap_or_default());
+    }
+    for (kind, list) in [("added", &diff.added), ("removed", &diff.removed), ("config changed", &diff.configs_changed)] {
+        for e in list.iter() {
+            println!("{},{},{},{},,,,,", e.section, e.id, csv_field(&e.name), kind);
+        }
+    }
+}
+
+fn tool_impl(mut args: Vec<String>) {
+    let registry = match super::take_flags_arg(&mut args) {
+        Err(e) => { println!("{}", e); return; },
+        Ok(r) => r
+    };
+    let mut format = "markdown".to_owned();
+    let mut min_abs = 0i64;
+    let mut min_pct = 0f64;
+    let mut paths = Vec::new();
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        let ok = match arg.as_str() {
+            "--format" => iter.next().map(|x| format = x).is_some(),
+            "--min-abs" => iter.next().and_then(|x| x.parse().ok()).map(|x| min_abs = x).is_some(),
+            "--min-pct" => iter.next().and_then(|x| x.parse().ok()).map(|x| min_pct = x).is_some(),
+            _ => { paths.push(arg.clone()); true }
+        };
+        if !ok {
+            println!("{} needs a value", arg);
+            return;
+        }
+    }
+    if paths.len() != 2 {
+        println!("statdiff needs exactly two statfiles");
+        return;
+    }
+    let (old, new) = match (load(&paths[0], &registry), load(&paths[1], &registry)) {
+        (Err(e), _) | (_, Err(e)) => { println!("{}", e); return; },
+        (Ok(o), Ok(n)) => (o, n)
+    };
+    let mut diff = old.diff(&new, &registry);
+    diff.filter(min_abs, min_pct);
+    match format.as_str() {
+        "markdown" | "md" => markdown(&diff),
+        "csv" => csv(&diff),
+        "json" => match serde_json::to_string_pretty(&diff) {
+            Err(e) => println!("Unable to serialize: {}", e),
+            Ok(s) => println!("{}", s)
+        },
+        _ => println!("Unknown format '{}'; use markdown, csv or json", format)
+    }
+}
@@ -0,0 +1,105 @@
+use procelio_files::files::stats::flags::FlagRegistry;
+use procelio_files::files::stats::lint::{LintRules, Severity};
+use procelio_files::files::stats::statfile::{JsonStatsFile, StatsFile, STATFILE_MAGIC_NUMBER};
+use procelio_files::files::stats::template::resolve_templates;
+use std::convert::TryFrom;
+
+pub struct StatLintTool {
+
+}
+
+impl super::ProcelioCLITool for StatLintTool {
+    fn command(&self) -> &'static str {
+        "statlint"
+    }
+
+    fn usage(&self) {
+        println!("path/to/statfile [--rules path/to/rules.json] [--flags path/to/flags.json] [--strict] [--json]");
+        println!("    checks a statfile (JSON, templates allowed, or binary .stats) for duplicate ids, unknown flags,");
+        println!("    values outside flag ranges, missing required flags, rule bounds and cosmetics without config");
+        println!("    --rules replaces the built-in rules key by key (see docs/statlint_rules.md)");
+        println!("    exits with status 1 on errors (or on warnings too with --strict), 2 if the files can't be read");
+        println!("--print-rules");
+        println!("    prints the built-in rules as a starting point for a rules file");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        std::process::exit(tool_impl(args))
+    }
+}
+
+fn load(path: &str, registry: &FlagRegistry) -> Result<serde_json::Value, String> {
+    let data = std::fs::read(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    if data.get(0..4) == Some(&STATFILE_MAGIC_NUMBER.to_be_bytes()) {
+        StatsFile::try_from(data.as_slice()).map_err(|e| e.to_string())
+            .and_then(|x| serde_json::to_value(JsonStatsFile::from_stats(&x, registry)).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Unable to load statfile {}: {}", path, e))
+    } else {
+        serde_json::from_slice(&data).map_err(std::io::Error::from)
+            .and_then(|x| resolve_templates(x, registry))
+            .map_err(|e| format!("Unable to load statfile JSON {}: {}", path, e))
+    }
+}
+
+fn tool_impl(mut args: Vec<String>) -> i32 {
+    let registry = match super::take_flags_arg(&mut args) {
+        Err(e) => { println!("{}", e); return 2; },
+        Ok(r) => r
+    };
+    let mut rules = LintRules::default();
+    let mut strict = false;
+    let mut json = false;
+    let mut paths = Vec::new();
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--strict" => strict = true,
+            "--json" => json = true,
+            "--print-rules" => {
+                match serde_json::to_string_pretty(&rules) {
+                    Err(e) => println!("Unable to serialize: {}", e),
+                    Ok(s) => println!("{}", s)
+                }
+                return 0;
+            },
+            "--rules" => {
+                let Some(path) = iter.next() else {
+                    println!("--rules needs a path");
+                    return 2;
+                };
+                rules = match std::fs::read(&path).map_err(|e| e.to_string())
+                    .and_then(|x| serde_json::from_slice::<LintRules>(&x).map_err(|e| e.to_string())) {
+                    Err(e) => { println!("Unable to read rules {}: {}", path, e); return 2; },
+                    Ok(r) => r
+                };
+            },
+            _ => paths.push(arg)
+        }
+    }
+    if let Err(e) = rules.validate(&registry) {
+        println!("Invalid rules: {}", e);
+        return 2;
+    }
+    if paths.len() != 1 {
+        println!("statlint needs one statfile");
+        return 2;
+    }
+    let doc = match load(&paths[0], &registry) {
+        Err(e) => { println!("{}", e); return 2; },
+        Ok(d) => d
+    };
+
+    let issues = rules.lint(&doc, &registry);
+    let errors = issues.iter().filter(|x| x.severity == Severity::Error).count();
+    let warnings = issues.len() - errors;
+    if json {
+        match serde_json::to_string_pretty(&issues) {
+            Err(e) => println!("Unable to serialize: {}", e),
+            Ok(s) => println!("{}", s)
+        }
+    } else {
+        issues.iter().for_each(|x| println!("{}", x));
+        println!("{}: {} errors, {} warnings", paths[0], errors, warnings);
+    }
+    if errors > 0 || strict && warnings > 0 { 1 } else { 0 }
+}
@@ -0,0 +1,70 @@
+use procelio_files::files::robot::Robot;
+use std::convert::TryFrom;
+
+pub struct VoxTool {
+
+}
+
+impl super::ProcelioCLITool for VoxTool {
+    fn command(&self) -> &'static str {
+        "vox"
+    }
+
+    fn usage(&self) {
+        println!("path/to/file [path/to/out] [--part ID]");
+        println!("    converts a binary robot to a MagicaVoxel .vox model, or a .vox model to a binary robot");
+        println!("    direction is chosen by the input extension; output defaults to the input path with the other extension");
+        println!("    --part sets the block type every imported voxel becomes (default 2, NormalCube)");
+    }
+
+    fn tool(&self, args: Vec<String>) {
+        tool_impl(args)
+    }
+}
+
+fn tool_impl(args: Vec<String>) {
+    let mut part_id = 2;
+    let mut paths = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--part" {
+            match args.next().and_then(|x| x.parse().ok()) {
+                Some(id) => part_id = id,
+                None => { println!("--part requires a numeric block type"); return; }
+            }
+        } else {
+            paths.push(arg);
+        }
+    }
+    let Some(source) = paths.first() else {
+        println!("No input file given");
+        return;
+    };
+    let source = std::path::Path::new(source);
+    let importing = source.extension().is_some_and(|x| x.eq_ignore_ascii_case("vox"));
+    let destination = match paths.get(1) {
+        None => source.with_extension(if importing { "robot" } else { "vox" }),
+        Some(e) => std::path::PathBuf::from(e)
+    };
+
+    let file_contents = match std::fs::read(source) {
+        Err(e) => { println!("Could not read {}: {}", source.display(), e); return; },
+        Ok(f) => f
+    };
+
+    let res = if importing {
+        Robot::from_vox(&file_contents, part_id).and_then(|bot| bot.compile())
+    } else {
+        Robot::try_from(file_contents.as_slice()).and_then(|bot| bot.to_vox())
+    };
+    match res {
+        Err(e) => { println!("Unable to convert {}: {}", source.display(), e); },
+        Ok(data) => {
+            if let Err(e) = std::fs::write(&destination, data) {
+                println!("Unable to save {}: {}", destination.display(), e);
+                return;
+            }
+            println!("Converted {} to {}", source.display(), destination.display());
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::io::{Cursor, Read, Write};
+use crate::files::robot::{JsonRobot, Robot};
+pub const GARAGE_MAGIC_NUMBER: u32 = 0x6A4A6E55;
+const CURRENT_VERSION: u32 = 1;
+
+// A set of robots in one file: prefab packs and full garage backups
+#[derive(Clone, Default)]
+pub struct Garage {
+    pub entries: Vec<GarageEntry>
+}
+
+#[derive(Clone)]
+pub struct GarageEntry {
+    pub slot: u16,
+    pub display_name: String,
+    pub tags: Vec<String>,
+    pub created: i64, // UTC MILLIS; NO LEAP SECONDS
+    pub robot: Robot
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JsonGarage {
+    pub version: u32,
+    pub entries: Vec<JsonGarageEntry>
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JsonGarageEntry {
+    pub slot: u16,
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created: i64,
+    pub robot: JsonRobot
+}
+
+fn read_string(file: &mut Cursor<&[u8]>, len: usize) -> Result<String, std::io::Error> {
+    let mut buf = vec![0u8; len];
+    file.read_exact(&mut buf)?;
+    String::from_utf8(buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+fn write_string(file: &mut Cursor<Vec<u8>>, text: &str, max: usize, what: &str) -> Result<(), std::io::Error> {
+    if text.len() > max {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{what} is longer than {max} bytes: {text}")));
+    }
+    if max > u8::MAX as usize {
+        file.write_all(&u16::to_be_bytes(text.len() as u16))?;
+    } else {
+        file.write_all(&u8::to_be_bytes(text.len() as u8))?;
+    }
+    file.write_all(text.as_bytes())
+}
+
+impl TryFrom<&[u8]> for Garage {
+    type Error = std::io::Error;
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let mut file = Cursor::new(data);
+        let mut buf4 = [0u8; 4];
+        file.read_exact(&mut buf4)?;
+        let magic = u32::from_be_bytes(buf4);
+        if magic != GARAGE_MAGIC_NUMBER {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Magic number was invalid: {magic}"),
+            ));
+        }
+
+        file.read_exact(&mut buf4)?;
+        let version = u32::from_be_bytes(buf4);
+        let garage = match version {
+            1 => Garage::from_v1(&mut file),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Version was invalid: {version}"),
+            )),
+        }?;
+        garage.validate()?;
+        Ok(garage)
+    }
+}
+
+impl TryFrom<JsonGarage> for Garage {
+    type Error = std::io::Error;
+    fn try_from(file: JsonGarage) -> Result<Self, Self::Error> {
+        if file.version > CURRENT_VERSION {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("JSON garage version was invalid: {}", file.version)));
+        }
+        let mut entries = Vec::new();
+        for entry in file.entries.into_iter() {
+            entries.push(GarageEntry {
+                slot: entry.slot,
+                display_name: entry.name,
+                tags: entry.tags,
+                created: entry.created,
+                robot: Robot::try_from(entry.robot)?
+            });
+        }
+        let garage = Garage { entries };
+        garage.validate()?;
+        Ok(garage)
+    }
+}
+
+impl From<Garage> for JsonGarage {
+    fn from(garage: Garage) -> Self {
+        JsonGarage {
+            version: CURRENT_VERSION,
+            entries: garage.entries.into_iter().map(|x| JsonGarageEntry {
+                slot: x.slot,
+                name: x.display_name,
+                tags: x.tags,
+                created: x.created,
+                robot: JsonRobot::from(x.robot)
+            }).collect()
+        }
+    }
+}
+
+impl Garage {
+    fn from_v1(file: &mut Cursor<&[u8]>) -> Result<Garage, std::io::Error> {
+        let mut buf1 = [0u8; 1];
+        let mut buf2 = [0u8; 2];
+        let mut buf4 = [0u8; 4];
+        let mut buf8 = [0u8; 8];
+
+        file.read_exact(&mut buf4)?;
+        let num_entries = u32::from_be_bytes(buf4);
+        let mut entries = Vec::new();
+        for _ in 0..num_entries {
+            file.read_exact(&mut buf2)?;
+            let slot = u16::from_be_bytes(buf2);
+            file.read_exact(&mut buf2)?;
+            let display_name = read_string(file, u16::from_be_bytes(buf2) as usize)?;
+            file.read_exact(&mut buf1)?;
+            let mut tags = Vec::new();
+            for _ in 0..buf1[0] {
+                let mut len = [0u8; 1];
+                file.read_exact(&mut len)?;
+                tags.push(read_string(file, len[0] as usize)?);
+            }
+            file.read_exact(&mut buf8)?;
+            let created = i64::from_be_bytes(buf8);
+            file.read_exact(&mut buf4)?;
+            let robot_len = u32::from_be_bytes(buf4) as u64;
+            if robot_len > file.get_ref().len() as u64 - file.position() {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Robot in slot {slot} is longer than the file: {robot_len}")));
+            }
+            let mut robot = vec![0u8; robot_len as usize];
+            file.read_exact(&mut robot)?;
+            let robot = Robot::try_from(robot.as_slice())
+                .map_err(|e| std::io::Error::new(e.kind(), format!("Robot in slot {slot}: {e}")))?;
+            entries.push(GarageEntry { slot, display_name, tags, created, robot });
+        }
+        Ok(Garage { entries })
+    }
+
+    // Slots must be unique; entries are kept in file order, not slot order
+    pub fn validate(self: &Garage) -> Result<(), std::io::Error> {
+        let mut seen = HashSet::new();
+        for entry in self.entries.iter() {
+            if !seen.insert(entry.slot) {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Garage slot {} is used twice", entry.slot)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(self: &Garage, slot: u16) -> Option<&GarageEntry> {
+        self.entries.iter().find(|x| x.slot == slot)
+    }
+
+    // Lowest slot not taken yet
+    pub fn free_slot(self: &Garage) -> Option<u16> {
+        (0..=u16::MAX).find(|s| self.get(*s).is_none())
+    }
+
+    pub fn compile(self: &Garage) -> Result<Vec<u8>, std::io::Error> {
+        self.validate()?;
+        let mut file = Cursor::new(Vec::new());
+        file.write_all(&u32::to_be_bytes(GARAGE_MAGIC_NUMBER))?;
+        file.write_all(&u32::to_be_bytes(CURRENT_VERSION))?;
+        file.write_all(&u32::to_be_bytes(self.entries.len() as u32))?;
+        for entry in self.entries.iter() {
+            file.write_all(&u16::to_be_bytes(entry.slot))?;
+            write_string(&mut file, &entry.display_name, u16::MAX as usize, "Display name")?;
+            if entry.tags.len() > u8::MAX as usize {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Slot {} has more than 255 tags", entry.slot)));
+            }
+            file.write_all(&u8::to_be_bytes(entry.tags.len() as u8))?;
+            for tag in entry.tags.iter() {
+                write_string(&mut file, tag, u8::MAX as usize, "Tag")?;
+            }
+            file.write_all(&i64::to_be_bytes(entry.created))?;
+            let robot = entry.robot.compile()?;
+            file.write_all(&u32::to_be_bytes(robot.len() as u32))?;
+            file.write_all(&robot)?;
+        }
+        Ok(file.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Garage {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/robotfile.json")).unwrap();
+        let robot = Robot::try_from(serde_json::from_slice::<JsonRobot>(&data).unwrap()).unwrap();
+        Garage { entries: vec!(
+            GarageEntry { slot: 3, display_name: "first".to_owned(), tags: vec!("prefab".to_owned()), created: 1_600_000_000_000, robot: robot.clone() },
+            GarageEntry { slot: 0, display_name: "second".to_owned(), tags: Vec::new(), created: -1, robot }
+        ) }
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let binary = sample().compile().unwrap();
+        let back = Garage::try_from(binary.as_slice()).unwrap();
+        assert_eq!(back.entries.len(), 2);
+        assert_eq!(back.entries[0].tags, vec!("prefab".to_owned()));
+        assert_eq!(back.compile().unwrap(), binary);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let binary = sample().compile().unwrap();
+        let json = serde_json::to_string(&JsonGarage::from(sample())).unwrap();
+        let back = Garage::try_from(serde_json::from_str::<JsonGarage>(&json).unwrap()).unwrap();
+        assert_eq!(back.compile().unwrap(), binary);
+    }
+
+    #[test]
+    fn oversized_robot_length_is_rejected() {
+        let mut binary = sample().compile().unwrap();
+        // the first robot's length follows magic, version, count, slot, name, tags and created
+        let at = 12 + 2 + 2 + "first".len() + 1 + 1 + "prefab".len() + 8;
+        binary[at..at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        let err = Garage::try_from(binary.as_slice()).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,3 @@
+mod garage;
+
+pub use garage::*;
\ No newline at end of file
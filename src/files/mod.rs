@@ -1,6 +1,9 @@
-pub mod stats;
-pub mod launcher;
-pub mod inventory;
-pub mod robot;
-pub mod localization;
-pub mod tech;
\ No newline at end of file
+pub mod stats;
+pub mod launcher;
+pub mod inventory;
+pub mod robot;
+pub mod localization;
+pub mod tech;
+pub mod schema;
+pub mod garage;
+pub mod remap;
\ No newline at end of file
@@ -0,0 +1,3 @@
+mod remap;
+
+pub use remap::*;
\ No newline at end of file
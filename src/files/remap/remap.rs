@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::files::inventory::Inventory;
+use crate::files::robot::Robot;
+use crate::files::tech::{AwardItem, TechTree};
+
+// What to do with every copy of one retired or renumbered part id. Remaps are applied all at once,
+// so {12 -> 13, 13 -> 14} moves 12 to 13 and 13 to 14 rather than chaining 12 to 14
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PartRemap {
+    pub from: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<u32>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub rotation: HashMap<u8, u8>, // old rotation -> new; unlisted rotations are kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 3]>, // repaint remapped parts
+    #[serde(default)]
+    pub delete: bool,
+    #[serde(default)]
+    pub refund: i64 // currency owed per deleted part
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct RemapTable {
+    pub parts: Vec<PartRemap>
+}
+
+// Per file: how many parts (or inventory/award counts) each entry touched, and the refund owed
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct RemapReport {
+    pub remapped: BTreeMap<u32, i64>,
+    pub deleted: BTreeMap<u32, i64>,
+    pub refund: i64
+}
+
+impl RemapReport {
+    pub fn is_empty(&self) -> bool {
+        self.remapped.is_empty() && self.deleted.is_empty()
+    }
+
+    fn record(&mut self, remap: &PartRemap, count: i64) {
+        if count == 0 {
+            return;
+        }
+        if remap.delete {
+            *self.deleted.entry(remap.from).or_default() += count;
+            self.refund += remap.refund * count;
+        } else {
+            *self.remapped.entry(remap.from).or_default() += count;
+        }
+    }
+}
+
+impl RemapTable {
+    // Every id is remapped at most once, and each entry either moves, repaints/rotates or deletes
+    pub fn validate(self: &RemapTable) -> Result<(), std::io::Error> {
+        let invalid = |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, msg);
+        let mut seen = HashSet::new();
+        for remap in self.parts.iter() {
+            if !seen.insert(remap.from) {
+                return Err(invalid(format!("Part {} is remapped twice", remap.from)));
+            }
+            if remap.delete && (remap.to.is_some() || remap.color.is_some() || !remap.rotation.is_empty()) {
+                return Err(invalid(format!("Part {} is deleted, so it can't also be remapped", remap.from)));
+            }
+            if !remap.delete && remap.refund != 0 {
+                return Err(invalid(format!("Part {} has a refund but isn't deleted", remap.from)));
+            }
+            if !remap.delete && remap.to.is_none() && remap.color.is_none() && remap.rotation.is_empty() {
+                return Err(invalid(format!("Part {} has nothing to change", remap.from)));
+            }
+        }
+        Ok(())
+    }
+
+    fn get(self: &RemapTable, id: u32) -> Option<&PartRemap> {
+        self.parts.iter().find(|x| x.from == id)
+    }
+
+    pub fn apply_robot(self: &RemapTable, bot: &mut Robot) -> RemapReport {
+        let mut report = RemapReport::default();
+        let mut counts: HashMap<u32, i64> = HashMap::new();
+        bot.parts.retain_mut(|part| {
+            let Some(remap) = self.get(part.id) else { return true; };
+            *counts.entry(part.id).or_default() += 1;
+            if remap.delete {
+                return false;
+            }
+            part.id = remap.to.unwrap_or(part.id);
+            if let Some(r) = remap.rotation.get(&part.rotation) {
+                part.rotation = *r;
+            }
+            if let Some(c) = remap.color {
+                part.set_rgb(c);
+            }
+            true
+        });
+        for remap in self.parts.iter() {
+            report.record(remap, counts.get(&remap.from).copied().unwrap_or(0));
+        }
+        report
+    }
+
+    // Remapped counts are merged into the new id; deleted counts are refunded
+    pub fn apply_inventory(self: &RemapTable, inv: &mut Inventory) -> RemapReport {
+        let mut report = RemapReport::default();
+        let mut moved = Vec::new();
+        for remap in self.parts.iter() {
+            let Some(count) = inv.parts.remove(&remap.from) else { continue; };
+            report.record(remap, i64::from(count));
+            if !remap.delete {
+                moved.push((remap.to.unwrap_or(remap.from), count));
+            }
+        }
+        for (id, count) in moved {
+            inv.add_part(id, count);
+        }
+        report
+    }
+
+    // Unlocks and awards follow the remap; deleted ones are dropped (nothing is owed for them)
+    pub fn apply_tech(self: &RemapTable, tree: &mut TechTree) -> RemapReport {
+        let mut report = RemapReport::default();
+        for node in tree.nodes.iter_mut() {
+            let reward = &mut node.reward;
+            let mut unlocks = Vec::new();
+            for id in reward.part_unlock.drain(..) {
+                match self.get(id) {
+                    None => unlocks.push(id),
+                    Some(remap) if remap.delete => *report.deleted.entry(id).or_default() += 1,
+                    Some(remap) => {
+                        *report.remapped.entry(id).or_default() += 1;
+                        unlocks.push(remap.to.unwrap_or(id));
+                    }
+                }
+            }
+            // two old ids can land on the same new one
+            let mut seen = HashSet::new();
+            unlocks.retain(|x| seen.insert(*x));
+            reward.part_unlock = unlocks;
+
+            let mut awards: Vec<AwardItem> = Vec::new();
+            for award in reward.part_award.drain(..) {
+                let item = match self.get(award.item) {
+                    None => award.item,
+                    Some(remap) if remap.delete => {
+                        *report.deleted.entry(award.item).or_default() += i64::from(award.count);
+                        continue;
+                    },
+                    Some(remap) => {
+                        *report.remapped.entry(award.item).or_default() += i64::from(award.count);
+                        remap.to.unwrap_or(award.item)
+                    }
+                };
+                match awards.iter_mut().find(|x| x.item == item) {
+                    Some(existing) => existing.count += award.count,
+                    None => awards.push(AwardItem { item, count: award.count })
+                }
+            }
+            reward.part_award = awards;
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::robot::Part;
+
+    fn table() -> RemapTable {
+        let table: RemapTable = serde_json::from_str(r#"{ "parts": [
+            { "from": 12, "to": 13, "rotation": { "0": 4 } },
+            { "from": 13, "to": 14 },
+            { "from": 20, "color": [1, 2, 3] },
+            { "from": 30, "delete": true, "refund": 50 }
+        ] }"#).unwrap();
+        table.validate().unwrap();
+        table
+    }
+
+    fn part(id: u32, x: i8) -> Part {
+        Part { id, pos_x: x, pos_y: 0, pos_z: 0, rotation: 0, color_r: 9, color_g: 9, color_b: 9, alpha_channel: 255, extra_bytes: Vec::new() }
+    }
+
+    #[test]
+    fn robots_are_remapped_all_at_once() {
+        let mut bot = Robot::new();
+        bot.parts = vec!(part(12, 0), part(13, 1), part(20, 2), part(30, 3), part(30, 4), part(1, 5));
+        let report = table().apply_robot(&mut bot);
+        let ids: Vec<u32> = bot.parts.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec!(13, 14, 20, 1));
+        assert_eq!(bot.parts[0].rotation, 4);
+        assert_eq!(bot.parts[1].rotation, 0);
+        assert_eq!((bot.parts[2].color_r, bot.parts[2].color_g, bot.parts[2].color_b), (1, 2, 3));
+        assert_eq!(report.remapped, BTreeMap::from([(12, 1), (13, 1), (20, 1)]));
+        assert_eq!(report.deleted, BTreeMap::from([(30, 2)]));
+        assert_eq!(report.refund, 100);
+    }
+
+    #[test]
+    fn inventories_merge_and_refund() {
+        let mut inv = Inventory::new();
+        inv.add_part(12, 2);
+        inv.add_part(13, 5);
+        inv.add_part(14, 1);
+        inv.add_part(30, 3);
+        let report = table().apply_inventory(&mut inv);
+        assert_eq!(inv.parts.get(&12), None);
+        assert_eq!(inv.parts.get(&13), Some(&2));
+        assert_eq!(inv.parts.get(&14), Some(&6));
+        assert_eq!(inv.parts.get(&30), None);
+        assert_eq!(report.refund, 150);
+    }
+
+    #[test]
+    fn tech_rewards_follow_the_remap() {
+        let mut tree: TechTree = serde_json::from_str(r#"{ "nodes": [ { "id": 1, "name": "a", "cost": 0, "prerequisite_tech": [],
+            "reward": { "part_unlock": [12, 14, 30], "part_award": [ { "item": 13, "count": 2 }, { "item": 14, "count": 1 }, { "item": 30, "count": 4 } ] } } ] }"#).unwrap();
+        let report = table().apply_tech(&mut tree);
+        let reward = &tree.nodes[0].reward;
+        assert_eq!(reward.part_unlock, vec!(13, 14));
+        assert_eq!(reward.part_award.iter().map(|x| (x.item, x.count)).collect::<Vec<_>>(), vec!((14, 3)));
+        assert_eq!(report.deleted, BTreeMap::from([(30, 5)]));
+        assert_eq!(report.refund, 0);
+    }
+
+    #[test]
+    fn conflicting_entries_are_rejected() {
+        for bad in [
+            r#"{ "parts": [ { "from": 1, "to": 2 }, { "from": 1, "to": 3 } ] }"#,
+            r#"{ "parts": [ { "from": 1, "to": 2, "delete": true } ] }"#,
+            r#"{ "parts": [ { "from": 1, "to": 2, "refund": 5 } ] }"#,
+            r#"{ "parts": [ { "from": 1 } ] }"#
+        ] {
+            assert!(serde_json::from_str::<RemapTable>(bad).unwrap().validate().is_err(), "{bad}");
+        }
+    }
+}
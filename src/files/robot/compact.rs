@@ -0,0 +1,393 @@
+use std::io::{Cursor, Read, Write};
+use md5::{Md5, Digest};
+use super::{Robot, Part, Cosmetic, ROBOT_MAGIC_NUMBER, MAX_EXTRADATA_SIZE};
+
+// Version 5 is a compact layout for uploads; see docs/robotfile_v5.md.
+// compile() still writes v4, which is what the game reads
+pub const COMPACT_VERSION: u32 = 5;
+const FLAG_ZSTD: u8 = 1;
+const FLAG_ORDER: u8 = 2;
+const ZSTD_LEVEL: i32 = 19;
+// Caps that keep a hostile file from asking for unbounded memory; far above anything buildable
+pub const MAX_COMPACT_PARTS: usize = 1 << 16;
+pub const MAX_COMPACT_COSMETICS: usize = 1 << 16;
+// Largest body those caps allow: name and rewires, then per part a run, varint id, rotation, palette
+// colour and index, extra data and order index, and per cosmetic two varints and extra data
+const MAX_VARINT: usize = 10;
+const MAX_BODY_SIZE: usize = 8 + 1 + 255 + 4 * MAX_VARINT + 1 + 2 * 255 + 16
+    + MAX_COMPACT_PARTS * (4 * MAX_VARINT + MAX_VARINT + 1 + 4 + MAX_VARINT + 1 + MAX_EXTRADATA_SIZE as usize + MAX_VARINT)
+    + MAX_COMPACT_COSMETICS * (2 * MAX_VARINT + 1 + MAX_EXTRADATA_SIZE as usize);
+
+fn invalid(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(file: &mut Cursor<&[u8]>) -> Result<u64, std::io::Error> {
+    let mut buf1 = [0u8; 1];
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        file.read_exact(&mut buf1)?;
+        value |= u64::from(buf1[0] & 0x7F) << shift;
+        if buf1[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("Varint was too long".to_owned()))
+}
+
+fn read_varint_u32(file: &mut Cursor<&[u8]>, what: &str) -> Result<u32, std::io::Error> {
+    let value = read_varint(file)?;
+    u32::try_from(value).map_err(|_| invalid(format!("{what} out of range: {value}")))
+}
+
+fn zigzag(value: i32) -> u64 {
+    ((value << 1) ^ (value >> 31)) as u32 as u64
+}
+
+fn unzigzag(value: u64) -> i32 {
+    ((value >> 1) as i32) ^ -((value & 1) as i32)
+}
+
+// Next run start from the previous run's last cell; the start must be a cell a part can occupy
+fn read_run_axis(file: &mut Cursor<&[u8]>, prev: i32, axis: &str) -> Result<i32, std::io::Error> {
+    let delta = read_varint(file)?;
+    let value = u32::try_from(delta).ok().and_then(|x| prev.checked_add(unzigzag(x.into())));
+    match value {
+        Some(v) if i8::try_from(v).is_ok() => Ok(v),
+        _ => Err(invalid(format!("Position run {axis} delta out of range: {delta}")))
+    }
+}
+
+fn write_extra(out: &mut Vec<u8>, extra: &[u8]) -> Result<(), std::io::Error> {
+    if extra.len() > MAX_EXTRADATA_SIZE.into() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Extra data region can only be 64 bytes long"));
+    }
+    out.push(extra.len() as u8);
+    out.extend_from_slice(extra);
+    Ok(())
+}
+
+fn read_extra(file: &mut Cursor<&[u8]>, what: &str) -> Result<Vec<u8>, std::io::Error> {
+    let mut buf1 = [0u8; 1];
+    file.read_exact(&mut buf1)?;
+    if buf1[0] > MAX_EXTRADATA_SIZE {
+        return Err(invalid(format!("{what}: Extra data region can only be 64 bytes long (was {})", buf1[0])));
+    }
+    let mut bytes = vec!(0u8; buf1[0].into());
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn part_color(part: &Part) -> [u8; 4] {
+    [part.color_r, part.color_g, part.color_b, part.alpha_channel]
+}
+
+impl Robot {
+    // Writes the v5 layout: palette colours, varint ids, and positions as runs along X in (y, z, x) order.
+    // Parts not already in that order get an order table so reading back gives the same robot
+    pub fn compile_v5(self: &Robot, compress: bool) -> Result<Vec<u8>, std::io::Error> {
+        if self.parts.len() > MAX_COMPACT_PARTS || self.cosmetics.len() > MAX_COMPACT_COSMETICS {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                format!("Version 5 robots can have at most {MAX_COMPACT_PARTS} parts and {MAX_COMPACT_COSMETICS} cosmetics")));
+        }
+        let mut body = Vec::new();
+        body.extend_from_slice(&u64::to_be_bytes(self.metadata));
+        if self.bot_name.len() > u8::MAX.into() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Bot name can only be 255 bytes long"));
+        }
+        body.push(self.bot_name.len() as u8);
+        body.extend_from_slice(&self.bot_name);
+
+        // palette, most used colour first so it gets the one-byte index
+        let mut counts: std::collections::HashMap<[u8; 4], usize> = std::collections::HashMap::new();
+        for part in self.parts.iter() {
+            *counts.entry(part_color(part)).or_default() += 1;
+        }
+        let mut palette: Vec<([u8; 4], usize)> = counts.into_iter().collect();
+        palette.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let palette_index: std::collections::HashMap<[u8; 4], usize> = palette.iter().enumerate().map(|(i, x)| (x.0, i)).collect();
+        write_varint(&mut body, palette.len() as u64);
+        for (color, _) in palette.iter() {
+            body.extend_from_slice(color);
+        }
+
+        let mut order: Vec<usize> = (0..self.parts.len()).collect();
+        order.sort_by_key(|&i| { let p = &self.parts[i]; (p.pos_y, p.pos_z, p.pos_x) });
+        let sorted: Vec<&Part> = order.iter().map(|&i| &self.parts[i]).collect();
+
+        write_varint(&mut body, sorted.len() as u64);
+        let mut runs: Vec<((i32, i32, i32), usize)> = Vec::new();
+        let mut last: Option<(i32, i32, i32)> = None;
+        for part in sorted.iter() {
+            let pos = (part.pos_x.into(), part.pos_y.into(), part.pos_z.into());
+            match (last, runs.last_mut()) {
+                (Some(l), Some(run)) if pos == (l.0 + 1, l.1, l.2) => run.1 += 1,
+                _ => runs.push((pos, 1))
+            }
+            last = Some(pos);
+        }
+        write_varint(&mut body, runs.len() as u64);
+        let mut prev = (0, 0, 0);
+        for (start, len) in runs.iter() {
+            write_varint(&mut body, zigzag(start.0 - prev.0));
+            write_varint(&mut body, zigzag(start.1 - prev.1));
+            write_varint(&mut body, zigzag(start.2 - prev.2));
+            write_varint(&mut body, *len as u64);
+            prev = (start.0 + *len as i32 - 1, start.1, start.2);
+        }
+        for part in sorted.iter() {
+            write_varint(&mut body, part.id.into());
+            body.push(part.rotation);
+            write_varint(&mut body, palette_index[&part_color(part)] as u64);
+            write_extra(&mut body, &part.extra_bytes)?;
+        }
+        let reordered = order.iter().enumerate().any(|(i, &x)| i != x);
+        if reordered {
+            for &i in order.iter() {
+                write_varint(&mut body, i as u64);
+            }
+        }
+
+        write_varint(&mut body, self.cosmetics.len() as u64);
+        for cosm in self.cosmetics.iter() {
+            write_varint(&mut body, cosm.id.into());
+            write_varint(&mut body, cosm.on_part.into());
+            write_extra(&mut body, &cosm.extra_bytes)?;
+        }
+        if self.input_rewire.len() > u8::MAX.into() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Input rewiring can only have 255 entries"));
+        }
+        body.push(self.input_rewire.len() as u8);
+        for elem in self.input_rewire.iter() {
+            body.push(elem.0);
+            body.push(elem.1);
+        }
+        let mut md5hash = Md5::new();
+        md5hash.update(&body);
+        body.extend_from_slice(&md5hash.finalize());
+
+        let mut flags = 0u8;
+        if reordered {
+            flags |= FLAG_ORDER;
+        }
+        if compress {
+            flags |= FLAG_ZSTD;
+            body = zstd::encode_all(body.as_slice(), ZSTD_LEVEL)?;
+        }
+        let mut file = Cursor::new(Vec::new());
+        file.write_all(&u32::to_be_bytes(ROBOT_MAGIC_NUMBER))?;
+        file.write_all(&u32::to_be_bytes(COMPACT_VERSION))?;
+        file.write_all(&[flags])?;
+        file.write_all(&body)?;
+        Ok(file.into_inner())
+    }
+
+    // Writes the bot in the layout `original` (a robot file) was read from: v5 stays v5, compressed
+    // if it was; older versions are upgraded to v4. For tools that edit robots in place
+    pub fn compile_like(self: &Robot, original: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        let version = original.get(4..8).map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]])).unwrap_or(0);
+        if version == COMPACT_VERSION {
+            self.compile_v5(original.get(8).is_some_and(|x| x & FLAG_ZSTD != 0))
+        } else {
+            self.compile()
+        }
+    }
+
+    pub(super) fn from_v5(bot: &mut Robot, file: &mut Cursor<&[u8]>) -> Result<(), std::io::Error> {
+        let mut buf1 = [0u8; 1];
+        file.read_exact(&mut buf1)?;
+        let flags = buf1[0];
+        if flags & !(FLAG_ZSTD | FLAG_ORDER) != 0 {
+            return Err(invalid(format!("Unknown robot flags: {flags:#x}")));
+        }
+        let mut body = Vec::new();
+        if flags & FLAG_ZSTD != 0 {
+            zstd::stream::Decoder::new(file)?.take(MAX_BODY_SIZE as u64 + 1).read_to_end(&mut body)?;
+        } else {
+            file.read_to_end(&mut body)?;
+        }
+        if body.len() > MAX_BODY_SIZE {
+            return Err(invalid(format!("Robot body is larger than {MAX_BODY_SIZE} bytes")));
+        }
+        if body.len() < 16 {
+            return Err(invalid("Robot body was too short".to_owned()));
+        }
+        let (content, hash) = body.split_at(body.len() - 16);
+        let mut md5hash = Md5::new();
+        md5hash.update(content);
+        let res = md5hash.finalize();
+        if res[..] != hash[..] {
+            return Err(invalid("Bot hash did not match".to_owned()));
+        }
+        bot.hash = Some(res.to_vec());
+
+        let mut file = Cursor::new(content);
+        let mut buf8 = [0u8; 8];
+        file.read_exact(&mut buf8)?;
+        bot.metadata = u64::from_be_bytes(buf8);
+        file.read_exact(&mut buf1)?;
+        let mut bufname = vec!(0u8; buf1[0].into());
+        file.read_exact(&mut bufname)?;
+        bot.bot_name = bufname;
+
+        let palette_len = read_varint(&mut file)?;
+        let mut palette = Vec::new();
+        for _ in 0..palette_len {
+            let mut color = [0u8; 4];
+            file.read_exact(&mut color)?;
+            palette.push(color);
+        }
+
+        let num_parts = read_varint(&mut file)? as usize;
+        if num_parts > content.len() || num_parts > MAX_COMPACT_PARTS {
+            return Err(invalid(format!("Part count was invalid: {num_parts}")));
+        }
+        let num_runs = read_varint(&mut file)?;
+        let mut positions = Vec::new();
+        let mut prev = (0i32, 0i32, 0i32);
+        for _ in 0..num_runs {
+            let start = (
+                read_run_axis(&mut file, prev.0, "x")?,
+                read_run_axis(&mut file, prev.1, "y")?,
+                read_run_axis(&mut file, prev.2, "z")?);
+            let len = read_varint(&mut file)?;
+            let len = match usize::try_from(len) {
+                Ok(n) if n > 0 && n <= num_parts - positions.len() => n,
+                _ => return Err(invalid(format!("Position run was invalid: {len}")))
+            };
+            for i in 0..len as i32 {
+                let pos = (start.0 + i, start.1, start.2);
+                let cell = (i8::try_from(pos.0), i8::try_from(pos.1), i8::try_from(pos.2));
+                let (Ok(x), Ok(y), Ok(z)) = cell else {
+                    return Err(invalid(format!("Part position out of range: {pos:?}")));
+                };
+                positions.push((x, y, z));
+            }
+            prev = (start.0 + len as i32 - 1, start.1, start.2);
+        }
+        if positions.len() != num_parts {
+            return Err(invalid(format!("Position runs covered {} of {} parts", positions.len(), num_parts)));
+        }
+
+        let mut sorted = Vec::new();
+        for (i, pos) in positions.into_iter().enumerate() {
+            let id = read_varint_u32(&mut file, "Part id")?;
+            file.read_exact(&mut buf1)?;
+            let rotation = buf1[0];
+            let color_idx = read_varint(&mut file)? as usize;
+            let color = *palette.get(color_idx).ok_or_else(|| invalid(format!("Block {i}: palette index out of range: {color_idx}")))?;
+            let extra_bytes = read_extra(&mut file, &format!("Block {i}"))?;
+            sorted.push(Part {
+                id, pos_x: pos.0, pos_y: pos.1, pos_z: pos.2, rotation,
+                color_r: color[0], color_g: color[1], color_b: color[2], alpha_channel: color[3], extra_bytes
+            });
+        }
+        if flags & FLAG_ORDER != 0 {
+            let mut slots: Vec<Option<Part>> = vec!(None; num_parts);
+            for part in sorted.into_iter() {
+                let idx = read_varint(&mut file)? as usize;
+                match slots.get_mut(idx) {
+                    Some(slot @ None) => *slot = Some(part),
+                    _ => return Err(invalid(format!("Part order table was invalid at {idx}")))
+                }
+            }
+            bot.parts = slots.into_iter().flatten().collect();
+        } else {
+            bot.parts = sorted;
+        }
+
+        let num_cosmetics = read_varint(&mut file)?;
+        if num_cosmetics > MAX_COMPACT_COSMETICS as u64 {
+            return Err(invalid(format!("Cosmetic count was invalid: {num_cosmetics}")));
+        }
+        for i in 0..num_cosmetics {
+            let id = read_varint_u32(&mut file, "Cosmetic id")?;
+            let on_part = read_varint_u32(&mut file, "Cosmetic part")?;
+            let extra_bytes = read_extra(&mut file, &format!("Cosmetic {i}"))?;
+            bot.cosmetics.push(Cosmetic { id, on_part, extra_bytes });
+        }
+
+        file.read_exact(&mut buf1)?;
+        for _ in 0..buf1[0] {
+            let mut pair = [0u8; 2];
+            file.read_exact(&mut pair)?;
+            bot.input_rewire.push((pair[0], pair[1]));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v5_file(flags: u8, body: &[u8]) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend_from_slice(&u32::to_be_bytes(ROBOT_MAGIC_NUMBER));
+        file.extend_from_slice(&u32::to_be_bytes(COMPACT_VERSION));
+        file.push(flags);
+        file.extend_from_slice(body);
+        file
+    }
+
+    fn hashed(mut body: Vec<u8>) -> Vec<u8> {
+        let mut md5hash = Md5::new();
+        md5hash.update(&body);
+        body.extend_from_slice(&md5hash.finalize());
+        body
+    }
+
+    #[test]
+    fn v4_v5_round_trip() {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/robotfile.json")).unwrap();
+        let mut bot = Robot::try_from(serde_json::from_slice::<crate::files::robot::JsonRobot>(&data).unwrap()).unwrap();
+        bot.cosmetics.push(Cosmetic { id: 432, on_part: 2, extra_bytes: vec!(9, 8) });
+        bot.input_rewire = vec!((6, 7), (7, 6));
+        let v4 = bot.compile().unwrap();
+        for compress in [false, true] {
+            let v5 = Robot::try_from(v4.as_slice()).unwrap().compile_v5(compress).unwrap();
+            let back = Robot::try_from(v5.as_slice()).unwrap();
+            assert_eq!(back.compile().unwrap(), v4);
+            assert_eq!(back.compile_like(&v5).unwrap(), v5);
+        }
+    }
+
+    #[test]
+    fn run_delta_overflow_is_an_error() {
+        let mut body = Vec::new();
+        body.extend_from_slice(&0u64.to_be_bytes());
+        body.push(0);
+        write_varint(&mut body, 1);
+        body.extend_from_slice(&[0, 0, 0, 255]);
+        write_varint(&mut body, 2);
+        write_varint(&mut body, 2);
+        for delta in [zigzag(1), 0, 0, 1, 0xFFFF_FFFE, 0, 0, 1] {
+            write_varint(&mut body, delta);
+        }
+        for _ in 0..2 {
+            write_varint(&mut body, 1);
+            body.extend_from_slice(&[0, 0, 0]);
+        }
+        write_varint(&mut body, 0);
+        body.push(0);
+        let file = v5_file(0, &hashed(body));
+        let err = Robot::try_from(file.as_slice()).err().unwrap();
+        assert!(err.to_string().contains("delta out of range"));
+    }
+
+    #[test]
+    fn oversized_zstd_body_is_an_error() {
+        let body = zstd::encode_all(vec!(0u8; MAX_BODY_SIZE + 1).as_slice(), 3).unwrap();
+        let file = v5_file(FLAG_ZSTD, &body);
+        let err = Robot::try_from(file.as_slice()).err().unwrap();
+        assert!(err.to_string().contains("larger than"));
+    }
+}
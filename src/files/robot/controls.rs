@@ -0,0 +1,292 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use std::collections::BTreeMap;
+use std::fmt;
+use super::Robot;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Control(pub u8);
+
+impl Control {
+    // Accepts a bare number or "inputN"
+    pub fn parse(name: &str) -> Option<Control> {
+        name.strip_prefix("input").unwrap_or(name).parse().ok().map(Control)
+    }
+}
+
+impl fmt::Display for Control {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Names for input ids, loaded from a JSON object like {"6": "fire1", "7": "fire2"}.
+// The game's input enum isn't published, so there are no built-in names; without a file controls print as raw ids
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ControlNames {
+    pub names: BTreeMap<u8, String>
+}
+
+impl ControlNames {
+    pub fn load(path: &std::path::Path) -> Result<ControlNames, std::io::Error> {
+        let data = std::fs::read(path)?;
+        serde_json::from_slice(&data).map_err(std::io::Error::from)
+    }
+
+    // "6 (fire1)" when the id has a name, otherwise just "6"
+    pub fn label(&self, control: Control) -> String {
+        match self.names.get(&control.0) {
+            Some(n) => format!("{} ({})", control, n),
+            None => control.to_string()
+        }
+    }
+
+    // The id with this name, if any
+    pub fn id_of(&self, name: &str) -> Option<Control> {
+        self.names.iter().find(|x| x.1 == name).map(|x| Control(*x.0))
+    }
+
+    // Without names every id is accepted
+    pub fn is_known(&self, control: Control) -> bool {
+        self.names.is_empty() || self.names.contains_key(&control.0)
+    }
+}
+
+// Typed view of Robot::input_rewire: pressing `input` triggers `action`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ControlBindings {
+    pub bindings: Vec<(Control, Control)> // (input, action), in file order
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BindingProblem {
+    Duplicate { input: String }, // bound more than once; the game only uses one
+    OutOfRange { control: u8 }, // only checked against a names file
+    Unreachable { action: String }, // its own input is rebound and nothing else triggers it
+    Redundant { input: String } // bound to itself
+}
+
+impl fmt::Display for BindingProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingProblem::Duplicate { input } => write!(f, "{} is bound more than once", input),
+            BindingProblem::OutOfRange { control } => write!(f, "control {} is not in the control names", control),
+            BindingProblem::Unreachable { action } => write!(f, "{} can no longer be triggered", action),
+            BindingProblem::Redundant { input } => write!(f, "{} is bound to itself", input)
+        }
+    }
+}
+
+impl ControlBindings {
+    pub fn from_pairs(pairs: &[(u8, u8)]) -> ControlBindings {
+        ControlBindings { bindings: pairs.iter().map(|x| (Control(x.0), Control(x.1))).collect() }
+    }
+
+    pub fn to_pairs(&self) -> Vec<(u8, u8)> {
+        self.bindings.iter().map(|x| (x.0.0, x.1.0)).collect()
+    }
+
+    // What pressing `input` does; unbound inputs do their own action
+    pub fn action_for(&self, input: Control) -> Control {
+        self.bindings.iter().find(|x| x.0 == input).map(|x| x.1).unwrap_or(input)
+    }
+
+    // Every input that triggers `action`
+    pub fn inputs_for(&self, action: Control) -> Vec<Control> {
+        let mut inputs: Vec<Control> = self.bindings.iter().filter(|x| x.1 == action).map(|x| x.0).collect();
+        if !self.bindings.iter().any(|x| x.0 == action) {
+            inputs.push(action);
+        }
+        inputs.sort();
+        inputs.dedup();
+        inputs
+    }
+
+    pub fn problems(&self, names: &ControlNames) -> Vec<BindingProblem> {
+        let mut problems = Vec::new();
+        let mut counts: BTreeMap<Control, usize> = BTreeMap::new();
+        for (input, action) in self.bindings.iter() {
+            *counts.entry(*input).or_default() += 1;
+            for c in [input, action] {
+                if !names.is_known(*c) && !problems.contains(&BindingProblem::OutOfRange { control: c.0 }) {
+                    problems.push(BindingProblem::OutOfRange { control: c.0 });
+                }
+            }
+            if input == action {
+                problems.push(BindingProblem::Redundant { input: names.label(*input) });
+            }
+        }
+        problems.extend(counts.iter().filter(|x| *x.1 > 1).map(|x| BindingProblem::Duplicate { input: names.label(*x.0) }));
+        let mut rebound: Vec<Control> = self.bindings.iter().filter(|x| x.0 != x.1).map(|x| x.0).collect();
+        rebound.sort();
+        rebound.dedup();
+        for action in rebound {
+            if self.inputs_for(action).is_empty() {
+                problems.push(BindingProblem::Unreachable { action: names.label(action) });
+            }
+        }
+        problems
+    }
+}
+
+impl Robot {
+    pub fn controls(self: &Robot) -> ControlBindings {
+        ControlBindings::from_pairs(&self.input_rewire)
+    }
+}
+
+// A rewire id as written in JSON: a number, or a name ("inputN", or one from a ControlNames file)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JsonControl {
+    Id(u8),
+    Name(String)
+}
+
+impl JsonControl {
+    fn resolve(&self, names: &ControlNames) -> Result<Control, std::io::Error> {
+        match self {
+            JsonControl::Id(i) => Ok(Control(*i)),
+            JsonControl::Name(n) => names.id_of(n).or_else(|| Control::parse(n)).ok_or_else(|| std::io::Error::new(
+                std::io::ErrorKind::InvalidInput, format!("Unknown control '{n}'; named controls need their --controls file")))
+        }
+    }
+
+    fn key(&self) -> String {
+        match self {
+            JsonControl::Id(i) => i.to_string(),
+            JsonControl::Name(n) => n.clone()
+        }
+    }
+}
+
+// JSON form of the rewires, in file order. Plain ids are written as [[6, 7], ...] pairs; with names
+// it is a {"fire1": "fire2"} map, or [["fire1", "fire2"], ...] pairs if an input is bound twice.
+// {"6": 7} maps and "inputN" ids (written by earlier builds) are also read
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JsonBindings {
+    pub bindings: Vec<(JsonControl, JsonControl)>
+}
+
+impl JsonBindings {
+    // Ids with a name are written as that name
+    pub fn named(bindings: &ControlBindings, names: &ControlNames) -> JsonBindings {
+        let name = |c: &Control| names.names.get(&c.0).map(|n| JsonControl::Name(n.clone())).unwrap_or(JsonControl::Id(c.0));
+        JsonBindings { bindings: bindings.bindings.iter().map(|(i, a)| (name(i), name(a))).collect() }
+    }
+
+    pub fn resolve(&self, names: &ControlNames) -> Result<ControlBindings, std::io::Error> {
+        let bindings = self.bindings.iter()
+            .map(|(i, a)| Ok((i.resolve(names)?, a.resolve(names)?)))
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        Ok(ControlBindings { bindings })
+    }
+}
+
+impl From<&ControlBindings> for JsonBindings {
+    fn from(bindings: &ControlBindings) -> Self {
+        JsonBindings::named(bindings, &ControlNames::default())
+    }
+}
+
+impl Serialize for JsonBindings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let named = self.bindings.iter().any(|(i, a)| matches!(i, JsonControl::Name(_)) || matches!(a, JsonControl::Name(_)));
+        let mut keys: Vec<String> = self.bindings.iter().map(|x| x.0.key()).collect();
+        keys.sort();
+        keys.dedup();
+        if named && keys.len() == self.bindings.len() {
+            let mut map = serializer.serialize_map(Some(self.bindings.len()))?;
+            for (input, action) in self.bindings.iter() {
+                map.serialize_entry(&input.key(), action)?;
+            }
+            return map.end();
+        }
+        let mut seq = serializer.serialize_seq(Some(self.bindings.len()))?;
+        for pair in self.bindings.iter() {
+            seq.serialize_element(pair)?;
+        }
+        seq.end()
+    }
+}
+
+struct BindingsVisitor;
+
+impl<'de> Visitor<'de> for BindingsVisitor {
+    type Value = JsonBindings;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of input to action, or a list of [input, action] pairs")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut bindings = Vec::new();
+        while let Some((input, action)) = access.next_entry::<String, JsonControl>()? {
+            bindings.push((JsonControl::Name(input), action));
+        }
+        Ok(JsonBindings { bindings })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut bindings = Vec::new();
+        while let Some(pair) = access.next_element::<(JsonControl, JsonControl)>()? {
+            bindings.push(pair);
+        }
+        Ok(JsonBindings { bindings })
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonBindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(BindingsVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> ControlNames {
+        ControlNames { names: [(6, "fire1".to_owned()), (7, "fire2".to_owned()), (8, "jump".to_owned())].into_iter().collect() }
+    }
+
+    #[test]
+    fn problems_are_found() {
+        let names = names();
+        let problems = ControlBindings::from_pairs(&[(6, 7), (6, 8), (9, 9)]).problems(&names);
+        assert!(problems.contains(&BindingProblem::Duplicate { input: "6 (fire1)".to_owned() }));
+        assert!(problems.contains(&BindingProblem::Unreachable { action: "6 (fire1)".to_owned() }));
+        assert!(problems.contains(&BindingProblem::OutOfRange { control: 9 }));
+        assert!(problems.contains(&BindingProblem::Redundant { input: "9".to_owned() }));
+        assert!(ControlBindings::from_pairs(&[(6, 7), (7, 6)]).problems(&names).is_empty());
+        assert!(ControlBindings::from_pairs(&[(9, 6)]).problems(&ControlNames::default()).iter()
+            .all(|x| !matches!(x, BindingProblem::OutOfRange { .. })));
+    }
+
+    #[test]
+    fn named_json_round_trip() {
+        let names = names();
+        let bindings = ControlBindings::from_pairs(&[(7, 6), (6, 9)]);
+        let json = serde_json::to_string(&JsonBindings::named(&bindings, &names)).unwrap();
+        assert_eq!(json, r#"{"fire2":"fire1","fire1":9}"#);
+        let back: JsonBindings = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.resolve(&names).unwrap(), bindings);
+        assert!(back.resolve(&ControlNames::default()).is_err());
+
+        let twice = ControlBindings::from_pairs(&[(6, 7), (6, 8)]);
+        let json = serde_json::to_string(&JsonBindings::named(&twice, &names)).unwrap();
+        assert_eq!(json, r#"[["fire1","fire2"],["fire1","jump"]]"#);
+        assert_eq!(serde_json::from_str::<JsonBindings>(&json).unwrap().resolve(&names).unwrap(), twice);
+    }
+
+    #[test]
+    fn plain_json_stays_pairs() {
+        let bindings = ControlBindings::from_pairs(&[(6, 7), (7, 6)]);
+        assert_eq!(serde_json::to_string(&JsonBindings::from(&bindings)).unwrap(), "[[6,7],[7,6]]");
+        let old: JsonBindings = serde_json::from_str(r#"{"input6": "input7", "7": 6}"#).unwrap();
+        assert_eq!(old.resolve(&ControlNames::default()).unwrap(), bindings);
+    }
+}
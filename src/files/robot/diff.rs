@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use super::{Robot, Part, Cosmetic, JsonName};
+
+// Cell coordinates used to match parts between two versions of a bot
+pub type PartPos = (i8, i8, i8);
+
+fn is_none<T>(elem: &Option<T>) -> bool {
+    elem.is_none()
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Change<T> {
+    pub from: T,
+    pub to: T
+}
+
+impl<T: PartialEq + Clone> Change<T> {
+    fn between(from: &T, to: &T) -> Option<Change<T>> {
+        if from == to { None } else { Some(Change { from: from.clone(), to: to.clone() }) }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct PartChange {
+    pub pos: [i8; 3],
+    #[serde(default, skip_serializing_if = "is_none")]
+    pub id: Option<Change<u32>>,
+    #[serde(default, skip_serializing_if = "is_none")]
+    pub rotation: Option<Change<u8>>,
+    #[serde(default, skip_serializing_if = "is_none")]
+    pub color: Option<Change<[u8; 4]>>,
+    #[serde(default, skip_serializing_if = "is_none")]
+    pub extra_bytes: Option<Change<Vec<u8>>>
+}
+
+// One rewire pair added to or removed from the ordered list
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RewireChange {
+    pub index: usize, // in the old list for removals, in the new list for additions
+    pub input: u8,
+    pub action: u8,
+    pub added: bool
+}
+
+// Everything that differs between two robots. Parts are matched by cell, cosmetics by (id, part);
+// where several share a cell or key they are paired up in file order
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct RobotDiff {
+    #[serde(default, skip_serializing_if = "is_none")]
+    pub name: Option<Change<JsonName>>, // compared as raw bytes
+    #[serde(default, skip_serializing_if = "is_none")]
+    pub metadata: Option<Change<u64>>,
+    pub added_parts: Vec<Part>,
+    pub removed_parts: Vec<Part>,
+    pub changed_parts: Vec<PartChange>,
+    pub added_cosmetics: Vec<Cosmetic>,
+    pub removed_cosmetics: Vec<Cosmetic>,
+    pub changed_cosmetics: Vec<Change<Cosmetic>>,
+    pub rewire: Vec<RewireChange>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub parts_reordered: bool, // same parts in a different file order
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub cosmetics_reordered: bool
+}
+
+// Every item under its key, in file order
+pub(super) fn grouped<T, K: Ord>(items: &[T], key: impl Fn(&T) -> K) -> BTreeMap<K, Vec<&T>> {
+    let mut map: BTreeMap<K, Vec<&T>> = BTreeMap::new();
+    for item in items.iter() {
+        map.entry(key(item)).or_default().push(item);
+    }
+    map
+}
+
+// Removals and additions that turn `old` into `new`, keeping the longest common subsequence
+fn rewire_changes(old: &[(u8, u8)], new: &[(u8, u8)]) -> Vec<RewireChange> {
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] { common[i + 1][j + 1] + 1 } else { common[i + 1][j].max(common[i][j + 1]) };
+        }
+    }
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            changes.push(RewireChange { index: i, input: old[i].0, action: old[i].1, added: false });
+            i += 1;
+        } else {
+            changes.push(RewireChange { index: j, input: new[j].0, action: new[j].1, added: true });
+            j += 1;
+        }
+    }
+    changes
+}
+
+impl PartChange {
+    pub fn between(from: &Part, to: &Part) -> Option<PartChange> {
+        let change = PartChange {
+            pos: [from.pos_x, from.pos_y, from.pos_z],
+            id: Change::between(&from.id, &to.id),
+            rotation: Change::between(&from.rotation, &to.rotation),
+            color: Change::between(&[from.color_r, from.color_g, from.color_b, from.alpha_channel], &[to.color_r, to.color_g, to.color_b, to.alpha_channel]),
+            extra_bytes: Change::between(&from.extra_bytes, &to.extra_bytes)
+        };
+        if change.id.is_none() && change.rotation.is_none() && change.color.is_none() && change.extra_bytes.is_none() {
+            None
+        } else {
+            Some(change)
+        }
+    }
+}
+
+impl RobotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.metadata.is_none()
+            && self.added_parts.is_empty() && self.removed_parts.is_empty() && self.changed_parts.is_empty()
+            && self.added_cosmetics.is_empty() && self.removed_cosmetics.is_empty() && self.changed_cosmetics.is_empty()
+            && self.rewire.is_empty() && !self.parts_reordered && !self.cosmetics_reordered
+    }
+}
+
+impl Robot {
+    // Structural comparison from self to `other`. Order only shows up as the reordered flags; the stored hash is ignored
+    pub fn diff(self: &Robot, other: &Robot) -> RobotDiff {
+        let mut diff = RobotDiff {
+            name: if self.bot_name == other.bot_name { None } else {
+                Some(Change { from: JsonName::from(self.bot_name.as_slice()), to: JsonName::from(other.bot_name.as_slice()) })
+            },
+            metadata: Change::between(&self.metadata, &other.metadata),
+            ..Default::default()
+        };
+
+        let before = grouped(&self.parts, |x| (x.pos_x, x.pos_y, x.pos_z));
+        let after = grouped(&other.parts, |x| (x.pos_x, x.pos_y, x.pos_z));
+        let cells: BTreeSet<&PartPos> = before.keys().chain(after.keys()).collect();
+        for cell in cells {
+            let (old, new) = (before.get(cell).map(Vec::as_slice).unwrap_or_default(), after.get(cell).map(Vec::as_slice).unwrap_or_default());
+            for i in 0..old.len().max(new.len()) {
+                match (old.get(i), new.get(i)) {
+                    (Some(o), Some(n)) => diff.changed_parts.extend(PartChange::between(o, n)),
+                    (Some(o), None) => diff.removed_parts.push((*o).clone()),
+                    (None, Some(n)) => diff.added_parts.push((*n).clone()),
+                    (None, None) => {}
+                }
+            }
+        }
+
+        let before = grouped(&self.cosmetics, |x| (x.id, x.on_part));
+        let after = grouped(&other.cosmetics, |x| (x.id, x.on_part));
+        let keys: BTreeSet<&(u32, u32)> = before.keys().chain(after.keys()).collect();
+        for key in keys {
+            let (old, new) = (before.get(key).map(Vec::as_slice).unwrap_or_default(), after.get(key).map(Vec::as_slice).unwrap_or_default());
+            for i in 0..old.len().max(new.len()) {
+                match (old.get(i), new.get(i)) {
+                    (Some(o), Some(n)) => diff.changed_cosmetics.extend(Change::between(*o, *n)),
+                    (Some(o), None) => diff.removed_cosmetics.push((*o).clone()),
+                    (None, Some(n)) => diff.added_cosmetics.push((*n).clone()),
+                    (None, None) => {}
+                }
+            }
+        }
+
+        diff.rewire = rewire_changes(&self.input_rewire, &other.input_rewire);
+        diff.parts_reordered = self.parts != other.parts
+            && diff.added_parts.is_empty() && diff.removed_parts.is_empty() && diff.changed_parts.is_empty();
+        diff.cosmetics_reordered = self.cosmetics != other.cosmetics
+            && diff.added_cosmetics.is_empty() && diff.removed_cosmetics.is_empty() && diff.changed_cosmetics.is_empty();
+        diff
+    }
+}
+
+fn fmt_color(c: &[u8; 4]) -> String {
+    format!("#{:02x}{:02x}{:02x}{:02x}", c[0], c[1], c[2], c[3])
+}
+
+impl fmt::Display for RobotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "robots are identical");
+        }
+        if let Some(c) = &self.name {
+            writeln!(f, "~ name: {} -> {}", c.from, c.to)?;
+        }
+        if let Some(c) = &self.metadata {
+            writeln!(f, "~ metadata: {:#x} -> {:#x}", c.from, c.to)?;
+        }
+        for p in self.removed_parts.iter() {
+            writeln!(f, "- part {} at ({}, {}, {})", p.id, p.pos_x, p.pos_y, p.pos_z)?;
+        }
+        for p in self.added_parts.iter() {
+            writeln!(f, "+ part {} at ({}, {}, {}) rot {} color {}", p.id, p.pos_x, p.pos_y, p.pos_z, p.rotation,
+                fmt_color(&[p.color_r, p.color_g, p.color_b, p.alpha_channel]))?;
+        }
+        for p in self.changed_parts.iter() {
+            write!(f, "~ part at ({}, {}, {}):", p.pos[0], p.pos[1], p.pos[2])?;
+            if let Some(c) = &p.id {
+                write!(f, " type {} -> {}", c.from, c.to)?;
+            }
+            if let Some(c) = &p.rotation {
+                write!(f, " rot {} -> {}", c.from, c.to)?;
+            }
+            if let Some(c) = &p.color {
+                write!(f, " color {} -> {}", fmt_color(&c.from), fmt_color(&c.to))?;
+            }
+            if let Some(c) = &p.extra_bytes {
+                write!(f, " extra {:?} -> {:?}", c.from, c.to)?;
+            }
+            writeln!(f)?;
+        }
+        for c in self.removed_cosmetics.iter() {
+            writeln!(f, "- cosmetic {} on {}", c.id, c.on_part)?;
+        }
+        for c in self.added_cosmetics.iter() {
+            writeln!(f, "+ cosmetic {} on {}", c.id, c.on_part)?;
+        }
+        for c in self.changed_cosmetics.iter() {
+            writeln!(f, "~ cosmetic {} on {}: extra {:?} -> {:?}", c.from.id, c.from.on_part, c.from.extra_bytes, c.to.extra_bytes)?;
+        }
+        for r in self.rewire.iter() {
+            writeln!(f, "{} rewire #{}: {} -> {}", if r.added { "+" } else { "-" }, r.index, r.input, r.action)?;
+        }
+        if self.parts_reordered {
+            writeln!(f, "~ part order changed")?;
+        }
+        if self.cosmetics_reordered {
+            writeln!(f, "~ cosmetic order changed")?;
+        }
+        writeln!(f, "{} added, {} removed, {} changed parts", self.added_parts.len(), self.removed_parts.len(), self.changed_parts.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(id: u32) -> Part {
+        Part { id, pos_x: 0, pos_y: 0, pos_z: 0, rotation: 0, color_r: 0, color_g: 0, color_b: 0, alpha_channel: 255, extra_bytes: Vec::new() }
+    }
+
+    #[test]
+    fn every_part_in_a_cell_is_compared() {
+        let mut old = Robot::new();
+        old.parts = vec!(part(1), part(2));
+        let mut new = old.clone();
+        new.parts[1].id = 3;
+        new.parts.push(part(4));
+        let diff = old.diff(&new);
+        assert_eq!(diff.changed_parts.len(), 1);
+        assert_eq!(diff.changed_parts[0].id, Some(Change { from: 2, to: 3 }));
+        assert_eq!(diff.added_parts.len(), 1);
+        assert_eq!(diff.added_parts[0].id, 4);
+    }
+
+    #[test]
+    fn names_are_compared_as_bytes() {
+        let mut old = Robot::new();
+        old.bot_name = b"bot\xfe".to_vec();
+        let mut new = old.clone();
+        new.bot_name = b"bot\xff".to_vec();
+        assert!(old.diff(&new).name.is_some());
+        assert!(old.diff(&old).name.is_none());
+    }
+
+    #[test]
+    fn rewires_are_diffed_as_a_list() {
+        let mut old = Robot::new();
+        old.input_rewire = vec!((6, 7), (6, 8), (9, 1));
+        let mut new = old.clone();
+        new.input_rewire = vec!((6, 8), (6, 7), (9, 1));
+        let diff = old.diff(&new);
+        assert_eq!(diff.rewire, vec!(
+            RewireChange { index: 0, input: 6, action: 7, added: false },
+            RewireChange { index: 1, input: 6, action: 7, added: true }
+        ));
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn reordering_is_not_identical() {
+        let mut old = Robot::new();
+        old.parts = vec!(part(1), part(2));
+        old.parts[1].pos_x = 1;
+        let mut new = old.clone();
+        new.parts.reverse();
+        let diff = old.diff(&new);
+        assert!(diff.parts_reordered && !diff.is_empty());
+        assert_ne!(old.compile().unwrap(), new.compile().unwrap());
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+use std::str::FromStr;
+use super::{Robot, Cosmetic};
+
+// One small in-place fix to a robot. Written one per line as:
+//   rename NAME            (the rest of the line; surrounding quotes are dropped)
+//   delete X Y Z           (every part at that cell)
+//   swap A B               (part id A becomes B)
+//   cosmetic ID PART_ON    (adds a cosmetic)
+//   metadata N             (raw value, decimal or 0x hex)
+#[derive(Clone, Debug, PartialEq)]
+pub enum RobotEdit {
+    Rename(String),
+    DeleteAt(i8, i8, i8),
+    SwapId(u32, u32),
+    AddCosmetic(u32, u32),
+    SetMetadata(u64)
+}
+
+fn invalid(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, msg)
+}
+
+fn number<T: FromStr>(word: Option<&str>, what: &str) -> Result<T, std::io::Error> {
+    word.and_then(|x| x.parse().ok()).ok_or_else(|| invalid(format!("expected {what}, found '{}'", word.unwrap_or(""))))
+}
+
+impl FromStr for RobotEdit {
+    type Err = std::io::Error;
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (op, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let mut words = rest.split_whitespace();
+        let edit = match op {
+            "rename" => {
+                let name = rest.strip_prefix('"').and_then(|x| x.strip_suffix('"')).unwrap_or(rest);
+                RobotEdit::Rename(name.to_owned())
+            },
+            "delete" => RobotEdit::DeleteAt(number(words.next(), "x")?, number(words.next(), "y")?, number(words.next(), "z")?),
+            "swap" => RobotEdit::SwapId(number(words.next(), "a part id")?, number(words.next(), "a part id")?),
+            "cosmetic" => RobotEdit::AddCosmetic(number(words.next(), "a cosmetic id")?, number(words.next(), "a part id")?),
+            "metadata" => {
+                let raw = match rest.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16).ok(),
+                    None => rest.parse().ok()
+                };
+                words.next();
+                RobotEdit::SetMetadata(raw.ok_or_else(|| invalid(format!("expected a number, found '{rest}'")))?)
+            },
+            _ => return Err(invalid(format!("unknown edit '{op}'")))
+        };
+        if words.next().is_some() && !matches!(edit, RobotEdit::Rename(_)) {
+            return Err(invalid(format!("too many values for '{op}'")));
+        }
+        match &edit {
+            RobotEdit::Rename(name) if name.len() > u8::MAX.into() => Err(invalid(format!("name is longer than 255 bytes: {name}"))),
+            _ => Ok(edit)
+        }
+    }
+}
+
+impl fmt::Display for RobotEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RobotEdit::Rename(name) => write!(f, "rename \"{name}\""),
+            RobotEdit::DeleteAt(x, y, z) => write!(f, "delete {x} {y} {z}"),
+            RobotEdit::SwapId(a, b) => write!(f, "swap {a} {b}"),
+            RobotEdit::AddCosmetic(id, on) => write!(f, "cosmetic {id} {on}"),
+            RobotEdit::SetMetadata(raw) => write!(f, "metadata {raw:#x}")
+        }
+    }
+}
+
+// Parses an edit file: one edit per line, blank lines and lines starting with # are skipped
+pub fn parse_edits(text: &str) -> Result<Vec<RobotEdit>, std::io::Error> {
+    text.lines().enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+        .map(|(i, l)| l.parse().map_err(|e: std::io::Error| invalid(format!("line {}: {}", i + 1, e))))
+        .collect()
+}
+
+impl Robot {
+    // Applies one edit and describes what it changed; input_rewire and everything else is untouched
+    pub fn apply_edit(self: &mut Robot, edit: &RobotEdit) -> Result<String, std::io::Error> {
+        Ok(match edit {
+            RobotEdit::Rename(name) => {
+                let old = String::from_utf8_lossy(&self.bot_name).into_owned();
+                self.bot_name = name.as_bytes().to_vec();
+                format!("renamed \"{old}\" to \"{name}\"")
+            },
+            RobotEdit::DeleteAt(x, y, z) => {
+                let before = self.parts.len();
+                self.parts.retain(|p| (p.pos_x, p.pos_y, p.pos_z) != (*x, *y, *z));
+                format!("deleted {} parts at ({x}, {y}, {z})", before - self.parts.len())
+            },
+            RobotEdit::SwapId(a, b) => {
+                let mut count = 0;
+                self.parts.iter_mut().filter(|p| p.id == *a).for_each(|p| { p.id = *b; count += 1; });
+                format!("swapped {count} parts from id {a} to {b}")
+            },
+            RobotEdit::AddCosmetic(id, on_part) => {
+                self.cosmetics.push(Cosmetic { id: *id, on_part: *on_part, extra_bytes: Vec::new() });
+                format!("added cosmetic {id} on {on_part}")
+            },
+            RobotEdit::SetMetadata(raw) => {
+                let old = self.metadata;
+                self.metadata = *raw;
+                format!("metadata {old:#x} -> {raw:#x}")
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_parse_and_print_back() {
+        let edits = parse_edits("# fixes\nrename \"Big Bot\"\n\ndelete -1 0 2\nswap 3 4\ncosmetic 432 2\nmetadata 0x1f\n").unwrap();
+        assert_eq!(edits, vec!(
+            RobotEdit::Rename("Big Bot".to_owned()),
+            RobotEdit::DeleteAt(-1, 0, 2),
+            RobotEdit::SwapId(3, 4),
+            RobotEdit::AddCosmetic(432, 2),
+            RobotEdit::SetMetadata(0x1f)
+        ));
+        for edit in edits.iter() {
+            assert_eq!(&edit.to_string().parse::<RobotEdit>().unwrap(), edit);
+        }
+        for bad in ["swap 3", "swap 3 4 5", "delete 0 0 200", "metadata soon", "paint 1"] {
+            assert!(bad.parse::<RobotEdit>().is_err(), "{bad}");
+        }
+        assert!(parse_edits("swap 1 2\nswap x 2").unwrap_err().to_string().starts_with("line 2"));
+    }
+
+    #[test]
+    fn edits_apply_in_place() {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/robotfile.json")).unwrap();
+        let mut bot = Robot::try_from(serde_json::from_slice::<crate::files::robot::JsonRobot>(&data).unwrap()).unwrap();
+        bot.input_rewire = vec!((6, 7));
+        let first = bot.parts[0].clone();
+        let same_id = bot.parts.iter().filter(|p| p.id == first.id).count();
+
+        assert_eq!(bot.apply_edit(&RobotEdit::SwapId(first.id, 9999)).unwrap(), format!("swapped {same_id} parts from id {} to 9999", first.id));
+        assert!(bot.parts.iter().all(|p| p.id != first.id));
+        let parts = bot.parts.len();
+        bot.apply_edit(&RobotEdit::DeleteAt(first.pos_x, first.pos_y, first.pos_z)).unwrap();
+        assert!(bot.parts.len() < parts);
+        bot.apply_edit(&RobotEdit::Rename("Edited".to_owned())).unwrap();
+        assert_eq!(bot.bot_name, b"Edited");
+        bot.apply_edit(&RobotEdit::SetMetadata(5)).unwrap();
+        assert_eq!(bot.metadata, 5);
+        assert_eq!(bot.input_rewire, vec!((6, 7)));
+    }
+}
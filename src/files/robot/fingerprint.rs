@@ -0,0 +1,149 @@
+use md5::{Md5, Digest};
+use std::collections::HashMap;
+use super::{Robot, Part};
+
+// Parts as compared by fingerprint/similarity: position (relative), id, rotation, colour (optional), extra data
+type CanonicalPart = ((i32, i32, i32), u32, u8, Option<[u8; 4]>, Vec<u8>);
+
+// How many of the most voted-for offsets similarity() tries when aligning two bots
+const ALIGNMENT_CANDIDATES: usize = 4;
+// Cap on same-id parts of the other bot each part votes with, to keep huge single-block bots fast
+const VOTES_PER_PART: usize = 64;
+
+fn canonical(part: &Part, offset: (i32, i32, i32), ignore_color: bool) -> CanonicalPart {
+    (
+        (i32::from(part.pos_x) - offset.0, i32::from(part.pos_y) - offset.1, i32::from(part.pos_z) - offset.2),
+        part.id,
+        part.rotation,
+        if ignore_color { None } else { Some([part.color_r, part.color_g, part.color_b, part.alpha_channel]) },
+        part.extra_bytes.clone()
+    )
+}
+
+fn min_corner(parts: &[Part]) -> (i32, i32, i32) {
+    parts.iter().fold(None, |acc: Option<(i32, i32, i32)>, p| {
+        let pos = (i32::from(p.pos_x), i32::from(p.pos_y), i32::from(p.pos_z));
+        Some(match acc {
+            None => pos,
+            Some(a) => (a.0.min(pos.0), a.1.min(pos.1), a.2.min(pos.2))
+        })
+    }).unwrap_or_default()
+}
+
+fn multiset(parts: &[Part], offset: (i32, i32, i32), ignore_color: bool) -> HashMap<CanonicalPart, usize> {
+    let mut set = HashMap::new();
+    for part in parts.iter() {
+        *set.entry(canonical(part, offset, ignore_color)).or_default() += 1;
+    }
+    set
+}
+
+fn jaccard(a: &HashMap<CanonicalPart, usize>, b: &HashMap<CanonicalPart, usize>, total: usize) -> f64 {
+    let shared: usize = a.iter().map(|(k, n)| (*n).min(b.get(k).copied().unwrap_or(0))).sum();
+    let union = total - shared;
+    if union == 0 { 1.0 } else { shared as f64 / union as f64 }
+}
+
+impl Robot {
+    // Hex MD5 of the parts only, sorted and shifted so the minimum corner is (0, 0, 0).
+    // Unaffected by part order, moving the whole bot, the name, metadata, cosmetics and rewiring
+    pub fn fingerprint(self: &Robot, ignore_color: bool) -> String {
+        let offset = min_corner(&self.parts);
+        let mut parts: Vec<CanonicalPart> = self.parts.iter().map(|x| canonical(x, offset, ignore_color)).collect();
+        parts.sort();
+        let mut md5hash = Md5::new();
+        md5hash.update([u8::from(ignore_color)]);
+        for (pos, id, rot, color, extra) in parts.iter() {
+            for p in [pos.0, pos.1, pos.2] {
+                md5hash.update((p as i16).to_be_bytes());
+            }
+            md5hash.update(id.to_be_bytes());
+            md5hash.update([*rot]);
+            if let Some(c) = color {
+                md5hash.update(c);
+            }
+            md5hash.update([extra.len() as u8]);
+            md5hash.update(extra);
+        }
+        hex::encode(md5hash.finalize())
+    }
+
+    // Share of parts the two bots have in common (same cell, id, rotation and, unless ignored, colour)
+    // after the best translation: 1.0 for identical builds, 0.0 for nothing in common
+    pub fn similarity(self: &Robot, other: &Robot, ignore_color: bool) -> f64 {
+        let total = self.parts.len() + other.parts.len();
+        if total == 0 {
+            return 1.0;
+        }
+        // candidate offsets: both min corners lined up, plus the shifts most same-id part pairs agree on
+        let (ma, mb) = (min_corner(&self.parts), min_corner(&other.parts));
+        let mut votes: HashMap<(i32, i32, i32), usize> = HashMap::new();
+        let mut by_id: HashMap<u32, Vec<&Part>> = HashMap::new();
+        for part in other.parts.iter() {
+            by_id.entry(part.id).or_default().push(part);
+        }
+        for a in self.parts.iter() {
+            for b in by_id.get(&a.id).map(|x| x.as_slice()).unwrap_or_default().iter().take(VOTES_PER_PART) {
+                let shift = (i32::from(b.pos_x) - i32::from(a.pos_x), i32::from(b.pos_y) - i32::from(a.pos_y), i32::from(b.pos_z) - i32::from(a.pos_z));
+                *votes.entry(shift).or_default() += 1;
+            }
+        }
+        let mut candidates: Vec<((i32, i32, i32), usize)> = votes.into_iter().collect();
+        candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let mut shifts: Vec<(i32, i32, i32)> = vec!((mb.0 - ma.0, mb.1 - ma.1, mb.2 - ma.2));
+        shifts.extend(candidates.into_iter().take(ALIGNMENT_CANDIDATES).map(|x| x.0));
+
+        let ours = multiset(&self.parts, (0, 0, 0), ignore_color);
+        shifts.into_iter()
+            .map(|shift| jaccard(&ours, &multiset(&other.parts, shift, ignore_color), total))
+            .fold(0.0, f64::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn robot() -> Robot {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/robotfile.json")).unwrap();
+        Robot::try_from(serde_json::from_slice::<crate::files::robot::JsonRobot>(&data).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn fingerprint_is_stable() {
+        let bot = robot();
+        // a change here invalidates fingerprints users have saved and every catalog index
+        assert_eq!(bot.fingerprint(false), "ffaa627b629b3031ec7f64a5922a753a");
+        assert_eq!(bot.fingerprint(true), "8ce4b401afe78edf10ac3b8fb7dec9e0");
+
+        let mut moved = bot.clone();
+        moved.parts.reverse();
+        for part in moved.parts.iter_mut() {
+            part.pos_x += 3;
+            part.pos_z -= 2;
+        }
+        moved.bot_name = b"Another name".to_vec();
+        moved.metadata = 1;
+        moved.input_rewire = vec!((1, 2));
+        assert_eq!(moved.fingerprint(false), bot.fingerprint(false));
+        assert_eq!(moved.similarity(&bot, false), 1.0);
+
+        let mut recolored = bot.clone();
+        recolored.parts[0].color_r ^= 0xff;
+        assert_ne!(recolored.fingerprint(false), bot.fingerprint(false));
+        assert_eq!(recolored.fingerprint(true), bot.fingerprint(true));
+        assert!(recolored.similarity(&bot, false) < 1.0);
+        assert_eq!(recolored.similarity(&bot, true), 1.0);
+    }
+
+    #[test]
+    fn similarity_counts_shared_parts() {
+        let bot = robot();
+        let mut other = bot.clone();
+        other.parts.push(Part { id: 99, pos_x: 20, pos_y: 0, pos_z: 0, rotation: 0, color_r: 0, color_g: 0, color_b: 0, alpha_channel: 255, extra_bytes: Vec::new() });
+        let shared = bot.parts.len() as f64;
+        assert_eq!(bot.similarity(&other, false), shared / (shared + 1.0));
+        assert_eq!(Robot::new().similarity(&bot, false), 0.0);
+        assert_eq!(Robot::new().similarity(&Robot::new(), false), 1.0);
+    }
+}
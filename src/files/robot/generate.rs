@@ -0,0 +1,178 @@
+use crate::files::stats::statfile::{StatsFile, COMPLEXITY_FLAG};
+use std::collections::HashSet;
+use super::{Robot, Part, Cosmetic, NO_ROTATION};
+
+pub const CORE_PART_ID: u32 = 1;
+
+// Directions a new part can be attached to an existing one
+const FACES: [(i8, i8, i8); 6] = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+// Placement attempts per part before the generator gives up growing the bot
+const ATTEMPTS_PER_PART: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct GeneratorConfig {
+    pub parts: usize, // target part count, core included
+    pub cpu_budget: Option<i64>, // cap on the summed cpuCost of every part
+    pub kit_size: usize, // how many distinct block types one bot is built from
+    pub colors: usize, // palette size
+    pub cosmetics: usize, // most cosmetics per bot
+    pub symmetric: bool // mirror parts across x = 0, like most hand-built bots
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig { parts: 60, cpu_budget: None, kit_size: 6, colors: 3, cosmetics: 1, symmetric: true }
+    }
+}
+
+// splitmix64: tiny, seedable and identical on every platform, so a seed always gives the same bot
+pub struct BotRng(u64);
+
+impl BotRng {
+    pub fn new(seed: u64) -> BotRng {
+        BotRng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in 0..n; n must be non-zero
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, from: &'a [T]) -> &'a T {
+        &from[self.below(from.len())]
+    }
+}
+
+// Parts are left unrotated until the rotation byte's encoding is settled
+fn part(id: u32, pos: (i8, i8, i8), color: [u8; 3]) -> Part {
+    Part {
+        id, pos_x: pos.0, pos_y: pos.1, pos_z: pos.2, rotation: NO_ROTATION,
+        color_r: color[0], color_g: color[1], color_b: color[2], alpha_channel: 255,
+        extra_bytes: Vec::new()
+    }
+}
+
+// Position mirrored across x = 0
+fn mirror(pos: (i8, i8, i8)) -> Option<(i8, i8, i8)> {
+    pos.0.checked_neg().map(|x| (x, pos.1, pos.2))
+}
+
+impl Robot {
+    // Builds a random bot from the blocks in `stats`: a core at the origin and parts grown face to face
+    // from it, so every part is connected. The same stats, config and seed always give the same bot.
+    // Parts are treated as single cells; the result may stop short of config.parts if the CPU budget runs out
+    pub fn generate(stats: &StatsFile, config: &GeneratorConfig, seed: u64) -> Result<Robot, std::io::Error> {
+        let cpu = |id: u32| stats.blocks.data.get(&id).and_then(|x| x.get(&COMPLEXITY_FLAG)).map(|x| i64::from(*x)).unwrap_or(0);
+        if !stats.blocks.data.contains_key(&CORE_PART_ID) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Statfile has no core block (id {CORE_PART_ID})")));
+        }
+        let mut budget = config.cpu_budget.unwrap_or(i64::MAX).saturating_sub(cpu(CORE_PART_ID));
+        if budget < 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "CPU budget can't fit the core"));
+        }
+        let mut rng = BotRng::new(seed);
+
+        let mut block_ids: Vec<u32> = stats.blocks.data.keys().copied().filter(|x| *x != CORE_PART_ID).collect();
+        block_ids.sort();
+        let mut kit = Vec::new();
+        while kit.len() < config.kit_size.max(1) && !block_ids.is_empty() {
+            kit.push(block_ids.swap_remove(rng.below(block_ids.len())));
+        }
+        // the first two kit entries make up the bulk of the frame, the rest are sprinkled in
+        let weights: Vec<usize> = (0..kit.len()).map(|i| if i < 2 { 6 } else { 1 }).collect();
+        let total_weight: usize = weights.iter().sum();
+
+        let palette: Vec<[u8; 3]> = (0..config.colors.max(1)).map(|_| {
+            let v = rng.next_u64().to_be_bytes();
+            [v[0], v[1], v[2]]
+        }).collect();
+
+        let mut bot = Robot::new();
+        bot.bot_name = format!("generated-{seed:016x}").into_bytes();
+        bot.parts.push(part(CORE_PART_ID, (0, 0, 0), palette[0]));
+        let mut occupied: HashSet<(i8, i8, i8)> = HashSet::from([(0, 0, 0)]);
+
+        let mut attempts = config.parts.saturating_mul(ATTEMPTS_PER_PART);
+        while bot.parts.len() < config.parts && !kit.is_empty() && attempts > 0 {
+            attempts -= 1;
+            let mut roll = rng.below(total_weight);
+            let slot = weights.iter().position(|w| if roll < *w { true } else { roll -= w; false }).unwrap_or(0);
+            let id = kit[slot];
+            let from = rng.pick(&bot.parts);
+            let face = rng.pick(&FACES);
+            let Some(pos) = from.pos_x.checked_add(face.0).zip(from.pos_y.checked_add(face.1)).zip(from.pos_z.checked_add(face.2))
+                .map(|((x, y), z)| (x, y, z)) else { continue; };
+            if occupied.contains(&pos) {
+                continue;
+            }
+            let twin = mirror(pos).filter(|m| config.symmetric && *m != pos && !occupied.contains(m));
+            let count = if twin.is_some() { 2 } else { 1 };
+            if config.symmetric && pos.0 != 0 && (twin.is_none() || bot.parts.len() + count > config.parts) {
+                continue; // keep the bot symmetric; the middle column fills up the odd part
+            }
+            if cpu(id) * count as i64 > budget {
+                continue;
+            }
+            budget -= cpu(id) * count as i64;
+            let color = *rng.pick(&palette);
+            for p in std::iter::once(pos).chain(twin) {
+                occupied.insert(p);
+                bot.parts.push(part(id, p, color));
+            }
+        }
+
+        let mut cosmetic_ids: Vec<u32> = stats.cosmetics.data.keys().copied().collect();
+        cosmetic_ids.sort();
+        let mut on_parts: Vec<u32> = bot.parts.iter().map(|x| x.id).collect();
+        on_parts.sort();
+        on_parts.dedup();
+        for _ in 0..rng.below(config.cosmetics.saturating_add(1)) {
+            if cosmetic_ids.is_empty() {
+                break;
+            }
+            let id = cosmetic_ids.swap_remove(rng.below(cosmetic_ids.len()));
+            bot.cosmetics.push(Cosmetic { id, on_part: *rng.pick(&on_parts), extra_bytes: Vec::new() });
+        }
+        Ok(bot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::stats::flags::FlagRegistry;
+
+    fn stats() -> StatsFile {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/statfile.json")).unwrap();
+        StatsFile::from_json(serde_json::from_slice(&data).unwrap(), &FlagRegistry::default())
+    }
+
+    #[test]
+    fn same_seed_same_robot() {
+        let (stats, config) = (stats(), GeneratorConfig::default());
+        let first = Robot::generate(&stats, &config, 42).unwrap().compile().unwrap();
+        assert_eq!(Robot::generate(&stats, &config, 42).unwrap().compile().unwrap(), first);
+        assert_ne!(Robot::generate(&stats, &config, 43).unwrap().compile().unwrap(), first);
+    }
+
+    #[test]
+    fn generated_bots_are_symmetric_and_within_budget() {
+        let stats = stats();
+        let config = GeneratorConfig { parts: 40, cpu_budget: Some(200), ..Default::default() };
+        let bot = Robot::generate(&stats, &config, 7).unwrap();
+        assert!(bot.parts.len() <= 40);
+        let cells: HashSet<(i8, i8, i8)> = bot.parts.iter().map(|x| (x.pos_x, x.pos_y, x.pos_z)).collect();
+        assert_eq!(cells.len(), bot.parts.len());
+        assert!(cells.iter().all(|x| cells.contains(&mirror(*x).unwrap())));
+        let cpu: i64 = bot.parts.iter().filter_map(|x| stats.blocks.data.get(&x.id)?.get(&COMPLEXITY_FLAG)).map(|x| i64::from(*x)).sum();
+        assert!(cpu <= 200);
+    }
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use super::{Robot, Part, Cosmetic, JsonName};
+use super::diff::grouped;
+
+// A cell (or cosmetic key, the rewire list, name...) that both sides changed differently.
+// The merged robot keeps "ours" for every conflict
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MergeConflict {
+    Name { base: JsonName, ours: JsonName, theirs: JsonName },
+    Metadata { base: u64, ours: u64, theirs: u64 },
+    Part { pos: [i8; 3], base: Vec<Part>, ours: Vec<Part>, theirs: Vec<Part> },
+    Cosmetic { id: u32, on_part: u32, base: Vec<Cosmetic>, ours: Vec<Cosmetic>, theirs: Vec<Cosmetic> },
+    Rewire { base: Vec<(u8, u8)>, ours: Vec<(u8, u8)>, theirs: Vec<(u8, u8)> }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MergeResult {
+    pub robot: Robot,
+    pub conflicts: Vec<MergeConflict>
+}
+
+// Standard three-way rule: take whichever side changed; Err if both changed it differently
+fn merge_value<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> Result<T, ()> {
+    if ours == theirs || theirs == base {
+        Ok(ours.clone())
+    } else if ours == base {
+        Ok(theirs.clone())
+    } else {
+        Err(())
+    }
+}
+
+// Everything under one key as a multiset: same items in any order compare equal
+fn normalized<T: Clone, K: Ord>(items: Option<&Vec<&T>>, key: impl Fn(&T) -> K) -> Vec<T> {
+    let mut items: Vec<T> = items.map(|x| x.iter().map(|i| (*i).clone()).collect()).unwrap_or_default();
+    items.sort_by_key(|x| key(x));
+    items
+}
+
+fn part_key(part: &Part) -> (u32, u8, [u8; 4], Vec<u8>) {
+    (part.id, part.rotation, [part.color_r, part.color_g, part.color_b, part.alpha_channel], part.extra_bytes.clone())
+}
+
+// base, ours, theirs
+type Sides<T> = (Vec<T>, Vec<T>, Vec<T>);
+
+// Merges one key's items; the chosen side's items are returned in that side's order
+fn merge_group<T: Clone + PartialEq, K: Ord>(base: Option<&Vec<&T>>, ours: Option<&Vec<&T>>, theirs: Option<&Vec<&T>>,
+        key: impl Fn(&T) -> K + Copy) -> Result<Vec<T>, Sides<T>> {
+    let (b, o, t) = (normalized(base, key), normalized(ours, key), normalized(theirs, key));
+    let in_order = |x: Option<&Vec<&T>>| x.map(|x| x.iter().map(|i| (*i).clone()).collect()).unwrap_or_default();
+    match merge_value(&b, &o, &t) {
+        Ok(v) if v == o => Ok(in_order(ours)),
+        Ok(_) => Ok(in_order(theirs)),
+        Err(_) => Err((in_order(base), in_order(ours), in_order(theirs)))
+    }
+}
+
+impl Robot {
+    // Merges the changes made in `ours` and `theirs` since `base`. Everything in a cell (parts) or
+    // under an (id, part) key (cosmetics) merges as one unit, and rewires merge as one ordered list,
+    // so nothing is merged unless it can be merged exactly. Part order follows ours, then cells new in theirs
+    pub fn merge3(base: &Robot, ours: &Robot, theirs: &Robot) -> MergeResult {
+        let mut conflicts = Vec::new();
+        let mut robot = Robot::new();
+
+        robot.bot_name = merge_value(&base.bot_name, &ours.bot_name, &theirs.bot_name).unwrap_or_else(|_| {
+            conflicts.push(MergeConflict::Name {
+                base: JsonName::from(base.bot_name.as_slice()),
+                ours: JsonName::from(ours.bot_name.as_slice()),
+                theirs: JsonName::from(theirs.bot_name.as_slice())
+            });
+            ours.bot_name.clone()
+        });
+        robot.metadata = merge_value(&base.metadata, &ours.metadata, &theirs.metadata).unwrap_or_else(|_| {
+            conflicts.push(MergeConflict::Metadata { base: base.metadata, ours: ours.metadata, theirs: theirs.metadata });
+            ours.metadata
+        });
+
+        let cell = |x: &Part| (x.pos_x, x.pos_y, x.pos_z);
+        let (base_parts, our_parts, their_parts) = (grouped(&base.parts, cell), grouped(&ours.parts, cell), grouped(&theirs.parts, cell));
+        let mut seen = HashSet::new();
+        for pos in ours.parts.iter().chain(theirs.parts.iter()).chain(base.parts.iter()).map(cell) {
+            if !seen.insert(pos) {
+                continue;
+            }
+            match merge_group(base_parts.get(&pos), our_parts.get(&pos), their_parts.get(&pos), part_key) {
+                Ok(v) => robot.parts.extend(v),
+                Err((base, ours, theirs)) => {
+                    robot.parts.extend(ours.iter().cloned());
+                    conflicts.push(MergeConflict::Part { pos: [pos.0, pos.1, pos.2], base, ours, theirs });
+                }
+            }
+        }
+
+        let key = |x: &Cosmetic| (x.id, x.on_part);
+        let (base_cosm, our_cosm, their_cosm) = (grouped(&base.cosmetics, key), grouped(&ours.cosmetics, key), grouped(&theirs.cosmetics, key));
+        let mut seen = HashSet::new();
+        for k in ours.cosmetics.iter().chain(theirs.cosmetics.iter()).chain(base.cosmetics.iter()).map(key) {
+            if !seen.insert(k) {
+                continue;
+            }
+            match merge_group(base_cosm.get(&k), our_cosm.get(&k), their_cosm.get(&k), |x: &Cosmetic| x.extra_bytes.clone()) {
+                Ok(v) => robot.cosmetics.extend(v),
+                Err((base, ours, theirs)) => {
+                    robot.cosmetics.extend(ours.iter().cloned());
+                    conflicts.push(MergeConflict::Cosmetic { id: k.0, on_part: k.1, base, ours, theirs });
+                }
+            }
+        }
+
+        robot.input_rewire = merge_value(&base.input_rewire, &ours.input_rewire, &theirs.input_rewire).unwrap_or_else(|_| {
+            conflicts.push(MergeConflict::Rewire { base: base.input_rewire.clone(), ours: ours.input_rewire.clone(), theirs: theirs.input_rewire.clone() });
+            ours.input_rewire.clone()
+        });
+
+        MergeResult { robot, conflicts }
+    }
+}
+
+fn fmt_parts(parts: &[Part]) -> String {
+    if parts.is_empty() {
+        return "(empty)".to_owned();
+    }
+    parts.iter().map(|p| format!("part {} rot {} color #{:02x}{:02x}{:02x}{:02x}", p.id, p.rotation, p.color_r, p.color_g, p.color_b, p.alpha_channel))
+        .collect::<Vec<_>>().join(" + ")
+}
+
+fn fmt_cosmetics(cosmetics: &[Cosmetic]) -> String {
+    if cosmetics.is_empty() {
+        return "(none)".to_owned();
+    }
+    cosmetics.iter().map(|c| format!("extra {:?}", c.extra_bytes)).collect::<Vec<_>>().join(" + ")
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeConflict::Name { base, ours, theirs } =>
+                write!(f, "name: base {}, ours {}, theirs {}", base, ours, theirs),
+            MergeConflict::Metadata { base, ours, theirs } =>
+                write!(f, "metadata: base {:#x}, ours {:#x}, theirs {:#x}", base, ours, theirs),
+            MergeConflict::Part { pos, base, ours, theirs } =>
+                write!(f, "cell ({}, {}, {}): base {}, ours {}, theirs {}", pos[0], pos[1], pos[2], fmt_parts(base), fmt_parts(ours), fmt_parts(theirs)),
+            MergeConflict::Cosmetic { id, on_part, base, ours, theirs } =>
+                write!(f, "cosmetic {} on {}: base {}, ours {}, theirs {}", id, on_part, fmt_cosmetics(base), fmt_cosmetics(ours), fmt_cosmetics(theirs)),
+            MergeConflict::Rewire { base, ours, theirs } =>
+                write!(f, "rewire: base {:?}, ours {:?}, theirs {:?}", base, ours, theirs)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(id: u32, x: i8) -> Part {
+        Part { id, pos_x: x, pos_y: 0, pos_z: 0, rotation: 0, color_r: 1, color_g: 2, color_b: 3, alpha_channel: 255, extra_bytes: Vec::new() }
+    }
+
+    // Two parts stacked in one cell, two identical cosmetics and an input bound twice
+    fn sample() -> Robot {
+        let mut bot = Robot::new();
+        bot.bot_name = b"bot\xff".to_vec();
+        bot.parts = vec!(part(1, 0), part(2, 0), part(3, 1));
+        bot.cosmetics = vec!(Cosmetic { id: 5, on_part: 1, extra_bytes: Vec::new() }, Cosmetic { id: 5, on_part: 1, extra_bytes: Vec::new() });
+        bot.input_rewire = vec!((6, 7), (6, 8));
+        bot
+    }
+
+    #[test]
+    fn merging_with_itself_keeps_everything() {
+        let bot = sample();
+        let merged = Robot::merge3(&bot, &bot, &bot);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.robot.compile().unwrap(), bot.compile().unwrap());
+    }
+
+    #[test]
+    fn one_sided_edits_are_taken() {
+        let base = sample();
+        let mut ours = base.clone();
+        ours.parts.push(part(4, 2));
+        ours.input_rewire.push((7, 6));
+        let mut theirs = base.clone();
+        theirs.parts[1].color_r = 200;
+        theirs.cosmetics.pop();
+        let merged = Robot::merge3(&base, &ours, &theirs);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.robot.parts.len(), 4);
+        assert_eq!(merged.robot.parts[1].color_r, 200);
+        assert_eq!(merged.robot.cosmetics.len(), 1);
+        assert_eq!(merged.robot.input_rewire, vec!((6, 7), (6, 8), (7, 6)));
+    }
+
+    #[test]
+    fn both_sided_edits_conflict() {
+        let base = sample();
+        let mut ours = base.clone();
+        ours.parts[0].id = 10;
+        ours.input_rewire.pop();
+        ours.bot_name = b"ours".to_vec();
+        let mut theirs = base.clone();
+        theirs.parts[1].id = 20;
+        theirs.input_rewire.reverse();
+        theirs.bot_name = b"theirs".to_vec();
+        let merged = Robot::merge3(&base, &ours, &theirs);
+        assert_eq!(merged.conflicts.len(), 3);
+        assert_eq!(merged.robot.compile().unwrap(), ours.compile().unwrap());
+    }
+}
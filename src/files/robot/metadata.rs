@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+
+// Decoded metadata carries the layout version it was written with under this key
+pub const LAYOUT_KEY: &str = "layout";
+
+// A named run of bits in Robot::metadata
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MetadataField {
+    pub name: String,
+    pub shift: u32, // lowest bit
+    pub bits: u32
+}
+
+// How fields of layout `from` become fields of layout `from + 1`
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MetadataMigration {
+    pub from: u32,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub renamed: BTreeMap<String, String>, // old name -> new name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub added: BTreeMap<String, u64>, // new field -> value for older files
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String> // only dropped when zero
+}
+
+// The definitions file (see docs/robot_metadata.md). Bits no field covers are reserved and must be 0
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MetadataLayout {
+    pub version: u32,
+    pub fields: Vec<MetadataField>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub migrations: Vec<MetadataMigration>
+}
+
+fn invalid(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+fn mask(field: &MetadataField) -> u64 {
+    (u64::MAX >> (64 - field.bits)) << field.shift
+}
+
+impl MetadataLayout {
+    pub fn load(path: &std::path::Path) -> Result<MetadataLayout, std::io::Error> {
+        let data = std::fs::read(path)?;
+        let layout: MetadataLayout = serde_json::from_slice(&data).map_err(std::io::Error::from)?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    fn validate(&self) -> Result<(), std::io::Error> {
+        if self.version == 0 {
+            return Err(invalid("Layout version must be at least 1".to_owned()));
+        }
+        let mut names = HashSet::new();
+        let mut used = 0u64;
+        for field in self.fields.iter() {
+            if field.name == LAYOUT_KEY || !names.insert(field.name.as_str()) {
+                return Err(invalid(format!("Field name {:?} is reserved or used twice", field.name)));
+            }
+            if field.bits == 0 || field.bits > 64 || field.shift + field.bits > 64 {
+                return Err(invalid(format!("Field {} does not fit in 64 bits", field.name)));
+            }
+            if used & mask(field) != 0 {
+                return Err(invalid(format!("Field {} overlaps another field", field.name)));
+            }
+            used |= mask(field);
+        }
+        let mut froms = HashSet::new();
+        for m in self.migrations.iter() {
+            if m.from >= self.version || !froms.insert(m.from) {
+                return Err(invalid(format!("Migration from layout {} is newer than the layout or given twice", m.from)));
+            }
+        }
+        Ok(())
+    }
+
+    // Named fields plus "layout": version. Errors if reserved bits are set
+    pub fn decode(&self, raw: u64) -> Result<Map<String, Value>, std::io::Error> {
+        let used = self.fields.iter().fold(0u64, |acc, f| acc | mask(f));
+        if raw & !used != 0 {
+            return Err(invalid(format!("Reserved metadata bits are set: {:#x}", raw & !used)));
+        }
+        let mut map = Map::new();
+        map.insert(LAYOUT_KEY.to_owned(), Value::from(self.version));
+        for field in self.fields.iter() {
+            map.insert(field.name.clone(), Value::from((raw & mask(field)) >> field.shift));
+        }
+        Ok(map)
+    }
+
+    // Fields written with an older "layout" are migrated first; missing fields are 0
+    pub fn encode(&self, fields: &Map<String, Value>) -> Result<u64, std::io::Error> {
+        let mut values = BTreeMap::new();
+        let mut from = self.version;
+        for (name, value) in fields.iter() {
+            let num = value.as_u64().ok_or_else(|| invalid(format!("Metadata field {name} must be a non-negative integer")))?;
+            if name == LAYOUT_KEY {
+                from = u32::try_from(num).map_err(|_| invalid(format!("Metadata layout {num} is invalid")))?;
+            } else {
+                values.insert(name.clone(), num);
+            }
+        }
+        if from > self.version {
+            return Err(invalid(format!("Metadata layout {} is newer than the definitions ({})", from, self.version)));
+        }
+        for version in from..self.version {
+            let m = self.migrations.iter().find(|m| m.from == version)
+                .ok_or_else(|| invalid(format!("No migration from metadata layout {version}")))?;
+            for name in m.removed.iter() {
+                if values.remove(name).is_some_and(|v| v != 0) {
+                    return Err(invalid(format!("Metadata field {name} was removed in layout {} but is set", version + 1)));
+                }
+            }
+            for (old, new) in m.renamed.iter() {
+                if let Some(v) = values.remove(old) {
+                    values.insert(new.clone(), v);
+                }
+            }
+            for (name, v) in m.added.iter() {
+                values.entry(name.clone()).or_insert(*v);
+            }
+        }
+        let mut raw = 0u64;
+        for (name, value) in values.iter() {
+            let field = self.fields.iter().find(|f| &f.name == name)
+                .ok_or_else(|| invalid(format!("Unknown metadata field {name}")))?;
+            if *value > mask(field) >> field.shift {
+                return Err(invalid(format!("Metadata field {name} does not fit in {} bits: {value}", field.bits)));
+            }
+            raw |= value << field.shift;
+        }
+        Ok(raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> MetadataLayout {
+        serde_json::from_str(r#"{
+            "version": 2,
+            "fields": [
+                { "name": "favorite", "shift": 0, "bits": 1 },
+                { "name": "locked", "shift": 1, "bits": 1 },
+                { "name": "icon", "shift": 8, "bits": 8 }
+            ],
+            "migrations": [ { "from": 1, "renamed": { "pinned": "favorite" }, "added": { "icon": 7 } } ]
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn decode_encode_round_trip() {
+        let layout = layout();
+        layout.validate().unwrap();
+        let fields = layout.decode(0x2a03).unwrap();
+        assert_eq!(fields["favorite"], 1);
+        assert_eq!(fields["icon"], 0x2a);
+        assert_eq!(fields[LAYOUT_KEY], 2);
+        assert_eq!(layout.encode(&fields).unwrap(), 0x2a03);
+    }
+
+    #[test]
+    fn reserved_bits_and_bad_values_are_errors() {
+        let layout = layout();
+        assert!(layout.decode(0x04).is_err());
+        let mut fields = layout.decode(0).unwrap();
+        fields.insert("icon".to_owned(), Value::from(256));
+        assert!(layout.encode(&fields).is_err());
+        fields.remove("icon");
+        fields.insert("unknown".to_owned(), Value::from(1));
+        assert!(layout.encode(&fields).is_err());
+    }
+
+    #[test]
+    fn older_layouts_are_migrated() {
+        let layout = layout();
+        let old: Map<String, Value> = serde_json::from_str(r#"{ "layout": 1, "pinned": 1, "locked": 1 }"#).unwrap();
+        assert_eq!(layout.encode(&old).unwrap(), 0x0703);
+        let newer: Map<String, Value> = serde_json::from_str(r#"{ "layout": 3 }"#).unwrap();
+        assert!(layout.encode(&newer).is_err());
+    }
+
+    #[test]
+    fn overlapping_fields_are_rejected() {
+        let mut layout = layout();
+        layout.fields.push(MetadataField { name: "wide".to_owned(), shift: 4, bits: 8 });
+        assert!(layout.validate().is_err());
+    }
+}
@@ -1,2 +1,26 @@
 mod robot;
-pub use robot::*;
\ No newline at end of file
+mod vox;
+mod diff;
+mod merge;
+mod text;
+mod compact;
+mod sharecode;
+mod paint;
+mod controls;
+mod fingerprint;
+mod totals;
+mod generate;
+mod edit;
+mod metadata;
+pub use robot::*;
+pub use vox::*;
+pub use diff::*;
+pub use merge::*;
+pub use text::*;
+pub use compact::*;
+pub use sharecode::*;
+pub use paint::*;
+pub use controls::*;
+pub use generate::*;
+pub use edit::*;
+pub use metadata::*;
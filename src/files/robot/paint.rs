@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use super::{Robot, Part};
+
+pub type Rgb = [u8; 3];
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ColorUsage {
+    pub color: [u8; 4], // r, g, b, alpha
+    pub count: usize
+}
+
+impl Part {
+    pub fn rgb(&self) -> Rgb {
+        [self.color_r, self.color_g, self.color_b]
+    }
+
+    // Alpha is left alone; it is a material property rather than paint
+    pub fn set_rgb(&mut self, color: Rgb) {
+        self.color_r = color[0];
+        self.color_g = color[1];
+        self.color_b = color[2];
+    }
+}
+
+// Largest per-channel difference
+fn color_distance(a: &Rgb, b: &Rgb) -> u8 {
+    a.iter().zip(b.iter()).map(|(x, y)| x.abs_diff(*y)).max().unwrap_or(0)
+}
+
+impl Robot {
+    // Every colour on the bot with how many parts use it, most used first
+    pub fn palette(self: &Robot) -> Vec<ColorUsage> {
+        let mut counts: HashMap<[u8; 4], usize> = HashMap::new();
+        for part in self.parts.iter() {
+            *counts.entry([part.color_r, part.color_g, part.color_b, part.alpha_channel]).or_default() += 1;
+        }
+        let mut palette: Vec<ColorUsage> = counts.into_iter().map(|(color, count)| ColorUsage { color, count }).collect();
+        palette.sort_by(|a, b| b.count.cmp(&a.count).then(a.color.cmp(&b.color)));
+        palette
+    }
+
+    // Repaints parts whose colour is within `tolerance` (per channel) of a mapping's source.
+    // The closest source wins, and mappings are not chained. Returns the number of parts changed
+    pub fn remap_colors(self: &mut Robot, mapping: &[(Rgb, Rgb)], tolerance: u8) -> usize {
+        let mut changed = 0;
+        for part in self.parts.iter_mut() {
+            let current = part.rgb();
+            let target = mapping.iter()
+                .map(|(from, to)| (color_distance(from, &current), to))
+                .filter(|x| x.0 <= tolerance)
+                .min_by_key(|x| x.0);
+            if let Some((_, to)) = target && *to != current {
+                part.set_rgb(*to);
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    // Paints every part of the given type; returns the number of parts changed
+    pub fn recolor_parts(self: &mut Robot, id: u32, color: Rgb) -> usize {
+        let mut changed = 0;
+        for part in self.parts.iter_mut().filter(|x| x.id == id && x.rgb() != color) {
+            part.set_rgb(color);
+            changed += 1;
+        }
+        changed
+    }
+
+    // Replaces the placeholder colour slots[i] with team[i]. The game's placeholder colours aren't
+    // published, so callers supply them; a team colour without a slot is an error
+    pub fn apply_team_colors(self: &mut Robot, slots: &[Rgb], team: &[Rgb]) -> Result<usize, std::io::Error> {
+        if team.len() > slots.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                format!("{} team colours given but only {} slot colours", team.len(), slots.len())));
+        }
+        let mapping: Vec<(Rgb, Rgb)> = slots.iter().copied().zip(team.iter().copied()).collect();
+        Ok(self.remap_colors(&mapping, 0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(color: Rgb) -> Part {
+        Part { id: 1, pos_x: 0, pos_y: 0, pos_z: 0, rotation: 0, color_r: color[0], color_g: color[1], color_b: color[2], alpha_channel: 200, extra_bytes: Vec::new() }
+    }
+
+    fn bot(colors: &[Rgb]) -> Robot {
+        let mut bot = Robot::new();
+        bot.parts = colors.iter().map(|x| part(*x)).collect();
+        bot
+    }
+
+    #[test]
+    fn palette_counts_colors() {
+        let palette = bot(&[[1, 2, 3], [9, 9, 9], [1, 2, 3]]).palette();
+        assert_eq!(palette, vec!(ColorUsage { color: [1, 2, 3, 200], count: 2 }, ColorUsage { color: [9, 9, 9, 200], count: 1 }));
+    }
+
+    #[test]
+    fn closest_mapping_wins_and_is_not_chained() {
+        let mut bot = bot(&[[10, 10, 10], [14, 14, 14], [100, 0, 0]]);
+        let changed = bot.remap_colors(&[([10, 10, 10], [14, 14, 14]), ([15, 15, 15], [0, 0, 0])], 4);
+        assert_eq!(changed, 2);
+        assert_eq!(bot.parts[0].rgb(), [14, 14, 14]);
+        assert_eq!(bot.parts[1].rgb(), [0, 0, 0]);
+        assert_eq!(bot.parts[2].rgb(), [100, 0, 0]);
+        assert_eq!(bot.parts[0].alpha_channel, 200);
+    }
+
+    #[test]
+    fn team_colors_need_slots() {
+        let mut bot = bot(&[[1, 1, 1], [2, 2, 2]]);
+        assert!(bot.apply_team_colors(&[[1, 1, 1]], &[[5, 5, 5], [6, 6, 6]]).is_err());
+        assert_eq!(bot.apply_team_colors(&[[1, 1, 1], [2, 2, 2]], &[[5, 5, 5]]).unwrap(), 1);
+        assert_eq!(bot.parts[1].rgb(), [2, 2, 2]);
+    }
+}
@@ -1,19 +1,114 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::io::{Cursor, Read, Write};
 use std::io::Seek;
 use md5::{Md5, Digest};
+use crate::files::schema::SchemaRegistry;
+use super::{ControlNames, JsonBindings, MetadataLayout};
 
 pub const ROBOT_MAGIC_NUMBER: u32 = 0xC571B040; // 40B071C5 "Robotics"
 const CURRENT_VERSION: u32 = 4;
 pub const MAX_EXTRADATA_SIZE: u8 = 64;
+// JSON layout version written by From<Robot>; files without a "version" are the original layout
+pub const JSON_ROBOT_VERSION: u32 = 3;
+
+fn json_v1() -> u32 {
+    1
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct JsonRobot {
-    name: String,
+    #[serde(default = "json_v1")]
+    version: u32,
+    name: JsonName,
     metadata: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata_fields: Option<serde_json::Map<String, serde_json::Value>>, // decoded with a MetadataLayout; replaces metadata
     parts: Vec<JsonPart>,
-    cosmetics: Vec<JsonCosmetic>
+    #[serde(default)]
+    cosmetics: Vec<JsonCosmetic>,
+    #[serde(default)]
+    input_rewire: JsonBindings, // [input, remapped to] pairs, in file order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<String> // hex MD5 of the binary this was dumped from; informational only
+}
+
+// Bot names are raw bytes. Valid UTF-8 is stored as a plain string, anything else as
+// {"escaped": "..."} where "\\" is a backslash and "\xNN" is a raw byte
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum JsonName {
+    Text(String),
+    Escaped { escaped: String }
+}
+
+impl From<&[u8]> for JsonName {
+    fn from(name: &[u8]) -> Self {
+        if let Ok(s) = std::str::from_utf8(name) {
+            return JsonName::Text(s.to_owned());
+        }
+        let mut escaped = String::new();
+        let mut rest = name;
+        while !rest.is_empty() {
+            let (valid, invalid) = match std::str::from_utf8(rest) {
+                Ok(s) => (s, &rest[rest.len()..]),
+                Err(e) => {
+                    let (ok, bad) = rest.split_at(e.valid_up_to());
+                    (std::str::from_utf8(ok).unwrap_or_default(), bad)
+                }
+            };
+            escaped.push_str(&valid.replace('\\', "\\\\"));
+            if let Some(b) = invalid.first() {
+                escaped.push_str(&format!("\\x{:02x}", b));
+                rest = &invalid[1..];
+            } else {
+                rest = invalid;
+            }
+        }
+        JsonName::Escaped { escaped }
+    }
+}
+
+// Quoted, with the escaped form for names that aren't UTF-8
+impl std::fmt::Display for JsonName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonName::Text(s) => write!(f, "{:?}", s),
+            JsonName::Escaped { escaped } => write!(f, "\"{}\"", escaped)
+        }
+    }
+}
+
+impl JsonName {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        match self {
+            JsonName::Text(s) => Ok(s.as_bytes().to_vec()),
+            JsonName::Escaped { escaped } => {
+                let mut out = Vec::new();
+                let mut chars = escaped.chars();
+                while let Some(c) = chars.next() {
+                    if c != '\\' {
+                        let mut buf = [0u8; 4];
+                        out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('\\') => out.push(b'\\'),
+                        Some('x') => {
+                            let hex: String = chars.by_ref().take(2).collect();
+                            // exactly two hex digits; from_str_radix alone would take "f" or "+f"
+                            if hex.len() != 2 || !hex.chars().all(|x| x.is_ascii_hexdigit()) {
+                                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Bad name escape \\x{hex}")));
+                            }
+                            out.push(u8::from_str_radix(&hex, 16).unwrap_or_default());
+                        },
+                        other => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Bad name escape \\{}", other.map(String::from).unwrap_or_default())))
+                    }
+                }
+                Ok(out)
+            }
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -23,14 +118,20 @@ pub struct JsonPart {
     rot: u8,
     color: [u8; 3],
     alpha: u8,
-    extra_data: Vec<u8>
+    #[serde(default)]
+    extra_data: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fields: Option<serde_json::Map<String, serde_json::Value>> // extra_data decoded with a SchemaRegistry
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct JsonCosmetic {
     id: u32,
     part_on: u32,
-    extra_data: Vec<u8>
+    #[serde(default)]
+    extra_data: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fields: Option<serde_json::Map<String, serde_json::Value>>
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -39,11 +140,11 @@ pub struct Robot {
     pub bot_name: Vec<u8>,
     pub parts: Vec<Part>,
     pub cosmetics: Vec<Cosmetic>,
-    pub input_rewire: HashMap<u8, u8>,
+    pub input_rewire: Vec<(u8, u8)>, // (input, remapped to), in file order
     pub hash: Option<Vec<u8>>
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Part {
     pub id: u32,
     pub pos_x: i8,
@@ -57,7 +158,7 @@ pub struct Part {
     pub extra_bytes: Vec<u8>
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Cosmetic {
     pub id: u32,
     pub on_part: u32,
@@ -86,6 +187,7 @@ impl TryFrom<&[u8]> for Robot {
             2 => Robot::from_v2(&mut blank, &mut file),
             3 => Robot::from_v3(&mut blank, &mut file),
             4 => Robot::from_v4(&mut blank, &mut file),
+            5 => Robot::from_v5(&mut blank, &mut file),
             _ => Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 format!("Version was invalid: {version}"),
@@ -97,12 +199,22 @@ impl TryFrom<&[u8]> for Robot {
     }
 }
 
-impl From<JsonRobot> for Robot {
-    fn from(file: JsonRobot) -> Self {
+impl TryFrom<JsonRobot> for Robot {
+    type Error = std::io::Error;
+    fn try_from(file: JsonRobot) -> Result<Self, Self::Error> {
+        if file.version > JSON_ROBOT_VERSION {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("JSON robot version was invalid: {}", file.version)));
+        }
         let mut bot = Robot::new();
-        bot.bot_name = file.name.into_bytes();
+        bot.bot_name = file.name.to_bytes()?;
         bot.metadata = file.metadata;
+        if file.metadata_fields.is_some() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Robot has decoded metadata fields; encode them with a metadata layout first"));
+        }
         bot.parts = Vec::new();
+        if file.parts.iter().any(|x| x.fields.is_some()) || file.cosmetics.iter().any(|x| x.fields.is_some()) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Robot has decoded extra data fields; encode them with a schema first"));
+        }
         file.parts.into_iter().for_each( |part| {
             bot.parts.push(Part {
                 id: part.id,
@@ -124,7 +236,12 @@ impl From<JsonRobot> for Robot {
                 extra_bytes: part.extra_data
             });
         });
-        bot
+        bot.input_rewire = file.input_rewire.resolve(&ControlNames::default())?.to_pairs();
+        bot.hash = match file.hash {
+            None => None,
+            Some(h) => Some(hex::decode(&h).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Bad hash {h}: {e}")))?)
+        };
+        Ok(bot)
     }
 }
 
@@ -276,7 +393,7 @@ impl Robot {
             let key = buf1[0];
             file.read_exact(&mut buf1)?;
             let val = buf1[0];
-            bot.input_rewire.insert(key, val);
+            bot.input_rewire.push((key, val));
         }
 
         Ok(())
@@ -288,7 +405,7 @@ impl Robot {
             bot_name: "robot".to_owned().into_bytes(),
             parts: Vec::new(),
             cosmetics: Vec::new(),
-            input_rewire: HashMap::new(),
+            input_rewire: Vec::new(),
             hash: None
         }
     }
@@ -332,10 +449,13 @@ impl Robot {
             file.write_all(&u8::to_be_bytes(elem.extra_bytes.len() as u8))?;
             file.write_all(&elem.extra_bytes)?;
         }
+        if self.input_rewire.len() > u8::MAX.into() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Input rewiring can only have 255 entries"))
+        }
         file.write_all(&u8::to_be_bytes(self.input_rewire.len() as u8))?;
         for elem in self.input_rewire.iter() {
-            file.write_all(&u8::to_be_bytes(*elem.0))?;
-            file.write_all(&u8::to_be_bytes(*elem.1))?;
+            file.write_all(&u8::to_be_bytes(elem.0))?;
+            file.write_all(&u8::to_be_bytes(elem.1))?;
         }
 
         let mut md5hash = Md5::new();
@@ -349,24 +469,153 @@ impl Robot {
     }
 }
 
+// The rotation byte's layout isn't known; tools keep existing rotations and write this for parts they create
+pub const NO_ROTATION: u8 = 0;
+
 impl From<Robot> for JsonRobot {
     fn from(bot: Robot) -> Self {
         JsonRobot {
-            name: std::str::from_utf8(&bot.bot_name).unwrap().to_owned(),
+            version: JSON_ROBOT_VERSION,
+            name: JsonName::from(bot.bot_name.as_slice()),
             metadata: bot.metadata,
+            metadata_fields: None,
             parts: bot.parts.iter().map(|x| JsonPart {
                 id: x.id,
                 pos: [x.pos_x, x.pos_y, x.pos_z],
                 rot: x.rotation,
                 color: [x.color_r, x.color_g, x.color_b],
                 alpha: x.alpha_channel,
-                extra_data: x.extra_bytes.clone()
+                extra_data: x.extra_bytes.clone(),
+                fields: None
             }).collect(),
             cosmetics: bot.cosmetics.iter().map(|x| JsonCosmetic {
                 id: x.id,
                 part_on: x.on_part,
-                extra_data: x.extra_bytes.clone()
-            }).collect()
+                extra_data: x.extra_bytes.clone(),
+                fields: None
+            }).collect(),
+            input_rewire: JsonBindings::from(&bot.controls()),
+            hash: bot.hash.as_ref().map(hex::encode)
+        }
+    }
+}
+
+impl JsonRobot {
+    // Replaces extra_data with named fields wherever the registry has a matching layout.
+    // Blobs that don't fit their layout are left as bytes; the returned list says why
+    pub fn decode_extra(self: &mut JsonRobot, registry: &SchemaRegistry) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (i, part) in self.parts.iter_mut().enumerate() {
+            if let Some(layout) = registry.parts.get(&part.id) {
+                match layout.decode(&part.extra_data) {
+                    Ok(fields) => { part.fields = Some(fields); part.extra_data.clear(); },
+                    Err(e) => problems.push(format!("part {} (id {}): {}", i, part.id, e))
+                }
+            }
+        }
+        for (i, cosm) in self.cosmetics.iter_mut().enumerate() {
+            if let Some(layout) = registry.cosmetics.get(&cosm.id) {
+                match layout.decode(&cosm.extra_data) {
+                    Ok(fields) => { cosm.fields = Some(fields); cosm.extra_data.clear(); },
+                    Err(e) => problems.push(format!("cosmetic {} (id {}): {}", i, cosm.id, e))
+                }
+            }
+        }
+        problems
+    }
+
+    // Turns named fields back into extra_data, validating them against the registry
+    pub fn encode_extra(self: &mut JsonRobot, registry: &SchemaRegistry) -> Result<(), std::io::Error> {
+        let fail = |what: String, e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{what}: {e}"));
+        for (i, part) in self.parts.iter_mut().enumerate() {
+            let Some(fields) = part.fields.take() else { continue; };
+            let what = format!("part {} (id {})", i, part.id);
+            if !part.extra_data.is_empty() {
+                return Err(fail(what, "has both extra_data and fields".to_owned()));
+            }
+            let layout = registry.parts.get(&part.id).ok_or_else(|| fail(what.clone(), "no schema layout for this id".to_owned()))?;
+            part.extra_data = layout.encode(&fields).map_err(|e| fail(what, e.to_string()))?;
+        }
+        for (i, cosm) in self.cosmetics.iter_mut().enumerate() {
+            let Some(fields) = cosm.fields.take() else { continue; };
+            let what = format!("cosmetic {} (id {})", i, cosm.id);
+            if !cosm.extra_data.is_empty() {
+                return Err(fail(what, "has both extra_data and fields".to_owned()));
+            }
+            let layout = registry.cosmetics.get(&cosm.id).ok_or_else(|| fail(what.clone(), "no schema layout for this id".to_owned()))?;
+            cosm.extra_data = layout.encode(&fields).map_err(|e| fail(what, e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    // Writes rewire ids that have a name as that name
+    pub fn name_controls(self: &mut JsonRobot, names: &ControlNames) {
+        if let Ok(bindings) = self.input_rewire.resolve(&ControlNames::default()) {
+            self.input_rewire = JsonBindings::named(&bindings, names);
+        }
+    }
+
+    // Turns named rewire ids back into numbers; unknown names are an error
+    pub fn resolve_controls(self: &mut JsonRobot, names: &ControlNames) -> Result<(), std::io::Error> {
+        self.input_rewire = JsonBindings::from(&self.input_rewire.resolve(names)?);
+        Ok(())
+    }
+
+    // Replaces metadata with named fields; if it doesn't fit the layout the number is kept and the problem returned
+    pub fn decode_metadata(self: &mut JsonRobot, layout: &MetadataLayout) -> Option<String> {
+        match layout.decode(self.metadata) {
+            Ok(fields) => { self.metadata_fields = Some(fields); self.metadata = 0; None },
+            Err(e) => Some(format!("metadata: {}", e))
+        }
+    }
+
+    pub fn encode_metadata(self: &mut JsonRobot, layout: &MetadataLayout) -> Result<(), std::io::Error> {
+        let Some(fields) = self.metadata_fields.take() else { return Ok(()); };
+        if self.metadata != 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Robot has both metadata and metadata_fields"));
+        }
+        self.metadata = layout.encode(&fields)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // robotfile.json plus the fields it doesn't exercise: rewires and a name that isn't UTF-8
+    fn sample() -> Robot {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/robotfile.json")).unwrap();
+        let mut bot = Robot::try_from(serde_json::from_slice::<JsonRobot>(&data).unwrap()).unwrap();
+        bot.bot_name = b"bot\xff\\name".to_vec();
+        bot.input_rewire = vec!((6, 7), (7, 6), (6, 8));
+        bot
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let binary = sample().compile().unwrap();
+        let json = serde_json::to_string(&JsonRobot::from(Robot::try_from(binary.as_slice()).unwrap())).unwrap();
+        let back = Robot::try_from(serde_json::from_str::<JsonRobot>(&json).unwrap()).unwrap();
+        assert_eq!(back.bot_name, b"bot\xff\\name");
+        assert_eq!(back.compile().unwrap(), binary);
+    }
+
+    #[test]
+    fn bad_json_robots_are_errors() {
+        let mut json = serde_json::to_value(JsonRobot::from(sample())).unwrap();
+        json["version"] = serde_json::Value::from(JSON_ROBOT_VERSION + 1);
+        assert!(Robot::try_from(serde_json::from_value::<JsonRobot>(json.clone()).unwrap()).is_err());
+        json["version"] = serde_json::Value::from(JSON_ROBOT_VERSION);
+        json["hash"] = serde_json::Value::from("not hex");
+        assert!(Robot::try_from(serde_json::from_value::<JsonRobot>(json).unwrap()).is_err());
+    }
+
+    #[test]
+    fn bad_name_escapes_are_errors() {
+        for escaped in ["bot\\xf", "bot\\x+f", "bot\\xzz", "bot\\q", "bot\\"] {
+            assert!(JsonName::Escaped { escaped: escaped.to_owned() }.to_bytes().is_err(), "{escaped}");
         }
+        assert_eq!(JsonName::Escaped { escaped: "bot\\xff\\\\".to_owned() }.to_bytes().unwrap(), b"bot\xff\\");
     }
 }
@@ -0,0 +1,90 @@
+use std::convert::TryFrom;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use md5::{Md5, Digest};
+use super::{Robot, ROBOT_MAGIC_NUMBER, COMPACT_VERSION};
+
+// Share codes are "PB1-" followed by unpadded URL-safe base64 of a v5 robot, zstd-compressed unless
+// that is longer (without its magic number and version) and the first 4 bytes of that payload's MD5.
+// All whitespace is ignored when decoding, so codes survive line wrapping in chat and forums
+pub const SHARE_CODE_PREFIX: &str = "PB1-";
+const CHECKSUM_SIZE: usize = 4;
+// Longest code accepted, in base64 characters after the prefix; codes are pasted from chat, so this is
+// checked before decoding
+pub const MAX_SHARE_CODE_LEN: usize = 256 * 1024;
+
+fn share_error(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let mut md5hash = Md5::new();
+    md5hash.update(payload);
+    let res = md5hash.finalize();
+    [res[0], res[1], res[2], res[3]]
+}
+
+impl Robot {
+    pub fn to_share_code(self: &Robot) -> Result<String, std::io::Error> {
+        let compressed = self.compile_v5(true)?;
+        let plain = self.compile_v5(false)?;
+        let file = if compressed.len() < plain.len() { compressed } else { plain };
+        let mut payload = file[8..].to_vec();
+        let sum = checksum(&payload);
+        payload.extend_from_slice(&sum);
+        Ok(format!("{SHARE_CODE_PREFIX}{}", URL_SAFE_NO_PAD.encode(payload)))
+    }
+
+    pub fn from_share_code(code: &str) -> Result<Robot, std::io::Error> {
+        let code: String = code.chars().filter(|x| !x.is_whitespace()).collect();
+        let Some(body) = code.strip_prefix(SHARE_CODE_PREFIX) else {
+            let version = code.split('-').next().unwrap_or_default();
+            return Err(share_error(format!("Share code must start with {SHARE_CODE_PREFIX} (was '{version}')")));
+        };
+        if body.len() > MAX_SHARE_CODE_LEN {
+            return Err(share_error(format!("Share code is longer than {MAX_SHARE_CODE_LEN} characters")));
+        }
+        let bytes = URL_SAFE_NO_PAD.decode(body).map_err(|e| share_error(format!("Share code is damaged: {e}")))?;
+        if bytes.len() <= CHECKSUM_SIZE {
+            return Err(share_error("Share code is too short".to_owned()));
+        }
+        let (payload, sum) = bytes.split_at(bytes.len() - CHECKSUM_SIZE);
+        if checksum(payload) != sum {
+            return Err(share_error("Share code checksum did not match (incomplete or mistyped?)".to_owned()));
+        }
+        let mut file = Vec::with_capacity(payload.len() + 8);
+        file.extend_from_slice(&u32::to_be_bytes(ROBOT_MAGIC_NUMBER));
+        file.extend_from_slice(&u32::to_be_bytes(COMPACT_VERSION));
+        file.extend_from_slice(payload);
+        Robot::try_from(file.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Robot {
+        let json = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/robotfile.json")).unwrap();
+        let json: super::super::JsonRobot = serde_json::from_slice(&json).unwrap();
+        Robot::try_from(json).unwrap()
+    }
+
+    #[test]
+    fn share_code_round_trip() {
+        let bot = sample();
+        let code = bot.to_share_code().unwrap();
+        let back = Robot::from_share_code(&code).unwrap();
+        assert_eq!(back.compile().unwrap(), bot.compile().unwrap());
+    }
+
+    #[test]
+    fn bad_share_codes_are_errors() {
+        let code = sample().to_share_code().unwrap();
+        assert!(Robot::from_share_code("PB2-AAAA").is_err());
+        assert!(Robot::from_share_code("PB1-!!!!").is_err());
+        assert!(Robot::from_share_code(&code[..code.len() - 3]).is_err());
+        let oversized = format!("{SHARE_CODE_PREFIX}{}", "A".repeat(MAX_SHARE_CODE_LEN + 1));
+        assert!(Robot::from_share_code(&oversized).err().unwrap().to_string().contains("longer than"));
+    }
+}
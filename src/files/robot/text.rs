@@ -0,0 +1,236 @@
+use std::fmt::Write;
+use super::{Robot, Part, Cosmetic, MAX_EXTRADATA_SIZE};
+
+// Line-oriented robot format for keeping bots in version control:
+//
+//   procelio-robot-text 1
+//   name "robot"
+//   metadata 0
+//   [parts]
+//   57 0 4 -1 0 #00467dff 00db4b06     (id x y z rot #rrggbbaa [extra-hex])
+//   [cosmetics]
+//   432 1                              (id on_part [extra-hex])
+//   [rewire]
+//   1 2                                (input remapped_to)
+//
+// Blank lines and lines starting with "//" are ignored. Order is preserved both ways
+pub const ROBOT_TEXT_HEADER: &str = "procelio-robot-text";
+const ROBOT_TEXT_VERSION: u32 = 1;
+
+fn text_error(line: usize, msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, format!("line {line}: {msg}"))
+}
+
+// Quotes a raw name; '"' and '\' are backslash-escaped and non-UTF-8 bytes become \xNN
+fn quote_name(name: &[u8]) -> String {
+    let mut out = String::from("\"");
+    for chunk in name.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                c if c.is_control() => { let _ = write!(out, "\\u{{{:x}}}", c as u32); },
+                c => out.push(c)
+            }
+        }
+        for b in chunk.invalid() {
+            let _ = write!(out, "\\x{b:02x}");
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn unquote_name(text: &str, line: usize) -> Result<Vec<u8>, std::io::Error> {
+    let inner = text.strip_prefix('"').and_then(|x| x.strip_suffix('"'))
+        .ok_or_else(|| text_error(line, "name must be in double quotes".to_owned()))?;
+    let mut out = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('"') => out.push(b'"'),
+            Some('\\') => out.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                out.push(u8::from_str_radix(&hex, 16).map_err(|_| text_error(line, format!("bad escape \\x{hex}")))?);
+            },
+            Some('u') => {
+                let code: String = chars.by_ref().skip(1).take_while(|x| *x != '}').collect();
+                let c = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32)
+                    .ok_or_else(|| text_error(line, format!("bad escape \\u{{{code}}}")))?;
+                let mut buf = [0u8; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            },
+            other => return Err(text_error(line, format!("bad escape \\{}", other.map(String::from).unwrap_or_default())))
+        }
+    }
+    Ok(out)
+}
+
+fn parse_field<T: std::str::FromStr>(token: Option<&str>, what: &str, line: usize) -> Result<T, std::io::Error> {
+    let token = token.ok_or_else(|| text_error(line, format!("missing {what}")))?;
+    token.parse().map_err(|_| text_error(line, format!("invalid {what} '{token}'")))
+}
+
+fn parse_extra(token: Option<&str>, line: usize) -> Result<Vec<u8>, std::io::Error> {
+    let bytes = match token {
+        None => Vec::new(),
+        Some(t) => hex::decode(t).map_err(|e| text_error(line, format!("invalid extra data '{t}': {e}")))?
+    };
+    if bytes.len() > MAX_EXTRADATA_SIZE.into() {
+        return Err(text_error(line, format!("extra data region can only be 64 bytes long (was {})", bytes.len())));
+    }
+    Ok(bytes)
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Header,
+    Parts,
+    Cosmetics,
+    Rewire
+}
+
+impl Robot {
+    pub fn to_text(self: &Robot) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{ROBOT_TEXT_HEADER} {ROBOT_TEXT_VERSION}");
+        let _ = writeln!(out, "name {}", quote_name(&self.bot_name));
+        let _ = writeln!(out, "metadata {}", self.metadata);
+        let _ = writeln!(out, "[parts]");
+        for p in self.parts.iter() {
+            let _ = write!(out, "{} {} {} {} {} #{:02x}{:02x}{:02x}{:02x}", p.id, p.pos_x, p.pos_y, p.pos_z, p.rotation,
+                p.color_r, p.color_g, p.color_b, p.alpha_channel);
+            if !p.extra_bytes.is_empty() {
+                let _ = write!(out, " {}", hex::encode(&p.extra_bytes));
+            }
+            out.push('\n');
+        }
+        let _ = writeln!(out, "[cosmetics]");
+        for c in self.cosmetics.iter() {
+            let _ = write!(out, "{} {}", c.id, c.on_part);
+            if !c.extra_bytes.is_empty() {
+                let _ = write!(out, " {}", hex::encode(&c.extra_bytes));
+            }
+            out.push('\n');
+        }
+        let _ = writeln!(out, "[rewire]");
+        for r in self.input_rewire.iter() {
+            let _ = writeln!(out, "{} {}", r.0, r.1);
+        }
+        out
+    }
+
+    pub fn from_text(text: &str) -> Result<Robot, std::io::Error> {
+        let mut bot = Robot::new();
+        let mut section = Section::Header;
+        let mut seen_header = false;
+        for (idx, raw) in text.lines().enumerate() {
+            let line = idx + 1;
+            let trimmed = raw.trim();
+            if trimmed.is_empty() || trimmed.starts_with("//") {
+                continue;
+            }
+            if !seen_header {
+                let mut tokens = trimmed.split_whitespace();
+                if tokens.next() != Some(ROBOT_TEXT_HEADER) {
+                    return Err(text_error(line, format!("expected '{ROBOT_TEXT_HEADER} {ROBOT_TEXT_VERSION}' header")));
+                }
+                let version: u32 = parse_field(tokens.next(), "version", line)?;
+                if version != ROBOT_TEXT_VERSION {
+                    return Err(text_error(line, format!("Version was invalid: {version}")));
+                }
+                seen_header = true;
+                continue;
+            }
+            match trimmed {
+                "[parts]" => { section = Section::Parts; continue; },
+                "[cosmetics]" => { section = Section::Cosmetics; continue; },
+                "[rewire]" => { section = Section::Rewire; continue; },
+                _ => {}
+            }
+
+            let mut tokens = trimmed.split_whitespace();
+            match section {
+                Section::Header => {
+                    match tokens.next() {
+                        Some("name") => {
+                            let rest = trimmed["name".len()..].trim();
+                            bot.bot_name = unquote_name(rest, line)?;
+                            if bot.bot_name.len() > u8::MAX.into() {
+                                return Err(text_error(line, "bot name can only be 255 bytes long".to_owned()));
+                            }
+                            continue;
+                        },
+                        Some("metadata") => bot.metadata = parse_field(tokens.next(), "metadata", line)?,
+                        Some(other) => return Err(text_error(line, format!("unknown header field '{other}'"))),
+                        None => {}
+                    }
+                },
+                Section::Parts => {
+                    let id = parse_field(tokens.next(), "part id", line)?;
+                    let pos_x = parse_field(tokens.next(), "x position", line)?;
+                    let pos_y = parse_field(tokens.next(), "y position", line)?;
+                    let pos_z = parse_field(tokens.next(), "z position", line)?;
+                    let rotation = parse_field(tokens.next(), "rotation", line)?;
+                    let color_token = tokens.next().ok_or_else(|| text_error(line, "missing color".to_owned()))?;
+                    let color = color_token.strip_prefix('#').and_then(|x| hex::decode(x).ok()).filter(|x| x.len() == 4)
+                        .ok_or_else(|| text_error(line, format!("invalid color '{color_token}', expected #rrggbbaa")))?;
+                    let extra_bytes = parse_extra(tokens.next(), line)?;
+                    bot.parts.push(Part {
+                        id, pos_x, pos_y, pos_z, rotation,
+                        color_r: color[0], color_g: color[1], color_b: color[2], alpha_channel: color[3], extra_bytes
+                    });
+                },
+                Section::Cosmetics => {
+                    let id = parse_field(tokens.next(), "cosmetic id", line)?;
+                    let on_part = parse_field(tokens.next(), "cosmetic part", line)?;
+                    let extra_bytes = parse_extra(tokens.next(), line)?;
+                    bot.cosmetics.push(Cosmetic { id, on_part, extra_bytes });
+                },
+                Section::Rewire => {
+                    let input = parse_field(tokens.next(), "rewire input", line)?;
+                    let output = parse_field(tokens.next(), "rewire output", line)?;
+                    bot.input_rewire.push((input, output));
+                }
+            }
+            if let Some(extra) = tokens.next() {
+                return Err(text_error(line, format!("unexpected '{extra}'")));
+            }
+        }
+        if !seen_header {
+            return Err(text_error(1, format!("missing '{ROBOT_TEXT_HEADER}' header")));
+        }
+        Ok(bot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/robotfile.json")).unwrap();
+        let mut bot = Robot::try_from(serde_json::from_slice::<crate::files::robot::JsonRobot>(&data).unwrap()).unwrap();
+        bot.bot_name = b"a \"quoted\" \xff name".to_vec();
+        bot.cosmetics.push(Cosmetic { id: 432, on_part: 1, extra_bytes: vec!(1, 2, 3) });
+        bot.input_rewire = vec!((6, 7), (7, 6));
+        let binary = bot.compile().unwrap();
+        let text = Robot::try_from(binary.as_slice()).unwrap().to_text();
+        assert_eq!(Robot::from_text(&text).unwrap().compile().unwrap(), binary);
+    }
+
+    #[test]
+    fn bad_text_is_an_error_with_its_line() {
+        let text = format!("{ROBOT_TEXT_HEADER} {ROBOT_TEXT_VERSION}\n[parts]\n57 0 4 -1 0 #00467dff\n57 0 4 300 0 #00467dff\n");
+        let err = Robot::from_text(&text).err().unwrap();
+        assert!(err.to_string().starts_with("line 4:"), "{}", err);
+        assert!(Robot::from_text("not a robot").is_err());
+    }
+}
@@ -0,0 +1,21 @@
+use crate::files::stats::statfile::{StatsFile, COST_FLAG, MODIFIER_COST};
+use super::Robot;
+
+impl Robot {
+    // Sum of one block flag over every part; parts (or flags) missing from the statfile count as 0
+    pub fn part_stat_total(self: &Robot, stats: &StatsFile, flag: u8) -> i64 {
+        self.parts.iter()
+            .filter_map(|x| stats.blocks.data.get(&x.id).and_then(|f| f.get(&flag)))
+            .map(|x| i64::from(*x))
+            .sum()
+    }
+
+    // Purchase cost of the parts plus cosmetics
+    pub fn cost(self: &Robot, stats: &StatsFile) -> i64 {
+        let cosmetics: i64 = self.cosmetics.iter()
+            .filter_map(|x| stats.cosmetics.data.get(&x.id).and_then(|f| f.get(&MODIFIER_COST)))
+            .map(|x| i64::from(*x))
+            .sum();
+        self.part_stat_total(stats, COST_FLAG) + cosmetics
+    }
+}
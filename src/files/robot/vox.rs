@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use super::{Robot, Part};
+
+pub const VOX_MAGIC_NUMBER: u32 = 0x20584F56; // "VOX " read little-endian
+const VOX_VERSION: u32 = 150;
+const VOX_MAX_COLORS: usize = 255; // palette index 0 is reserved for "empty"
+const VOX_MAX_DEPTH: usize = 16; // chunk nesting; real files nest MAIN's children one level deep
+
+// MagicaVoxel's built-in palette, used for models saved without an RGBA chunk.
+// Index 0 is empty, 1-215 are a 6x6x6 colour cube (minus black), then 10-step red, green, blue and grey ramps
+fn default_vox_palette() -> Vec<[u8; 4]> {
+    let mut palette = vec!([0u8, 0, 0, 0]);
+    for r in (0..6u8).rev() {
+        for g in (0..6u8).rev() {
+            for b in (0..6u8).rev() {
+                if r == 0 && g == 0 && b == 0 {
+                    continue;
+                }
+                palette.push([r * 0x33, g * 0x33, b * 0x33, 255]);
+            }
+        }
+    }
+    let ramp = [0xEEu8, 0xDD, 0xBB, 0xAA, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+    ramp.iter().for_each(|x| palette.push([*x, 0, 0, 255]));
+    ramp.iter().for_each(|x| palette.push([0, *x, 0, 255]));
+    ramp.iter().for_each(|x| palette.push([0, 0, *x, 255]));
+    ramp.iter().for_each(|x| palette.push([*x, *x, *x, 255]));
+    palette
+}
+
+fn write_chunk(file: &mut Cursor<Vec<u8>>, id: &[u8; 4], content: &[u8], children: &[u8]) -> Result<(), std::io::Error> {
+    file.write_all(id)?;
+    file.write_all(&u32::to_le_bytes(content.len() as u32))?;
+    file.write_all(&u32::to_le_bytes(children.len() as u32))?;
+    file.write_all(content)?;
+    file.write_all(children)?;
+    Ok(())
+}
+
+struct VoxModel {
+    voxels: Vec<[u8; 4]>, // x, y, z, palette index
+    size: [u32; 3],
+    palette: Option<Vec<[u8; 4]>>
+}
+
+impl VoxModel {
+    // Walks a chunk list, keeping the first model found. Scene-graph chunks (nTRN, nGRP, ...) are skipped
+    fn read_chunks(model: &mut VoxModel, file: &mut Cursor<&[u8]>, end: u64, depth: usize) -> Result<(), std::io::Error> {
+        if depth > VOX_MAX_DEPTH {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Chunks are nested more than {VOX_MAX_DEPTH} deep")));
+        }
+        let mut buf4 = [0u8; 4];
+        while file.position() < end {
+            let mut id = [0u8; 4];
+            file.read_exact(&mut id)?;
+            file.read_exact(&mut buf4)?;
+            let content_size = u32::from_le_bytes(buf4) as u64;
+            file.read_exact(&mut buf4)?;
+            let children_size = u32::from_le_bytes(buf4) as u64;
+            let content_start = file.position();
+
+            match &id {
+                b"SIZE" if model.voxels.is_empty() => {
+                    for i in 0..3 {
+                        file.read_exact(&mut buf4)?;
+                        model.size[i] = u32::from_le_bytes(buf4);
+                    }
+                },
+                b"XYZI" if model.voxels.is_empty() => {
+                    file.read_exact(&mut buf4)?;
+                    let num_voxels = u32::from_le_bytes(buf4);
+                    for _ in 0..num_voxels {
+                        file.read_exact(&mut buf4)?;
+                        model.voxels.push(buf4);
+                    }
+                },
+                b"RGBA" => {
+                    let mut palette = vec!([0u8, 0, 0, 0]);
+                    for _ in 0..VOX_MAX_COLORS {
+                        file.read_exact(&mut buf4)?;
+                        palette.push(buf4);
+                    }
+                    model.palette = Some(palette);
+                },
+                _ => {}
+            }
+
+            file.set_position(content_start + content_size);
+            if children_size > 0 {
+                VoxModel::read_chunks(model, file, content_start + content_size + children_size, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Robot {
+    // Converts a MagicaVoxel model into a robot, one part of type `default_part` per voxel
+    // MagicaVoxel is Z-up and Procelio is Y-up, so the axes are swapped; the model is centered on X/Z
+    pub fn from_vox(data: &[u8], default_part: u32) -> Result<Robot, std::io::Error> {
+        let mut file = Cursor::new(data);
+        let mut buf4 = [0u8; 4];
+        file.read_exact(&mut buf4)?;
+        let magic = u32::from_le_bytes(buf4);
+        if magic != VOX_MAGIC_NUMBER {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Magic number was invalid: {magic}"),
+            ));
+        }
+        file.read_exact(&mut buf4)?; // version; every known version shares the chunk layout
+
+        let mut model = VoxModel { voxels: Vec::new(), size: [0, 0, 0], palette: None };
+        VoxModel::read_chunks(&mut model, &mut file, data.len() as u64, 0)?;
+        if model.voxels.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Vox file contains no voxels"));
+        }
+        let palette = model.palette.unwrap_or_else(default_vox_palette);
+
+        let offset_x = (model.size[0] / 2) as i32;
+        let offset_z = (model.size[1] / 2) as i32;
+        let mut bot = Robot::new();
+        for (i, voxel) in model.voxels.iter().enumerate() {
+            let pos = [voxel[0] as i32 - offset_x, voxel[2] as i32, voxel[1] as i32 - offset_z];
+            let pos = pos.map(i8::try_from);
+            let (pos_x, pos_y, pos_z) = match pos {
+                [Ok(x), Ok(y), Ok(z)] => (x, y, z),
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Voxel {i} does not fit in robot bounds"))),
+            };
+            let color = palette[voxel[3] as usize];
+            bot.parts.push(Part {
+                id: default_part, pos_x, pos_y, pos_z, rotation: 0,
+                color_r: color[0], color_g: color[1], color_b: color[2], alpha_channel: color[3], extra_bytes: Vec::new()
+            });
+        }
+        Ok(bot)
+    }
+
+    // Converts the robot into a single-model MagicaVoxel file, one voxel per occupied cell
+    // Part type, rotation, extra data and cosmetics have no vox equivalent and are dropped
+    pub fn to_vox(self: &Robot) -> Result<Vec<u8>, std::io::Error> {
+        if self.parts.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Robot has no parts"));
+        }
+        let min_x = self.parts.iter().map(|x| x.pos_x as i32).min().unwrap_or(0);
+        let min_y = self.parts.iter().map(|x| x.pos_y as i32).min().unwrap_or(0);
+        let min_z = self.parts.iter().map(|x| x.pos_z as i32).min().unwrap_or(0);
+        let max_x = self.parts.iter().map(|x| x.pos_x as i32).max().unwrap_or(0);
+        let max_y = self.parts.iter().map(|x| x.pos_y as i32).max().unwrap_or(0);
+        let max_z = self.parts.iter().map(|x| x.pos_z as i32).max().unwrap_or(0);
+
+        let mut palette: Vec<[u8; 4]> = Vec::new();
+        let mut palette_lookup: HashMap<[u8; 4], u8> = HashMap::new();
+        let mut occupied = std::collections::HashSet::new();
+        let mut voxels = Vec::new();
+        for part in self.parts.iter() {
+            let pos = [(part.pos_x as i32 - min_x) as u8, (part.pos_z as i32 - min_z) as u8, (part.pos_y as i32 - min_y) as u8];
+            if !occupied.insert(pos) {
+                continue;
+            }
+            let color = [part.color_r, part.color_g, part.color_b, part.alpha_channel];
+            let index = match palette_lookup.get(&color) {
+                Some(i) => *i,
+                None => {
+                    if palette.len() >= VOX_MAX_COLORS {
+                        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Vox palettes can only hold 255 colors"));
+                    }
+                    palette.push(color);
+                    palette_lookup.insert(color, palette.len() as u8);
+                    palette.len() as u8
+                }
+            };
+            voxels.push([pos[0], pos[1], pos[2], index]);
+        }
+        palette.resize(256, [0, 0, 0, 0]);
+
+        let mut size = Vec::new();
+        size.write_all(&u32::to_le_bytes((max_x - min_x + 1) as u32))?;
+        size.write_all(&u32::to_le_bytes((max_z - min_z + 1) as u32))?;
+        size.write_all(&u32::to_le_bytes((max_y - min_y + 1) as u32))?;
+
+        let mut xyzi = Vec::new();
+        xyzi.write_all(&u32::to_le_bytes(voxels.len() as u32))?;
+        voxels.iter().try_for_each(|x| xyzi.write_all(x))?;
+
+        let mut rgba = Vec::new();
+        palette.iter().try_for_each(|x| rgba.write_all(x))?;
+
+        let mut children = Cursor::new(Vec::new());
+        write_chunk(&mut children, b"SIZE", &size, &[])?;
+        write_chunk(&mut children, b"XYZI", &xyzi, &[])?;
+        write_chunk(&mut children, b"RGBA", &rgba, &[])?;
+
+        let mut file = Cursor::new(Vec::new());
+        file.write_all(&u32::to_le_bytes(VOX_MAGIC_NUMBER))?;
+        file.write_all(&u32::to_le_bytes(VOX_VERSION))?;
+        write_chunk(&mut file, b"MAIN", &[], &children.into_inner())?;
+        Ok(file.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deeply_nested_chunks_are_an_error() {
+        let mut chunk = Vec::new();
+        for _ in 0..=VOX_MAX_DEPTH + 1 {
+            let mut outer = Cursor::new(Vec::new());
+            write_chunk(&mut outer, b"nGRP", &[], &chunk).unwrap();
+            chunk = outer.into_inner();
+        }
+        let mut file = Vec::new();
+        file.extend_from_slice(&VOX_MAGIC_NUMBER.to_le_bytes());
+        file.extend_from_slice(&VOX_VERSION.to_le_bytes());
+        file.extend_from_slice(&chunk);
+        let err = Robot::from_vox(&file, 1).err().unwrap();
+        assert!(err.to_string().contains("nested"), "{}", err);
+    }
+
+    fn part(x: i8, y: i8, z: i8, color: [u8; 4]) -> Part {
+        Part { id: 7, pos_x: x, pos_y: y, pos_z: z, rotation: 0, color_r: color[0], color_g: color[1], color_b: color[2], alpha_channel: color[3], extra_bytes: Vec::new() }
+    }
+
+    // (x, y, z) relative to the minimum corner, and colour
+    fn cells(bot: &Robot) -> Vec<((i32, i32, i32), [u8; 4])> {
+        let min = |f: fn(&Part) -> i8| bot.parts.iter().map(|p| i32::from(f(p))).min().unwrap_or(0);
+        let (x, y, z) = (min(|p| p.pos_x), min(|p| p.pos_y), min(|p| p.pos_z));
+        let mut cells: Vec<_> = bot.parts.iter()
+            .map(|p| ((i32::from(p.pos_x) - x, i32::from(p.pos_y) - y, i32::from(p.pos_z) - z), [p.color_r, p.color_g, p.color_b, p.alpha_channel]))
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn vox_round_trip_keeps_cells_and_colors() {
+        let (red, blue, clear) = ([255, 0, 0, 255], [0, 0, 255, 255], [10, 20, 30, 128]);
+        let mut bot = Robot::new();
+        bot.parts = vec!(part(-3, 0, 2, red), part(-2, 0, 2, blue), part(4, 5, -1, red), part(0, 1, 0, clear));
+        let mut with_duplicate = bot.clone();
+        with_duplicate.parts.push(part(4, 5, -1, blue));
+
+        let back = Robot::from_vox(&with_duplicate.to_vox().unwrap(), 7).unwrap();
+        assert_eq!(cells(&back), cells(&bot));
+        assert!(back.parts.iter().all(|p| p.id == 7 && p.rotation == 0));
+    }
+
+    #[test]
+    fn palette_limits_and_defaults() {
+        let mut bot = Robot::new();
+        bot.parts = (0..=VOX_MAX_COLORS as u8).map(|i| part(0, 0, 0, [i, 0, 0, 255])).collect();
+        // every part shares a cell, so only the first colour is used
+        assert!(bot.to_vox().is_ok());
+        for (i, p) in bot.parts.iter_mut().enumerate() {
+            p.pos_y = i as i8;
+        }
+        assert!(bot.to_vox().is_err());
+
+        // no RGBA chunk: indices refer to MagicaVoxel's built-in palette
+        let mut children = Cursor::new(Vec::new());
+        write_chunk(&mut children, b"SIZE", &[1, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0], &[]).unwrap();
+        write_chunk(&mut children, b"XYZI", &[2, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 215], &[]).unwrap();
+        let mut file = Cursor::new(Vec::new());
+        file.write_all(&VOX_MAGIC_NUMBER.to_le_bytes()).unwrap();
+        file.write_all(&VOX_VERSION.to_le_bytes()).unwrap();
+        write_chunk(&mut file, b"MAIN", &[], &children.into_inner()).unwrap();
+        let bot = Robot::from_vox(&file.into_inner(), 1).unwrap();
+        assert_eq!(cells(&bot), vec!(((0, 0, 0), [255, 255, 255, 255]), ((0, 1, 0), [0, 0, 0x33, 255])));
+    }
+}
@@ -0,0 +1,2 @@
+mod registry;
+pub use registry::*;
\ No newline at end of file
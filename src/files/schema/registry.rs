@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+// Typed layouts for the opaque extra-data blobs: Part::extra_bytes, Cosmetic::extra_bytes and
+// StatsFile::cosmetics_bin, keyed by part/cosmetic id. See docs/extradata_schema.md for the file format
+#[derive(Clone, Serialize, Deserialize, Default, Debug)]
+pub struct SchemaRegistry {
+    #[serde(default)]
+    pub parts: BTreeMap<u32, Layout>,
+    #[serde(default)]
+    pub cosmetics: BTreeMap<u32, Layout>,
+    #[serde(default)]
+    pub cosmetic_configs: BTreeMap<u32, Layout>
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Layout {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub fields: Vec<Field>
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Field {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: FieldType
+}
+
+// All multi-byte values are big-endian, like the files themselves
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FieldType {
+    U8 {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<u8>
+    },
+    U16 {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<u16>
+    },
+    Color, // 3 bytes, "#rrggbb"
+    ColorAlpha, // 4 bytes, "#rrggbbaa"
+    Enum { values: BTreeMap<String, u8> }, // 1 byte
+    Bitflags { bits: BTreeMap<String, u8> } // 1 byte, or 2 if any bit index is above 7
+}
+
+fn schema_error(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+fn check_range<T: PartialOrd + std::fmt::Display + Copy>(field: &str, value: T, min: Option<T>, max: Option<T>) -> Result<T, std::io::Error> {
+    if min.is_some_and(|m| value < m) || max.is_some_and(|m| value > m) {
+        let show = |x: Option<T>| x.map(|v| v.to_string()).unwrap_or_default();
+        return Err(schema_error(format!("{field}: {value} is outside {}..={}", show(min), show(max))));
+    }
+    Ok(value)
+}
+
+impl FieldType {
+    pub fn size(&self) -> usize {
+        match self {
+            FieldType::U8 { .. } | FieldType::Enum { .. } => 1,
+            FieldType::U16 { .. } => 2,
+            FieldType::Color => 3,
+            FieldType::ColorAlpha => 4,
+            FieldType::Bitflags { bits } => if bits.values().any(|x| *x > 7) { 2 } else { 1 }
+        }
+    }
+
+    fn decode(&self, field: &str, bytes: &[u8]) -> Result<Value, std::io::Error> {
+        Ok(match self {
+            FieldType::U8 { min, max } => Value::from(check_range(field, bytes[0], *min, *max)?),
+            FieldType::U16 { min, max } => Value::from(check_range(field, u16::from_be_bytes([bytes[0], bytes[1]]), *min, *max)?),
+            FieldType::Color | FieldType::ColorAlpha => Value::from(format!("#{}", hex::encode(bytes))),
+            FieldType::Enum { values } => match values.iter().find(|x| *x.1 == bytes[0]) {
+                Some((name, _)) => Value::from(name.clone()),
+                None => return Err(schema_error(format!("{field}: {} is not a known value", bytes[0])))
+            },
+            FieldType::Bitflags { bits } => {
+                let raw = bytes.iter().fold(0u16, |acc, b| (acc << 8) | u16::from(*b));
+                let known = bits.values().fold(0u16, |acc, b| acc | (1 << b));
+                if raw & !known != 0 {
+                    return Err(schema_error(format!("{field}: unknown bits set in {raw:#x}")));
+                }
+                let mut set: Vec<(&u8, &String)> = bits.iter().filter(|x| raw & (1 << x.1) != 0).map(|x| (x.1, x.0)).collect();
+                set.sort();
+                Value::from(set.into_iter().map(|x| Value::from(x.1.clone())).collect::<Vec<Value>>())
+            }
+        })
+    }
+
+    fn encode(&self, field: &str, value: &Value, out: &mut Vec<u8>) -> Result<(), std::io::Error> {
+        let expected = |what: &str| schema_error(format!("{field}: expected {what}, got {value}"));
+        match self {
+            FieldType::U8 { min, max } => {
+                let v = value.as_u64().and_then(|x| u8::try_from(x).ok()).ok_or_else(|| expected("a number from 0 to 255"))?;
+                out.push(check_range(field, v, *min, *max)?);
+            },
+            FieldType::U16 { min, max } => {
+                let v = value.as_u64().and_then(|x| u16::try_from(x).ok()).ok_or_else(|| expected("a number from 0 to 65535"))?;
+                out.extend_from_slice(&check_range(field, v, *min, *max)?.to_be_bytes());
+            },
+            FieldType::Color | FieldType::ColorAlpha => {
+                let bytes = value.as_str().and_then(|x| x.strip_prefix('#')).and_then(|x| hex::decode(x).ok())
+                    .filter(|x| x.len() == self.size())
+                    .ok_or_else(|| expected(if self.size() == 3 { "\"#rrggbb\"" } else { "\"#rrggbbaa\"" }))?;
+                out.extend_from_slice(&bytes);
+            },
+            FieldType::Enum { values } => {
+                let name = value.as_str().ok_or_else(|| expected("a name"))?;
+                let v = values.get(name).ok_or_else(|| schema_error(format!("{field}: '{name}' is not one of {:?}", values.keys().collect::<Vec<_>>())))?;
+                out.push(*v);
+            },
+            FieldType::Bitflags { bits } => {
+                let names = value.as_array().ok_or_else(|| expected("a list of flag names"))?;
+                let mut raw = 0u16;
+                for name in names.iter() {
+                    let name = name.as_str().ok_or_else(|| expected("a list of flag names"))?;
+                    let bit = bits.get(name).ok_or_else(|| schema_error(format!("{field}: unknown flag '{name}'")))?;
+                    raw |= 1 << bit;
+                }
+                if self.size() == 2 {
+                    out.extend_from_slice(&raw.to_be_bytes());
+                } else {
+                    out.push(raw as u8);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Layout {
+    pub fn size(&self) -> usize {
+        self.fields.iter().map(|x| x.kind.size()).sum()
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Result<Map<String, Value>, std::io::Error> {
+        if bytes.len() != self.size() {
+            return Err(schema_error(format!("expected {} bytes, got {}", self.size(), bytes.len())));
+        }
+        let mut out = Map::new();
+        let mut offset = 0;
+        for field in self.fields.iter() {
+            let size = field.kind.size();
+            out.insert(field.name.clone(), field.kind.decode(&field.name, &bytes[offset..offset + size])?);
+            offset += size;
+        }
+        Ok(out)
+    }
+
+    pub fn encode(&self, values: &Map<String, Value>) -> Result<Vec<u8>, std::io::Error> {
+        if let Some(unknown) = values.keys().find(|k| !self.fields.iter().any(|f| f.name == **k)) {
+            return Err(schema_error(format!("unknown field '{unknown}'")));
+        }
+        let mut out = Vec::with_capacity(self.size());
+        for field in self.fields.iter() {
+            let value = values.get(&field.name).ok_or_else(|| schema_error(format!("missing field '{}'", field.name)))?;
+            field.kind.encode(&field.name, value, &mut out)?;
+        }
+        Ok(out)
+    }
+}
+
+impl TryFrom<&[u8]> for SchemaRegistry {
+    type Error = std::io::Error;
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let registry: SchemaRegistry = serde_json::from_slice(data).map_err(|e| schema_error(format!("Invalid schema definitions: {e}")))?;
+        let sections = [("part", &registry.parts), ("cosmetic", &registry.cosmetics), ("cosmetic config", &registry.cosmetic_configs)];
+        for (section, layouts) in sections {
+            for (id, layout) in layouts.iter() {
+                for (i, field) in layout.fields.iter().enumerate() {
+                    if layout.fields[..i].iter().any(|x| x.name == field.name) {
+                        return Err(schema_error(format!("{section} {id}: duplicate field '{}'", field.name)));
+                    }
+                    if let FieldType::Bitflags { bits } = &field.kind && bits.values().any(|x| *x > 15) {
+                        return Err(schema_error(format!("{section} {id}: {}: bit indices must be 0-15", field.name)));
+                    }
+                }
+                if layout.size() > crate::files::robot::MAX_EXTRADATA_SIZE.into() && section != "cosmetic config" {
+                    return Err(schema_error(format!("{section} {id}: layout is {} bytes, over the 64 byte limit", layout.size())));
+                }
+            }
+        }
+        Ok(registry)
+    }
+}
+
+impl SchemaRegistry {
+    pub fn load(path: &std::path::Path) -> Result<SchemaRegistry, std::io::Error> {
+        SchemaRegistry::try_from(std::fs::read(path)?.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> SchemaRegistry {
+        SchemaRegistry::try_from(br##"{
+            "parts": { "12": { "name": "light", "fields": [
+                { "name": "brightness", "type": "u8", "min": 1, "max": 200 },
+                { "name": "range", "type": "u16" },
+                { "name": "tint", "type": "color" },
+                { "name": "glow", "type": "color_alpha" },
+                { "name": "mode", "type": "enum", "values": { "steady": 0, "blink": 3 } },
+                { "name": "options", "type": "bitflags", "bits": { "shadows": 0, "flare": 9 } }
+            ] } }
+        }"##.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn decode_encode_round_trip() {
+        let layout = &registry().parts[&12];
+        assert_eq!(layout.size(), 13);
+        let bytes = [100, 0x01, 0x02, 0xff, 0x80, 0x00, 0x10, 0x20, 0x30, 0x40, 3, 0x02, 0x01];
+        let values = layout.decode(&bytes).unwrap();
+        assert_eq!(values["range"], 258);
+        assert_eq!(values["tint"], "#ff8000");
+        assert_eq!(values["glow"], "#10203040");
+        assert_eq!(values["mode"], "blink");
+        assert_eq!(values["options"], serde_json::json!(["shadows", "flare"]));
+        assert_eq!(layout.encode(&values).unwrap(), bytes);
+    }
+
+    #[test]
+    fn bad_bytes_and_values_are_errors() {
+        let layout = &registry().parts[&12];
+        let bytes = [100, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert!(layout.decode(&bytes[..12]).is_err());
+        let mut bad = bytes;
+        bad[0] = 201;
+        assert!(layout.decode(&bad).is_err());
+        bad = bytes;
+        bad[10] = 1;
+        assert!(layout.decode(&bad).is_err());
+        bad = bytes;
+        bad[11] = 0x04;
+        assert!(layout.decode(&bad).is_err());
+
+        let values = layout.decode(&bytes).unwrap();
+        let mut edited = values.clone();
+        edited.insert("tint".to_owned(), Value::from("#ff80"));
+        assert!(layout.encode(&edited).is_err());
+        edited = values.clone();
+        edited.insert("extra".to_owned(), Value::from(1));
+        assert!(layout.encode(&edited).is_err());
+        edited = values;
+        edited.remove("mode");
+        assert!(layout.encode(&edited).is_err());
+    }
+
+    #[test]
+    fn bad_definitions_are_rejected() {
+        let duplicate = br#"{ "parts": { "1": { "fields": [ { "name": "a", "type": "u8" }, { "name": "a", "type": "u8" } ] } } }"#;
+        assert!(SchemaRegistry::try_from(duplicate.as_slice()).is_err());
+        let high_bit = br#"{ "cosmetics": { "1": { "fields": [ { "name": "a", "type": "bitflags", "bits": { "x": 16 } } ] } } }"#;
+        assert!(SchemaRegistry::try_from(high_bit.as_slice()).is_err());
+    }
+}
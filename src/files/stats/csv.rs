@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use super::flags::FlagRegistry;
+use super::statfile::{JsonAttackStats, JsonBlockStats, JsonCosmeticStats, JsonStatsFile};
+
+// Spreadsheet form of a JsonStatsFile: one CSV per section, "id,name" then a column per flag spelling.
+// An empty cell means the entity doesn't have that flag. Cosmetics end with "config" (hex) and,
+// if any cosmetic has decoded fields, "config_fields" (JSON)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatsCsv {
+    pub blocks: String,
+    pub attacks: String,
+    pub cosmetics: String
+}
+
+pub const BLOCKS_CSV: &str = "blocks.csv";
+pub const ATTACKS_CSV: &str = "attacks.csv";
+pub const COSMETICS_CSV: &str = "cosmetics.csv";
+
+const CONFIG_COLUMN: &str = "config";
+const CONFIG_FIELDS_COLUMN: &str = "config_fields";
+
+struct Row {
+    id: u32,
+    name: String,
+    flags: BTreeMap<String, i32>,
+    extra: Vec<String> // one cell per extra column
+}
+
+fn invalid(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) || cell.trim() != cell {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_owned()
+    }
+}
+
+// Rows stay in file order and flag columns are ordered by flag id, so re-exporting an unchanged file gives the same text
+fn write_section(registry: &FlagRegistry, rows: &[Row], extra: &[&str]) -> String {
+    let mut columns: Vec<&String> = rows.iter().flat_map(|x| x.flags.keys()).collect::<HashSet<_>>().into_iter().collect();
+    columns.sort_by_key(|x| (registry.id_of(x).map(u16::from).unwrap_or(u16::MAX), x.as_str()));
+    let mut out = String::new();
+    let header: Vec<String> = ["id", "name"].iter().map(|x| x.to_string())
+        .chain(columns.iter().map(|x| quote(x)))
+        .chain(extra.iter().map(|x| x.to_string())).collect();
+    out.push_str(&header.join(","));
+    out.push('\n');
+    for row in rows.iter() {
+        let cells: Vec<String> = [row.id.to_string(), quote(&row.name)].into_iter()
+            .chain(columns.iter().map(|x| row.flags.get(*x).map(|v| v.to_string()).unwrap_or_default()))
+            .chain(row.extra.iter().map(|x| quote(x))).collect();
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+// RFC 4180-ish: quoted cells may hold commas, quotes and newlines; \r\n and a leading BOM are accepted
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => { chars.next(); cell.push('"'); },
+                '"' => quoted = false,
+                _ => cell.push(c)
+            }
+            continue;
+        }
+        match c {
+            '"' if cell.is_empty() => quoted = true,
+            ',' => record.push(std::mem::take(&mut cell)),
+            '\r' if chars.peek() == Some(&'\n') => {},
+            '\n' => {
+                record.push(std::mem::take(&mut cell));
+                records.push(std::mem::take(&mut record));
+            },
+            _ => cell.push(c)
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted cell".to_owned());
+    }
+    if !cell.is_empty() || !record.is_empty() {
+        record.push(cell);
+        records.push(record);
+    }
+    // spreadsheets like to pad with empty rows
+    records.retain(|x| x.iter().any(|c| !c.trim().is_empty()));
+    Ok(records)
+}
+
+fn read_section(registry: &FlagRegistry, file: &str, text: &str, extra: &[&str]) -> Result<Vec<Row>, std::io::Error> {
+    let records = parse_csv(text).map_err(|e| invalid(format!("{file}: {e}")))?;
+    let Some((header, records)) = records.split_first() else {
+        return Ok(Vec::new());
+    };
+    let header: Vec<&str> = header.iter().map(|x| x.trim()).collect();
+    if header.len() < 2 || header[0] != "id" || header[1] != "name" {
+        return Err(invalid(format!("{file}: the first two columns must be 'id' and 'name'")));
+    }
+    let mut seen = HashSet::new();
+    let mut flag_columns = Vec::new();
+    let mut extra_columns = HashMap::new();
+    for (i, column) in header.iter().enumerate().skip(2) {
+        if !seen.insert(*column) {
+            return Err(invalid(format!("{file}: column '{column}' appears twice")));
+        }
+        if let Some(e) = extra.iter().position(|x| x == column) {
+            extra_columns.insert(e, i);
+            continue;
+        }
+        match registry.id_of(column) {
+            None => return Err(invalid(format!("{file}: column '{column}' is not a known flag"))),
+            Some(flag) => flag_columns.push((i, *column, flag))
+        }
+    }
+
+    let mut rows = Vec::new();
+    let mut ids = HashSet::new();
+    for (n, record) in records.iter().enumerate() {
+        // 1-based, after the header
+        let line = n + 2;
+        let fail = |msg: String| invalid(format!("{file} row {line}: {msg}"));
+        if record.iter().skip(header.len()).any(|x| !x.trim().is_empty()) {
+            return Err(fail("more cells than columns".to_owned()));
+        }
+        let cell = |i: usize| record.get(i).map(|x| x.trim()).unwrap_or("");
+        let id: u32 = cell(0).parse().map_err(|_| fail(format!("id '{}' is not a number", cell(0))))?;
+        if !ids.insert(id) {
+            return Err(fail(format!("id {id} appears twice")));
+        }
+        let mut flags = BTreeMap::new();
+        let mut flag_ids = HashMap::new();
+        for (i, column, flag) in flag_columns.iter() {
+            if cell(*i).is_empty() {
+                continue;
+            }
+            let value: i32 = cell(*i).parse().map_err(|_| fail(format!("{column} '{}' is not a whole number", cell(*i))))?;
+            if let Some(other) = flag_ids.insert(*flag, *column) {
+                return Err(fail(format!("'{other}' and '{column}' are the same flag")));
+            }
+            flags.insert(column.to_string(), value);
+        }
+        let extra = (0..extra.len()).map(|e| extra_columns.get(&e).map(|i| cell(*i).to_owned()).unwrap_or_default()).collect();
+        rows.push(Row { id, name: record.get(1).cloned().unwrap_or_default(), flags, extra });
+    }
+    Ok(rows)
+}
+
+impl JsonStatsFile {
+    pub fn to_csv(self: &JsonStatsFile, registry: &FlagRegistry) -> StatsCsv {
+        let plain = |id: u32, name: &String, flags: &BTreeMap<String, i32>| Row { id, name: name.clone(), flags: flags.clone(), extra: Vec::new() };
+        let blocks: Vec<Row> = self.blocks.iter().map(|x| plain(x.id, &x.name, &x.flags)).collect();
+        let attacks: Vec<Row> = self.attacks.iter().map(|x| plain(x.id, &x.name, &x.flags)).collect();
+        let with_fields = self.cosmetics.iter().any(|x| x.config_fields.is_some());
+        let cosmetics: Vec<Row> = self.cosmetics.iter().map(|x| {
+            let mut row = plain(x.id, &x.name, &x.flags);
+            row.extra.push(hex::encode(&x.config));
+            if with_fields {
+                row.extra.push(x.config_fields.as_ref().map(|f| serde_json::Value::Object(f.clone()).to_string()).unwrap_or_default());
+            }
+            row
+        }).collect();
+        let cosmetic_columns: &[&str] = if with_fields { &[CONFIG_COLUMN, CONFIG_FIELDS_COLUMN] } else { &[CONFIG_COLUMN] };
+        StatsCsv {
+            blocks: write_section(registry, &blocks, &[]),
+            attacks: write_section(registry, &attacks, &[]),
+            cosmetics: write_section(registry, &cosmetics, cosmetic_columns)
+        }
+    }
+
+    // Every flag column must be a name the registry knows
+    pub fn from_csv(csv: &StatsCsv, registry: &FlagRegistry) -> Result<JsonStatsFile, std::io::Error> {
+        let blocks = read_section(registry, BLOCKS_CSV, &csv.blocks, &[])?;
+        let attacks = read_section(registry, ATTACKS_CSV, &csv.attacks, &[])?;
+        let cosmetics = read_section(registry, COSMETICS_CSV, &csv.cosmetics, &[CONFIG_COLUMN, CONFIG_FIELDS_COLUMN])?;
+        let cosmetics = cosmetics.into_iter().map(|row| {
+            let fail = |msg: String| invalid(format!("{COSMETICS_CSV}: cosmetic {}: {}", row.id, msg));
+            let config = hex::decode(&row.extra[0]).map_err(|e| fail(format!("config is not hex: {e}")))?;
+            let config_fields = match row.extra[1].as_str() {
+                "" => None,
+                text => match serde_json::from_str(text) {
+                    Ok(serde_json::Value::Object(fields)) => Some(fields),
+                    _ => return Err(fail("config_fields is not a JSON object".to_owned()))
+                }
+            };
+            Ok(JsonCosmeticStats { id: row.id, name: row.name, flags: row.flags, config, config_fields })
+        }).collect::<Result<Vec<_>, std::io::Error>>()?;
+        Ok(JsonStatsFile {
+            blocks: blocks.into_iter().map(|x| JsonBlockStats { id: x.id, name: x.name, flags: x.flags }).collect(),
+            attacks: attacks.into_iter().map(|x| JsonAttackStats { id: x.id, name: x.name, flags: x.flags }).collect(),
+            cosmetics
+        })
+    }
+}
+
+impl StatsCsv {
+    pub fn write_dir(self: &StatsCsv, dir: &Path) -> Result<(), std::io::Error> {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(BLOCKS_CSV), &self.blocks)?;
+        std::fs::write(dir.join(ATTACKS_CSV), &self.attacks)?;
+        std::fs::write(dir.join(COSMETICS_CSV), &self.cosmetics)
+    }
+
+    // A missing file is an empty section
+    pub fn read_dir(dir: &Path) -> Result<StatsCsv, std::io::Error> {
+        if !dir.is_dir() {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} is not a directory", dir.display())));
+        }
+        let read = |name: &str| match std::fs::read_to_string(dir.join(name)) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            other => other
+        };
+        Ok(StatsCsv { blocks: read(BLOCKS_CSV)?, attacks: read(ATTACKS_CSV)?, cosmetics: read(COSMETICS_CSV)? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::stats::statfile::StatsFile;
+
+    fn sample() -> JsonStatsFile {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/statfile.json")).unwrap();
+        serde_json::from_slice(&data).unwrap()
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let registry = FlagRegistry::default();
+        let csv = sample().to_csv(&registry);
+        let imported = JsonStatsFile::from_csv(&csv, &registry).unwrap();
+        assert_eq!(imported.to_csv(&registry), csv);
+        let binary = StatsFile::from_json(sample(), &registry).compile().unwrap();
+        assert_eq!(StatsFile::from_json(imported, &registry).compile().unwrap(), binary);
+    }
+
+    #[test]
+    fn bad_csv_is_an_error() {
+        let registry = FlagRegistry::default();
+        let mut csv = sample().to_csv(&registry);
+        csv.blocks = "id,name,notAFlag\n1,Core,5\n".to_owned();
+        assert!(JsonStatsFile::from_csv(&csv, &registry).is_err());
+        csv.blocks = "id,name,health\n1,Core,lots\n".to_owned();
+        assert!(JsonStatsFile::from_csv(&csv, &registry).is_err());
+    }
+}
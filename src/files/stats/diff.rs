@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use super::flags::FlagRegistry;
+use super::statfile::{EntityNames, FlagStats, StatsFile};
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FlagChange {
+    pub section: String,
+    pub id: u32,
+    pub name: String,
+    pub flag: String,
+    pub old: Option<i32>, // None: the flag was added
+    pub new: Option<i32> // None: the flag was removed
+}
+
+impl FlagChange {
+    pub fn delta(&self) -> Option<i64> {
+        Some(i64::from(self.new?) - i64::from(self.old?))
+    }
+
+    // Relative change in percent; None if the flag was added/removed or the old value was 0
+    pub fn percent(&self) -> Option<f64> {
+        let old = self.old.filter(|x| *x != 0)?;
+        Some(self.delta()? as f64 * 100.0 / f64::from(old).abs())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct EntityChange {
+    pub section: String,
+    pub id: u32,
+    pub name: String
+}
+
+// Everything that differs between two statfiles, entity by entity and flag by flag
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct StatsDiff {
+    pub added: Vec<EntityChange>,
+    pub removed: Vec<EntityChange>,
+    pub changed: Vec<FlagChange>,
+    pub configs_changed: Vec<EntityChange> // cosmetic config bytes
+}
+
+fn name_in(names: &[EntityNames], id: u32) -> Option<&str> {
+    names.iter().find(|x| x.id == id).map(|x| x.name.as_str()).filter(|x| !x.is_empty())
+}
+
+fn flag_name_in(names: &[EntityNames], id: u32, flag: u8) -> Option<String> {
+    names.iter().find(|x| x.id == id).and_then(|x| x.flag_names.get(&flag)).cloned()
+}
+
+impl StatsDiff {
+    // Drops flag changes smaller than min_abs or min_percent; added/removed flags are always kept
+    pub fn filter(&mut self, min_abs: i64, min_percent: f64) {
+        self.changed.retain(|c| match c.delta() {
+            None => true,
+            Some(d) => d.abs() >= min_abs && c.percent().is_none_or(|p| p.abs() >= min_percent)
+        });
+    }
+}
+
+// name, old stats, new stats, old names, new names
+type Section<'a> = (&'a str, &'a FlagStats, &'a FlagStats, &'a [EntityNames], &'a [EntityNames]);
+
+impl StatsFile {
+    // Entity names and flag spellings only exist in statfile JSON and version 5 binaries; version 4 binaries
+    // have none, so their entities get an empty name. `registry` names flags that neither file kept a spelling for
+    pub fn diff(self: &StatsFile, new: &StatsFile, registry: &FlagRegistry) -> StatsDiff {
+        let mut diff = StatsDiff::default();
+        let sections: [Section; 3] = [
+            ("block", &self.blocks, &new.blocks, &self.names.blocks, &new.names.blocks),
+            ("attack", &self.attacks, &new.attacks, &self.names.attacks, &new.names.attacks),
+            ("cosmetic", &self.cosmetics, &new.cosmetics, &self.names.cosmetics, &new.names.cosmetics)
+        ];
+        for (section, old_stats, new_stats, old_names, new_names) in sections {
+            let ids: BTreeSet<u32> = old_stats.data.keys().chain(new_stats.data.keys()).copied().collect();
+            for id in ids {
+                let name = name_in(new_names, id).or_else(|| name_in(old_names, id)).unwrap_or_default().to_owned();
+                let entity = || EntityChange { section: section.to_owned(), id, name: name.clone() };
+                let (before, after) = match (old_stats.data.get(&id), new_stats.data.get(&id)) {
+                    (None, _) => { diff.added.push(entity()); continue; },
+                    (_, None) => { diff.removed.push(entity()); continue; },
+                    (Some(o), Some(n)) => (o, n)
+                };
+                let flags: BTreeSet<u8> = before.keys().chain(after.keys()).copied().collect();
+                for flag in flags {
+                    let (o, n) = (before.get(&flag).copied(), after.get(&flag).copied());
+                    if o != n {
+                        let flag = flag_name_in(new_names, id, flag).or_else(|| flag_name_in(old_names, id, flag)).unwrap_or_else(|| registry.name_of(flag));
+                        diff.changed.push(FlagChange { section: section.to_owned(), id, name: name.clone(), flag, old: o, new: n });
+                    }
+                }
+                let config = |stats: &StatsFile| stats.cosmetics_bin.data.get(&id).cloned().unwrap_or_default();
+                if section == "cosmetic" && config(self) != config(new) {
+                    diff.configs_changed.push(entity());
+                }
+            }
+        }
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::stats::statfile::{COST_FLAG, HEALTH_FLAG, MASS_FLAG};
+
+    fn stats(blocks: &[(u32, &[(u8, i32)])]) -> StatsFile {
+        let mut stats = StatsFile::new();
+        for (id, flags) in blocks {
+            stats.blocks.data.insert(*id, flags.iter().copied().collect());
+        }
+        stats
+    }
+
+    fn change(flag: &str, old: Option<i32>, new: Option<i32>) -> FlagChange {
+        FlagChange { section: "block".to_owned(), id: 1, name: String::new(), flag: flag.to_owned(), old, new }
+    }
+
+    #[test]
+    fn entities_and_flags_are_compared() {
+        let old = stats(&[(1, &[(HEALTH_FLAG, 100), (MASS_FLAG, 5)]), (2, &[])]);
+        let new = stats(&[(1, &[(HEALTH_FLAG, 150), (COST_FLAG, 0)]), (3, &[])]);
+        let diff = old.diff(&new, &FlagRegistry::default());
+        assert_eq!(diff.added, vec!(EntityChange { section: "block".to_owned(), id: 3, name: String::new() }));
+        assert_eq!(diff.removed, vec!(EntityChange { section: "block".to_owned(), id: 2, name: String::new() }));
+        assert_eq!(diff.changed, vec!(
+            change("health", Some(100), Some(150)),
+            change("mass", Some(5), None),
+            change("cost", None, Some(0))
+        ));
+        assert!(old.diff(&old, &FlagRegistry::default()) == StatsDiff::default());
+    }
+
+    #[test]
+    fn percent_needs_a_nonzero_old_value() {
+        assert_eq!(change("health", Some(-200), Some(-100)).percent(), Some(50.0));
+        assert_eq!(change("health", Some(0), Some(10)).percent(), None);
+        assert_eq!(change("health", None, Some(10)).percent(), None);
+        assert_eq!(change("health", Some(0), Some(10)).delta(), Some(10));
+    }
+
+    #[test]
+    fn filter_keeps_large_and_added_changes() {
+        let mut diff = StatsDiff { changed: vec!(
+            change("a", Some(100), Some(101)), // 1, 1%
+            change("b", Some(100), Some(150)), // 50, 50%
+            change("c", Some(10000), Some(10100)), // 100, 1%
+            change("d", Some(0), Some(3)), // 3, no percent
+            change("e", None, Some(1))
+        ), ..Default::default() };
+        diff.filter(2, 5.0);
+        let kept: Vec<&str> = diff.changed.iter().map(|x| x.flag.as_str()).collect();
+        assert_eq!(kept, vec!("b", "d", "e"));
+    }
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use super::statfile::*;
+
+// Names like "flag37" always mean flag byte 37, so ids the registry doesn't know still round-trip
+const UNKNOWN_PREFIX: &str = "flag";
+
+// One statfile flag: the byte stored in .stats files and the names it goes by in JSON
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FlagDef {
+    pub id: u8,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>, // any name starting with this also means the flag, e.g. "spec1_" for "spec1_reloadMs"
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub unit: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<i32>
+}
+
+// The definitions file: entries replace the built-in flag with the same id, or add new ones
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct JsonFlagDefinitions {
+    pub flags: Vec<FlagDef>
+}
+
+#[derive(Clone, Debug)]
+pub struct FlagRegistry {
+    flags: Vec<FlagDef>, // sorted by id
+    names: HashMap<String, u8> // names and aliases
+}
+
+// Built-in flags have no range; min/max only come from a definitions file
+fn builtin(id: u8, name: &str, description: &str, unit: &str) -> FlagDef {
+    FlagDef {
+        id, name: name.to_owned(), aliases: Vec::new(), prefix: None,
+        description: description.to_owned(), unit: unit.to_owned(), min: None, max: None
+    }
+}
+
+impl Default for FlagRegistry {
+    fn default() -> Self {
+        let mut flags = vec!(
+            builtin(HEALTH_FLAG, "health", "Hit points", "hp"),
+            builtin(MASS_FLAG, "mass", "Mass", ""),
+            builtin(COST_FLAG, "cost", "Purchase price", "currency"),
+            builtin(RANKING_FLAG, "roboRanking", "Contribution to the bot's matchmaking rank", ""),
+            builtin(COMPLEXITY_FLAG, "cpuCost", "CPU used by the part", "cpu"),
+            builtin(THRUST_FLAG, "thrust", "Thrust", ""),
+            builtin(ROTATION_FLAG, "rotationSpeed", "Rotation speed", ""),
+            builtin(DAMAGE_FLAG, "damage", "Damage per hit", "hp"),
+            builtin(PREMIUM_COST_FLAG, "premiumCost", "Purchase price in premium currency", "premium currency"),
+            builtin(SHIELD_FLAG, "shield", "Shield HP", "hp"),
+            builtin(SHIELD_CHARGE_RATE_FLAG, "shieldCharge", "Shield recharge rate", "hp/s"),
+            builtin(SHIELD_CHARGE_DELAY_FLAG, "shieldChargeDelay", "Delay after damage before the shield recharges", "ms"),
+            builtin(USABILITY_HEALTH, "functionHealth", "How much \"usable\" HP there is (e.g. tesla blade charges)", "hp"),
+            builtin(LIFT_FLAG, "lift", "Lift", ""),
+            builtin(COSMETIC_COMPLEXITY_FLAG, "cosmCmplx", "Cosmetic complexity", ""),
+            builtin(MODIFIER_COST, "cosmeticCost", "Cosmetic purchase price", "currency"),
+            builtin(MODIFIER_PREMIUM_COST, "cosmeticPremiumCost", "Cosmetic purchase price in premium currency", "premium currency")
+        );
+        for n in SPECIAL_FLAG_0..=SPECIAL_FLAG_19 {
+            let index = n - SPECIAL_FLAG_0;
+            let mut def = builtin(n, &format!("spec{index}"), &format!("Special per-part usage {index}"), "");
+            def.prefix = Some(format!("spec{index}_"));
+            flags.push(def);
+        }
+        FlagRegistry::from_defs(flags).expect("built-in flags are consistent")
+    }
+}
+
+impl FlagRegistry {
+    fn from_defs(mut flags: Vec<FlagDef>) -> Result<FlagRegistry, std::io::Error> {
+        let invalid = |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+        flags.sort_by_key(|x| x.id);
+        let mut names = HashMap::new();
+        for (i, def) in flags.iter().enumerate() {
+            if i > 0 && flags[i - 1].id == def.id {
+                return Err(invalid(format!("Flag {} is defined twice", def.id)));
+            }
+            if let (Some(min), Some(max)) = (def.min, def.max) && min > max {
+                return Err(invalid(format!("Flag {}: min {} is above max {}", def.name, min, max)));
+            }
+            for name in std::iter::once(&def.name).chain(def.aliases.iter()) {
+                if name.is_empty() || name.starts_with(UNKNOWN_PREFIX) && name[UNKNOWN_PREFIX.len()..].parse::<u8>().is_ok() {
+                    return Err(invalid(format!("Flag {}: '{}' can't be used as a name", def.id, name)));
+                }
+                if let Some(other) = names.insert(name.clone(), def.id) {
+                    return Err(invalid(format!("Flag name '{}' is used by both {} and {}", name, other, def.id)));
+                }
+            }
+        }
+        Ok(FlagRegistry { flags, names })
+    }
+
+    // Built-in flags plus (and overridden by) the ones in a definitions file
+    pub fn with_definitions(definitions: JsonFlagDefinitions) -> Result<FlagRegistry, std::io::Error> {
+        let mut flags = FlagRegistry::default().flags;
+        for def in definitions.flags.into_iter() {
+            flags.retain(|x| x.id != def.id);
+            flags.push(def);
+        }
+        FlagRegistry::from_defs(flags)
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<FlagRegistry, std::io::Error> {
+        let defs: JsonFlagDefinitions = serde_json::from_slice(&std::fs::read(path)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        FlagRegistry::with_definitions(defs)
+    }
+
+    pub fn flags(&self) -> &[FlagDef] {
+        &self.flags
+    }
+
+    pub fn get(&self, id: u8) -> Option<&FlagDef> {
+        self.flags.binary_search_by_key(&id, |x| x.id).ok().map(|i| &self.flags[i])
+    }
+
+    // Canonical name, or "flagN" for ids the registry doesn't know
+    pub fn name_of(&self, id: u8) -> String {
+        self.get(id).map(|x| x.name.clone()).unwrap_or_else(|| format!("{UNKNOWN_PREFIX}{id}"))
+    }
+
+    // Accepts canonical names, aliases, prefixed names and "flagN"
+    pub fn id_of(&self, name: &str) -> Option<u8> {
+        if let Some(id) = self.names.get(name) {
+            return Some(*id);
+        }
+        if let Some(id) = name.strip_prefix(UNKNOWN_PREFIX).and_then(|x| x.parse().ok()) {
+            return Some(id);
+        }
+        self.flags.iter()
+            .filter(|x| x.prefix.as_ref().is_some_and(|p| name.starts_with(p.as_str())))
+            .max_by_key(|x| x.prefix.as_ref().map(|p| p.len()))
+            .map(|x| x.id)
+    }
+
+    // Why a value is outside its flag's range, if it is
+    pub fn check(&self, id: u8, value: i32) -> Option<String> {
+        let def = self.get(id)?;
+        match (def.min, def.max) {
+            (Some(min), _) if value < min => Some(format!("{} is {}, below the minimum {}", def.name, value, min)),
+            (_, Some(max)) if value > max => Some(format!("{} is {}, above the maximum {}", def.name, value, max)),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn def(id: u8, name: &str) -> FlagDef {
+        FlagDef { id, name: name.to_owned(), aliases: Vec::new(), prefix: None, description: String::new(), unit: String::new(), min: None, max: None }
+    }
+
+    #[test]
+    fn names_and_prefixes_resolve() {
+        let registry = FlagRegistry::default();
+        assert_eq!(registry.id_of("health"), Some(HEALTH_FLAG));
+        assert_eq!(registry.id_of("spec1_reloadMs"), Some(SPECIAL_FLAG_0 + 1));
+        assert_eq!(registry.id_of("spec10_reloadMs"), Some(SPECIAL_FLAG_0 + 10));
+        assert_eq!(registry.id_of("spec1"), Some(SPECIAL_FLAG_0 + 1));
+        assert_eq!(registry.id_of("spec"), None);
+        assert_eq!(registry.id_of("healthy"), None);
+    }
+
+    #[test]
+    fn every_id_round_trips_through_its_name() {
+        let registry = FlagRegistry::default();
+        for id in 0..=u8::MAX {
+            assert_eq!(registry.id_of(&registry.name_of(id)), Some(id), "{id}");
+            assert_eq!(registry.id_of(&format!("flag{id}")), Some(id));
+        }
+        assert_eq!(registry.id_of("flag256"), None);
+    }
+
+    #[test]
+    fn definitions_override_and_are_checked() {
+        let mut hp = def(HEALTH_FLAG, "hp");
+        hp.aliases.push("health".to_owned());
+        hp.min = Some(1);
+        let registry = FlagRegistry::with_definitions(JsonFlagDefinitions { flags: vec!(hp, def(200, "armor")) }).unwrap();
+        assert_eq!(registry.name_of(HEALTH_FLAG), "hp");
+        assert_eq!(registry.id_of("health"), Some(HEALTH_FLAG));
+        assert_eq!(registry.id_of("armor"), Some(200));
+        assert!(registry.check(HEALTH_FLAG, 0).is_some());
+        assert!(registry.check(HEALTH_FLAG, 1).is_none());
+        assert!(FlagRegistry::default().check(HEALTH_FLAG, -5).is_none());
+
+        for bad in [def(200, "flag7"), def(200, "mass"), def(200, "")] {
+            assert!(FlagRegistry::with_definitions(JsonFlagDefinitions { flags: vec!(bad) }).is_err());
+        }
+        assert!(FlagRegistry::from_defs(vec!(def(200, "a"), def(200, "b"))).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use super::flags::FlagRegistry;
+use super::template::ENTITY_KEYS;
+
+// (key in statfile JSON, name used by rules and issues)
+const SECTIONS: [(&str, &str); 3] = [("blocks", "block"), ("attacks", "attack"), ("cosmetics", "cosmetic")];
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    #[default]
+    Error
+}
+
+// Entities in `section` ("block", "attack" or "cosmetic"; every section if absent) with ids in `ids` (inclusive) must have `flags`
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RequiredRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ids: Option<[u32; 2]>,
+    pub flags: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity
+}
+
+// `flag` must be within [min, max] where it is set; with `when`, only on entities that have that flag
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BoundsRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ids: Option<[u32; 2]>,
+    pub flag: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    #[serde(default)]
+    pub severity: Severity
+}
+
+// A rules file replaces the built-in rules key by key; keys it leaves out keep their defaults.
+// Duplicate ids, unknown flags, one flag under two names and values outside the flag registry's range are always errors
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintRules {
+    pub required: Vec<RequiredRule>,
+    pub bounds: Vec<BoundsRule>,
+    pub cosmetic_config: Option<Severity>, // cosmetics with no "config"; null turns the check off
+    pub duplicate_names: Option<Severity>, // two entities in a section with the same name
+    pub unique_across: Vec<Vec<String>> // sections whose ids must not collide, e.g. [["block", "cosmetic"]]
+}
+
+impl Default for LintRules {
+    fn default() -> Self {
+        let required = |section: &str, flags: &[&str]| RequiredRule {
+            section: Some(section.to_owned()), ids: None, flags: flags.iter().map(|x| x.to_string()).collect(), severity: Severity::Error
+        };
+        let bounds = |flag: &str, min: i32, when: Option<&str>| BoundsRule {
+            section: Some("block".to_owned()), ids: None, flag: flag.to_owned(), min: Some(min), max: None,
+            when: when.map(|x| x.to_owned()), severity: Severity::Error
+        };
+        LintRules {
+            required: vec!(required("block", &["health", "mass", "cpuCost"]), required("attack", &["damage"])),
+            bounds: vec!(bounds("health", 1, None), bounds("mass", 1, Some("thrust")), bounds("mass", 1, Some("lift"))),
+            cosmetic_config: Some(Severity::Error),
+            duplicate_names: Some(Severity::Warning),
+            unique_across: Vec::new()
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LintIssue {
+    pub severity: Severity,
+    pub section: String,
+    pub id: Option<u32>,
+    pub name: String,
+    pub message: String
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity { Severity::Warning => "warning", Severity::Error => "error" };
+        match self.id {
+            None => write!(f, "{}: {}: {}", severity, self.section, self.message),
+            Some(id) if self.name.is_empty() => write!(f, "{}: {} {}: {}", severity, self.section, id, self.message),
+            Some(id) => write!(f, "{}: {} {} ({}): {}", severity, self.section, id, self.name, self.message)
+        }
+    }
+}
+
+struct Entity<'a> {
+    section: &'static str,
+    id: Option<u32>,
+    name: &'a str,
+    flags: HashMap<u8, (&'a str, i32)>,
+    has_config: bool
+}
+
+fn covers(section: &Option<String>, ids: &Option<[u32; 2]>, entity: &Entity) -> bool {
+    section.as_ref().is_none_or(|x| x == entity.section)
+        && ids.is_none_or(|[lo, hi]| entity.id.is_some_and(|id| lo <= id && id <= hi))
+}
+
+impl LintRules {
+    // Rule flags must be known to the registry and sections must exist
+    pub fn validate(self: &LintRules, registry: &FlagRegistry) -> Result<(), std::io::Error> {
+        let invalid = |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, msg);
+        let section = |s: &str| match SECTIONS.iter().any(|x| x.1 == s) {
+            true => Ok(()),
+            false => Err(invalid(format!("'{s}' is not a section; use block, attack or cosmetic")))
+        };
+        let flag = |f: &str| registry.id_of(f).map(|_| ()).ok_or_else(|| invalid(format!("'{f}' is not a known flag")));
+        for rule in self.required.iter() {
+            rule.section.iter().try_for_each(|x| section(x))?;
+            rule.flags.iter().try_for_each(|x| flag(x))?;
+        }
+        for rule in self.bounds.iter() {
+            rule.section.iter().try_for_each(|x| section(x))?;
+            flag(&rule.flag)?;
+            rule.when.iter().try_for_each(|x| flag(x))?;
+            if let (Some(min), Some(max)) = (rule.min, rule.max) && min > max {
+                return Err(invalid(format!("{}: min {} is above max {}", rule.flag, min, max)));
+            }
+        }
+        self.unique_across.iter().flatten().try_for_each(|x| section(x))
+    }
+
+    // Checks statfile JSON (after templates are resolved), which still has the duplicates and
+    // unknown flags that converting to a StatsFile would drop
+    pub fn lint(self: &LintRules, doc: &Value, registry: &FlagRegistry) -> Vec<LintIssue> {
+        let mut issues = Vec::new();
+        let mut entities = Vec::new();
+        for (key, section) in SECTIONS {
+            let issue = |severity, id, name: &str, message: String| LintIssue { severity, section: section.to_owned(), id, name: name.to_owned(), message };
+            let Some(list) = doc.get(key) else {
+                issues.push(issue(Severity::Error, None, "", format!("no \"{key}\" list")));
+                continue;
+            };
+            let Some(list) = list.as_array() else {
+                issues.push(issue(Severity::Error, None, "", format!("\"{key}\" is not a list")));
+                continue;
+            };
+            for (index, value) in list.iter().enumerate() {
+                let Some(obj) = value.as_object() else {
+                    issues.push(issue(Severity::Error, None, "", format!("entry {index} is not an object")));
+                    continue;
+                };
+                let id = obj.get("id").and_then(|x| x.as_u64()).and_then(|x| u32::try_from(x).ok());
+                let name = obj.get("name").and_then(|x| x.as_str()).unwrap_or("");
+                if id.is_none() {
+                    issues.push(issue(Severity::Error, None, name, format!("entry {index} has no valid id")));
+                }
+                if !obj.get("name").is_some_and(|x| x.is_string()) {
+                    issues.push(issue(Severity::Error, id, name, "has no name".to_owned()));
+                }
+                let mut flags: HashMap<u8, (&str, i32)> = HashMap::new();
+                let mut keys: Vec<&String> = obj.keys().filter(|x| !ENTITY_KEYS.contains(&x.as_str())).collect();
+                keys.sort();
+                for key in keys {
+                    let Some(value) = obj[key].as_i64().and_then(|x| i32::try_from(x).ok()) else {
+                        issues.push(issue(Severity::Error, id, name, format!("{key} is {}, not a whole 32-bit number", obj[key])));
+                        continue;
+                    };
+                    let Some(flag) = registry.id_of(key) else {
+                        issues.push(issue(Severity::Error, id, name, format!("unknown flag '{key}' (dropped when compiled)")));
+                        continue;
+                    };
+                    if let Some((other, _)) = flags.insert(flag, (key, value)) {
+                        issues.push(issue(Severity::Error, id, name, format!("'{other}' and '{key}' are the same flag; only one is kept")));
+                    }
+                    if let Some(problem) = registry.check(flag, value) {
+                        issues.push(issue(Severity::Error, id, name, problem));
+                    }
+                }
+                let has_config = obj.contains_key("config") || obj.contains_key("config_fields");
+                entities.push(Entity { section, id, name, flags, has_config });
+            }
+        }
+
+        let issue = |severity, entity: &Entity, message: String| LintIssue {
+            severity, section: entity.section.to_owned(), id: entity.id, name: entity.name.to_owned(), message
+        };
+        let value_of = |entity: &Entity, flag: &str| registry.id_of(flag).and_then(|f| entity.flags.get(&f)).map(|x| x.1);
+        let mut first_id: HashMap<(&str, u32), usize> = HashMap::new();
+        let mut first_name: HashMap<(&str, &str), usize> = HashMap::new();
+        for (i, entity) in entities.iter().enumerate() {
+            if let Some(id) = entity.id {
+                match first_id.entry((entity.section, id)) {
+                    Entry::Vacant(x) => { x.insert(i); },
+                    Entry::Occupied(x) => issues.push(issue(Severity::Error, entity,
+                        format!("id {} is already used by {}; only one is kept", id, entities[*x.get()].name)))
+                }
+            }
+            if let Some(severity) = self.duplicate_names && !entity.name.is_empty() {
+                match first_name.entry((entity.section, entity.name)) {
+                    Entry::Vacant(x) => { x.insert(i); },
+                    Entry::Occupied(x) => issues.push(issue(severity, entity,
+                        format!("name is also used by {} {}", entity.section, entities[*x.get()].id.map(|x| x.to_string()).unwrap_or_default())))
+                }
+            }
+            for rule in self.required.iter().filter(|x| covers(&x.section, &x.ids, entity)) {
+                let missing: Vec<&str> = rule.flags.iter().filter(|x| value_of(entity, x).is_none()).map(|x| x.as_str()).collect();
+                if !missing.is_empty() {
+                    issues.push(issue(rule.severity, entity, format!("missing {}", missing.join(", "))));
+                }
+            }
+            for rule in self.bounds.iter().filter(|x| covers(&x.section, &x.ids, entity)) {
+                if rule.when.as_ref().is_some_and(|x| value_of(entity, x).is_none()) {
+                    continue;
+                }
+                let when = rule.when.as_ref().map(|x| format!(" (it has {x})")).unwrap_or_default();
+                let value = value_of(entity, &rule.flag);
+                match (value, rule.min, rule.max) {
+                    (None, Some(_), _) if rule.when.is_some() => issues.push(issue(rule.severity, entity, format!("{} is missing{}", rule.flag, when))),
+                    (Some(v), Some(min), _) if v < min => issues.push(issue(rule.severity, entity, format!("{} is {}, below {}{}", rule.flag, v, min, when))),
+                    (Some(v), _, Some(max)) if v > max => issues.push(issue(rule.severity, entity, format!("{} is {}, above {}{}", rule.flag, v, max, when))),
+                    _ => {}
+                }
+            }
+            if entity.section == "cosmetic" && !entity.has_config && let Some(severity) = self.cosmetic_config {
+                issues.push(issue(severity, entity, "has no config".to_owned()));
+            }
+        }
+        for group in self.unique_across.iter() {
+            let mut owner: HashMap<u32, &Entity> = HashMap::new();
+            for entity in entities.iter().filter(|x| group.iter().any(|s| s == x.section)) {
+                let Some(id) = entity.id else { continue; };
+                match owner.get(&id) {
+                    Some(other) if other.section != entity.section =>
+                        issues.push(issue(Severity::Error, entity, format!("id {} is also {} {}", id, other.section, other.name))),
+                    Some(_) => {},
+                    None => { owner.insert(id, entity); }
+                }
+            }
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(issues: &[LintIssue]) -> Vec<String> {
+        issues.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn broken_statfile_is_reported() {
+        let doc: Value = serde_json::from_str(r#"{
+            "blocks": [
+                { "id": 1, "name": "Cube", "health": 100, "mass": 5, "cpuCost": 1 },
+                { "id": 1, "name": "Other cube", "health": -20, "mass": 5, "cpuCost": 1 },
+                { "id": 2, "name": "Thruster", "health": 50, "mass": 0, "cpuCost": 2, "thrust": 300 },
+                { "id": 3, "name": "Cube", "health": 10, "mass": 1, "cpuCost": 1, "hp": 3 }
+            ],
+            "attacks": [ { "id": 1, "name": "Laser", "damage": 10 } ],
+            "cosmetics": [ { "id": 1, "name": "Hat", "config": [] } ]
+        }"#).unwrap();
+        let registry = FlagRegistry::default();
+        let rules = LintRules::default();
+        rules.validate(&registry).unwrap();
+        let issues = rules.lint(&doc, &registry);
+        assert_eq!(messages(&issues), vec!(
+            "error: block 3 (Cube): unknown flag 'hp' (dropped when compiled)",
+            "error: block 1 (Other cube): id 1 is already used by Cube; only one is kept",
+            "error: block 1 (Other cube): health is -20, below 1",
+            "error: block 2 (Thruster): mass is 0, below 1 (it has thrust)",
+            "warning: block 3 (Cube): name is also used by block 1"
+        ));
+    }
+
+    #[test]
+    fn rules_files_are_checked() {
+        let registry = FlagRegistry::default();
+        for bad in [
+            r#"{ "required": [ { "section": "part", "flags": ["health"] } ] }"#,
+            r#"{ "required": [ { "flags": ["hitpoints"] } ] }"#,
+            r#"{ "bounds": [ { "flag": "mass", "min": 5, "max": 1 } ] }"#
+        ] {
+            assert!(serde_json::from_str::<LintRules>(bad).unwrap().validate(&registry).is_err(), "{bad}");
+        }
+        let rules: LintRules = serde_json::from_str(r#"{ "cosmetic_config": null, "unique_across": [["block", "cosmetic"]] }"#).unwrap();
+        let doc: Value = serde_json::from_str(r#"{ "blocks": [ { "id": 4, "name": "A", "health": 1, "mass": 1, "cpuCost": 1 } ],
+            "attacks": [], "cosmetics": [ { "id": 4, "name": "B" } ] }"#).unwrap();
+        assert_eq!(messages(&rules.lint(&doc, &registry)), vec!("error: cosmetic 4 (B): id 4 is also block A"));
+    }
+}
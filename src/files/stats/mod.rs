@@ -1 +1,6 @@
-pub mod statfile;
+pub mod statfile;
+pub mod flags;
+pub mod diff;
+pub mod csv;
+pub mod template;
+pub mod lint;
\ No newline at end of file
@@ -1,12 +1,15 @@
 use serde::{Deserialize, Deserializer, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use fnv::FnvHashMap;
 use std::convert::TryFrom;
 use std::io::{Cursor, Read, Write};
 use serde::ser::{Serializer, SerializeMap};
+use crate::files::schema::SchemaRegistry;
+use super::flags::FlagRegistry;
 
 pub const STATFILE_MAGIC_NUMBER: u32 = 0x1EF1A757; // 57A7F11E "statfile"
 const CURRENT_VERSION: u32 = 4;
+const NAMED_VERSION: u32 = 5; // version 4 plus a names section; only written by compile_with_names
 
 pub const HEALTH_FLAG: u8 = 0;
 pub const MASS_FLAG: u8 = 1;
@@ -53,7 +56,32 @@ pub struct StatsFile {
     pub blocks: FlagStats,
     pub attacks: FlagStats,
     pub cosmetics: FlagStats,
-    pub cosmetics_bin: BinaryConfig
+    pub cosmetics_bin: BinaryConfig,
+    #[serde(default)]
+    pub names: StatNames
+}
+
+// Authoring details the game doesn't need: entity names, in the order they were written,
+// and flag spellings other than the canonical name (e.g. "spec1_reloadMs" for spec1)
+#[derive(Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct StatNames {
+    pub blocks: Vec<EntityNames>,
+    pub attacks: Vec<EntityNames>,
+    pub cosmetics: Vec<EntityNames>
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub struct EntityNames {
+    pub id: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub flag_names: BTreeMap<u8, String>
+}
+
+impl StatNames {
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.attacks.is_empty() && self.cosmetics.is_empty()
+    }
 }
 
 #[derive(Clone)]
@@ -66,11 +94,13 @@ impl Serialize for FlagStats {
     where
         S: Serializer,
     {
-        let mut m2: std::collections::BTreeMap<&u32, HashMap<&str, &i32>> = std::collections::BTreeMap::new();
+        // serde has no way to be handed a registry, so it only knows the built-in flags
+        let registry = FlagRegistry::default();
+        let mut m2: std::collections::BTreeMap<&u32, BTreeMap<String, &i32>> = std::collections::BTreeMap::new();
         for (k, v) in &self.data {
-            let mut mm = HashMap::new();
+            let mut mm = BTreeMap::new();
             for (k2, v2) in v {
-                mm.insert(flag_name(*k2), v2);
+                mm.insert(registry.name_of(*k2), v2);
             }
             m2.insert(k, mm);
         }
@@ -90,11 +120,12 @@ impl<'de> Deserialize<'de> for FlagStats {
     {
         let m: std::collections::BTreeMap<u32, HashMap<String, i32>> = std::collections::BTreeMap::deserialize(deserializer)?;
         let mut data: FnvHashMap<u32, FnvHashMap<u8, i32>> = FnvHashMap::default();
-        
+        let registry = FlagRegistry::default();
+
         for (key, v) in m {           
             let mut inner_map = FnvHashMap::default();
             for (k2_str, v2) in v {
-                let k2 = flag_id(&k2_str).ok_or_else(|| serde::de::Error::custom(format!("Invalid flag name {k2_str}")))?;
+                let k2 = registry.id_of(&k2_str).ok_or_else(|| serde::de::Error::custom(format!("Invalid flag name {k2_str}")))?;
                 inner_map.insert(k2, v2);
             }
 
@@ -142,7 +173,7 @@ pub struct JsonBlockStats {
     pub id: u32,
     pub name: String,
     #[serde(flatten)]
-    pub flags: HashMap<String, i32>,
+    pub flags: BTreeMap<String, i32>, // sorted, so dumps are stable
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -150,7 +181,7 @@ pub struct JsonAttackStats {
     pub id: u32,
     pub name: String,
     #[serde(flatten)]
-    pub flags: HashMap<String, i32>,
+    pub flags: BTreeMap<String, i32>, // sorted, so dumps are stable
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -158,121 +189,58 @@ pub struct JsonCosmeticStats {
     pub id: u32,
     pub name: String,
     #[serde(flatten)]
-    pub flags: HashMap<String, i32>,
-    pub config: Vec<u8>
+    pub flags: BTreeMap<String, i32>, // sorted, so dumps are stable
+    #[serde(default)]
+    pub config: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_fields: Option<serde_json::Map<String, serde_json::Value>> // config decoded with a SchemaRegistry
 }
 
-fn flag_id(flag: &str) -> Option<u8> {
-    match flag {
-        "health" => Some(HEALTH_FLAG),
-        "mass" => Some(MASS_FLAG),
-        "cost" => Some(COST_FLAG),
-        "roboRanking" => Some(RANKING_FLAG),
-        "cpuCost" => Some(COMPLEXITY_FLAG),
-        "cosmCmplx" => Some(COSMETIC_COMPLEXITY_FLAG),
-        "thrust" => Some(THRUST_FLAG),
-        "rotationSpeed" => Some(ROTATION_FLAG),
-        "shield" => Some(SHIELD_FLAG),
-        "shieldCharge" => Some(SHIELD_CHARGE_RATE_FLAG),
-        "shieldChargeDelay" => Some(SHIELD_CHARGE_DELAY_FLAG),
-        "premiumCost" => Some(PREMIUM_COST_FLAG),
-        "functionHealth" => Some(USABILITY_HEALTH),
-        "damage" => Some(DAMAGE_FLAG),
-        "lift" => Some(LIFT_FLAG),
-        "cosmeticCost" => Some(MODIFIER_COST),
-        "cosmeticPremiumCost" => Some(MODIFIER_PREMIUM_COST),
-        x => {
-            if x.starts_with("spec") { // I know it's bad
-                if x.starts_with("spec0") {
-                    Some(SPECIAL_FLAG_0)
-                } else if x.starts_with("spec1_") || x == "spec1" {
-                    Some(SPECIAL_FLAG_1)
-                } else if x.starts_with("spec2_") || x == "spec2" {
-                    Some(SPECIAL_FLAG_2)
-                } else if x.starts_with("spec3_") || x == "spec3" {
-                    Some(SPECIAL_FLAG_3)
-                } else if x.starts_with("spec4_") || x == "spec4" {
-                    Some(SPECIAL_FLAG_4)
-                } else if x.starts_with("spec5_") || x == "spec5" {
-                    Some(SPECIAL_FLAG_5)
-                } else if x.starts_with("spec6_") || x == "spec6" {
-                    Some(SPECIAL_FLAG_6)
-                } else if x.starts_with("spec7_") || x == "spec7" {
-                    Some(SPECIAL_FLAG_7)
-                } else if x.starts_with("spec8_") || x == "spec8" {
-                    Some(SPECIAL_FLAG_8)
-                } else if x.starts_with("spec9_") || x == "spec9" {
-                    Some(SPECIAL_FLAG_9)
-                } else if x.starts_with("spec10_") || x == "spec10" {
-                    Some(SPECIAL_FLAG_10)
-                } else if x.starts_with("spec11_") || x == "spec11" {
-                    Some(SPECIAL_FLAG_11)
-                } else if x.starts_with("spec12_") || x == "spec12" {
-                    Some(SPECIAL_FLAG_12)
-                } else if x.starts_with("spec13_") || x == "spec13" {
-                    Some(SPECIAL_FLAG_13)
-                } else if x.starts_with("spec14_") || x == "spec14" {
-                    Some(SPECIAL_FLAG_14)
-                } else if x.starts_with("spec15_") || x == "spec15" {
-                    Some(SPECIAL_FLAG_15)
-                } else if x.starts_with("spec16_") || x == "spec16" {
-                    Some(SPECIAL_FLAG_16)
-                } else if x.starts_with("spec17_") || x == "spec17" {
-                    Some(SPECIAL_FLAG_17)
-                } else if x.starts_with("spec18_") || x == "spec18" {
-                    Some(SPECIAL_FLAG_18)
-                } else if x.starts_with("spec19_") || x == "spec19" {
-                    Some(SPECIAL_FLAG_19)
-                } else {
-                    None
+impl JsonStatsFile {
+    // Replaces config bytes with named fields wherever the registry has a layout; returns blobs that didn't fit
+    pub fn decode_configs(self: &mut JsonStatsFile, registry: &SchemaRegistry) -> Vec<String> {
+        let mut problems = Vec::new();
+        for cosm in self.cosmetics.iter_mut() {
+            if let Some(layout) = registry.cosmetic_configs.get(&cosm.id) {
+                match layout.decode(&cosm.config) {
+                    Ok(fields) => { cosm.config_fields = Some(fields); cosm.config.clear(); },
+                    Err(e) => problems.push(format!("cosmetic {}: {}", cosm.id, e))
                 }
-            } else {
-                None
             }
         }
+        problems
     }
-}
 
-fn flag_name(flag: u8) -> &'static str {
-    match flag {
-        HEALTH_FLAG =>"health",
-        MASS_FLAG => "mass",
-        COST_FLAG => "cost",
-        RANKING_FLAG => "roboRanking",
-        COMPLEXITY_FLAG => "cpuCost",
-        COSMETIC_COMPLEXITY_FLAG => "cosmCmplx",
-        THRUST_FLAG => "thrust",
-        ROTATION_FLAG => "rotationSpeed",
-        SHIELD_FLAG => "shield",
-        DAMAGE_FLAG => "damage",
-        SHIELD_CHARGE_RATE_FLAG => "shieldCharge",
-        SHIELD_CHARGE_DELAY_FLAG => "shieldChargeDelay",
-        PREMIUM_COST_FLAG => "premiumCost",
-        USABILITY_HEALTH => "functionHealth",
-        LIFT_FLAG => "lift",
-        SPECIAL_FLAG_0 => "spec0",
-        SPECIAL_FLAG_1 => "spec1",
-        SPECIAL_FLAG_2 => "spec2",
-        SPECIAL_FLAG_3 => "spec3",
-        SPECIAL_FLAG_4 => "spec4",
-        SPECIAL_FLAG_5 => "spec5",
-        SPECIAL_FLAG_6 => "spec6",
-        SPECIAL_FLAG_7 => "spec7",
-        SPECIAL_FLAG_8 => "spec8",
-        SPECIAL_FLAG_9 => "spec9",
-        SPECIAL_FLAG_10 => "spec10",
-        SPECIAL_FLAG_11 => "spec11",
-        SPECIAL_FLAG_12 => "spec12",
-        SPECIAL_FLAG_13 => "spec13",
-        SPECIAL_FLAG_14 => "spec14",
-        SPECIAL_FLAG_15 => "spec15",
-        SPECIAL_FLAG_16 => "spec16",
-        SPECIAL_FLAG_17 => "spec17",
-        SPECIAL_FLAG_18 => "spec18",
-        SPECIAL_FLAG_19 => "spec19",
-        MODIFIER_COST => "cosmeticCost",
-        MODIFIER_PREMIUM_COST => "cosmeticPremiumCost",
-        _ => "err"
+    // Flag names the registry doesn't know; From<JsonStatsFile> drops these
+    pub fn unknown_flags(self: &JsonStatsFile, registry: &FlagRegistry) -> Vec<String> {
+        let sections = [
+            ("block", self.blocks.iter().map(|x| (x.id, &x.flags)).collect::<Vec<_>>()),
+            ("attack", self.attacks.iter().map(|x| (x.id, &x.flags)).collect()),
+            ("cosmetic", self.cosmetics.iter().map(|x| (x.id, &x.flags)).collect())
+        ];
+        let mut problems = Vec::new();
+        for (section, entries) in sections.iter() {
+            for (id, flags) in entries.iter() {
+                let mut unknown: Vec<&String> = flags.keys().filter(|x| registry.id_of(x).is_none()).collect();
+                unknown.sort();
+                unknown.iter().for_each(|x| problems.push(format!("{section} {id}: unknown flag '{x}'")));
+            }
+        }
+        problems
+    }
+
+    // Turns config_fields back into config bytes, validating them against the registry
+    pub fn encode_configs(self: &mut JsonStatsFile, registry: &SchemaRegistry) -> Result<(), std::io::Error> {
+        for cosm in self.cosmetics.iter_mut() {
+            let Some(fields) = cosm.config_fields.take() else { continue; };
+            let fail = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("cosmetic {}: {}", cosm.id, e));
+            if !cosm.config.is_empty() {
+                return Err(fail("has both config and config_fields".to_owned()));
+            }
+            let layout = registry.cosmetic_configs.get(&cosm.id).ok_or_else(|| fail("no schema layout for this id".to_owned()))?;
+            cosm.config = layout.encode(&fields).map_err(|e| fail(e.to_string()))?;
+        }
+        Ok(())
     }
 }
 
@@ -299,6 +267,7 @@ impl TryFrom<&[u8]> for StatsFile {
             2 => StatsFile::from_v2(&mut blank, &mut file),
             3 => StatsFile::from_v3(&mut blank, &mut file),
             4 => StatsFile::from_v4(&mut blank, &mut file),
+            5 => StatsFile::from_v5(&mut blank, &mut file),
             _ => Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 format!("Version was invalid: {version}"),
@@ -310,37 +279,67 @@ impl TryFrom<&[u8]> for StatsFile {
     }
 }
 
+// Flags by id, noting the entity's name and any non-canonical flag spellings in `names`
+fn flags_from_json(registry: &FlagRegistry, id: u32, name: &str, flags: &BTreeMap<String, i32>, names: &mut Vec<EntityNames>) -> FnvHashMap<u8, i32> {
+    let mut map = FnvHashMap::default();
+    let mut flag_names = BTreeMap::new();
+    flags.iter().for_each(|flag| {
+        let idn = registry.id_of(flag.0);
+        if let Some(x) = idn {
+            map.insert(x, *flag.1);
+            if registry.name_of(x) != *flag.0 {
+                flag_names.insert(x, flag.0.clone());
+            }
+        }
+    });
+    names.push(EntityNames { id, name: name.to_owned(), flag_names });
+    map
+}
+
+// (id, name, flags) in authoring order, then entities the names don't mention by id
+fn flags_to_json(registry: &FlagRegistry, stats: &FlagStats, names: &[EntityNames]) -> Vec<(u32, String, BTreeMap<String, i32>)> {
+    let named: FnvHashMap<u32, &EntityNames> = names.iter().map(|x| (x.id, x)).collect();
+    let mut seen = HashSet::new();
+    let mut order: Vec<u32> = names.iter().map(|x| x.id).filter(|x| stats.data.contains_key(x) && seen.insert(*x)).collect();
+    let mut rest: Vec<u32> = stats.data.keys().filter(|x| !seen.contains(*x)).copied().collect();
+    rest.sort();
+    order.extend(rest);
+    order.into_iter().map(|id| {
+        let entity = named.get(&id);
+        let flags = stats.data[&id].iter().map(|(f, v)| {
+            let name = entity.and_then(|x| x.flag_names.get(f)).cloned().unwrap_or_else(|| registry.name_of(*f));
+            (name, *v)
+        }).collect();
+        (id, entity.map(|x| x.name.clone()).unwrap_or_default(), flags)
+    }).collect()
+}
+
+// The From conversions use the built-in flags; from_json/from_stats take the registry to use
 impl From<JsonStatsFile> for StatsFile {
     fn from(file: JsonStatsFile) -> Self {
+        StatsFile::from_json(file, &FlagRegistry::default())
+    }
+}
+
+impl From<&StatsFile> for JsonStatsFile {
+    fn from(stats: &StatsFile) -> Self {
+        JsonStatsFile::from_stats(stats, &FlagRegistry::default())
+    }
+}
+
+impl StatsFile {
+    pub fn from_json(file: JsonStatsFile, registry: &FlagRegistry) -> StatsFile {
         let mut sf = StatsFile::new();
         file.blocks.iter().for_each(|elem| {
-            let mut map = FnvHashMap::default();
-            elem.flags.iter().for_each(|flag| {
-                let idn = flag_id(flag.0);
-                if let Some(x) = idn {
-                    map.insert(x, *flag.1);
-                }
-            });
+            let map = flags_from_json(registry, elem.id, &elem.name, &elem.flags, &mut sf.names.blocks);
             sf.blocks.data.insert(elem.id, map);
         });
         file.attacks.iter().for_each(|elem| {
-            let mut map = FnvHashMap::default();
-            elem.flags.iter().for_each(|flag| {
-                let idn = flag_id(flag.0);
-                if let Some(x) = idn {
-                    map.insert(x, *flag.1);
-                }
-            });
+            let map = flags_from_json(registry, elem.id, &elem.name, &elem.flags, &mut sf.names.attacks);
             sf.attacks.data.insert(elem.id, map);
         });
         file.cosmetics.iter().for_each(|elem| {
-            let mut map = FnvHashMap::default();
-            elem.flags.iter().for_each(|flag| {
-                let idn = flag_id(flag.0);
-                if let Some(x) = idn {
-                    map.insert(x, *flag.1);
-                }
-            });
+            let map = flags_from_json(registry, elem.id, &elem.name, &elem.flags, &mut sf.names.cosmetics);
             sf.cosmetics.data.insert(elem.id, map);
         });
         file.cosmetics.into_iter().for_each(|elem| {
@@ -350,6 +349,24 @@ impl From<JsonStatsFile> for StatsFile {
     }
 }
 
+impl JsonStatsFile {
+    // The authoring form back: names and flag spellings come from `names` where the file kept them
+    pub fn from_stats(stats: &StatsFile, registry: &FlagRegistry) -> JsonStatsFile {
+        JsonStatsFile {
+            blocks: flags_to_json(registry, &stats.blocks, &stats.names.blocks).into_iter()
+                .map(|(id, name, flags)| JsonBlockStats { id, name, flags }).collect(),
+            attacks: flags_to_json(registry, &stats.attacks, &stats.names.attacks).into_iter()
+                .map(|(id, name, flags)| JsonAttackStats { id, name, flags }).collect(),
+            cosmetics: flags_to_json(registry, &stats.cosmetics, &stats.names.cosmetics).into_iter()
+                .map(|(id, name, flags)| JsonCosmeticStats {
+                    id, name, flags,
+                    config: stats.cosmetics_bin.data.get(&id).cloned().unwrap_or_default(),
+                    config_fields: None
+                }).collect()
+        }
+    }
+}
+
 impl StatsFile {
     fn from_v1(stats: &mut StatsFile, file: &mut Cursor<&[u8]>) -> Result<(), std::io::Error> {
         let mut buf4 = [0u8; 4];
@@ -475,12 +492,66 @@ impl StatsFile {
         Ok(())
     }
 
+    fn load_names(file: &mut Cursor<&[u8]>) -> Result<Vec<EntityNames>, std::io::Error> {
+        let mut buf4 = [0u8; 4];
+        let mut buf2 = [0u8; 2];
+        let mut buf1 = [0u8; 1];
+        let read_string = |file: &mut Cursor<&[u8]>, len: usize| -> Result<String, std::io::Error> {
+            let mut bytes = vec![0u8; len];
+            file.read_exact(&mut bytes)?;
+            String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        };
+        file.read_exact(&mut buf4)?;
+        let num_entity = u32::from_be_bytes(buf4);
+        let mut names = Vec::new();
+        for _ in 0..num_entity {
+            file.read_exact(&mut buf4)?;
+            let id = u32::from_be_bytes(buf4);
+            file.read_exact(&mut buf2)?;
+            let name = read_string(file, u16::from_be_bytes(buf2) as usize)?;
+            file.read_exact(&mut buf1)?;
+            let mut flag_names = BTreeMap::new();
+            for _ in 0..buf1[0] {
+                let mut flag = [0u8; 2];
+                file.read_exact(&mut flag)?;
+                flag_names.insert(flag[0], read_string(file, flag[1] as usize)?);
+            }
+            names.push(EntityNames { id, name, flag_names });
+        }
+        Ok(names)
+    }
+
+    fn from_v5(stats: &mut StatsFile, file: &mut Cursor<&[u8]>) -> Result<(), std::io::Error> {
+        StatsFile::from_v4(stats, file)?;
+        stats.names.blocks = StatsFile::load_names(file)?;
+        stats.names.attacks = StatsFile::load_names(file)?;
+        stats.names.cosmetics = StatsFile::load_names(file)?;
+        Ok(())
+    }
+
+    // Values outside the range their flag allows
+    pub fn check_ranges(self: &StatsFile, registry: &FlagRegistry) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (section, stats) in [("block", &self.blocks), ("attack", &self.attacks), ("cosmetic", &self.cosmetics)] {
+            let mut ids: Vec<&u32> = stats.data.keys().collect();
+            ids.sort();
+            for id in ids {
+                let mut flags: Vec<(&u8, &i32)> = stats.data[id].iter().collect();
+                flags.sort();
+                flags.into_iter().filter_map(|(f, v)| registry.check(*f, *v))
+                    .for_each(|x| problems.push(format!("{section} {id}: {x}")));
+            }
+        }
+        problems
+    }
+
     pub fn new() -> StatsFile {
         StatsFile {
             blocks: FlagStats::new(),
             attacks: FlagStats::new(),
             cosmetics: FlagStats::new(),
-            cosmetics_bin: BinaryConfig::new()
+            cosmetics_bin: BinaryConfig::new(),
+            names: StatNames::default()
         }
     }
 
@@ -513,22 +584,105 @@ impl StatsFile {
 
     fn compile_cosm_v4(stat: &FlagStats, stat_bin: &BinaryConfig, file: &mut Cursor<Vec<u8>>) -> Result<(), std::io::Error> {
         StatsFile::compile_sub_flag(stat, file)?;
-        for kvp in &stat_bin.data {
-            file.write_all(&u8::to_be_bytes((kvp.1.len()) as u8))?;
-            file.write_all(kvp.1)?;
+        // configs follow in the same order as the flags, one per cosmetic (empty if it has none)
+        for id in stat.data.keys() {
+            let config = stat_bin.data.get(id).map(|x| x.as_slice()).unwrap_or_default();
+            if config.len() > u8::MAX.into() {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Config of cosmetic {id} is longer than 255 bytes")));
+            }
+            file.write_all(&u8::to_be_bytes(config.len() as u8))?;
+            file.write_all(config)?;
         }
 
         Ok(())
     }
 
+    fn compile_names(names: &[EntityNames], file: &mut Cursor<Vec<u8>>) -> Result<(), std::io::Error> {
+        let too_long = |what: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{what} is too long"));
+        file.write_all(&u32::to_be_bytes(names.len() as u32))?;
+        for entity in names.iter() {
+            file.write_all(&u32::to_be_bytes(entity.id))?;
+            let len = u16::try_from(entity.name.len()).map_err(|_| too_long(format!("Name of {}", entity.id)))?;
+            file.write_all(&u16::to_be_bytes(len))?;
+            file.write_all(entity.name.as_bytes())?;
+            let count = u8::try_from(entity.flag_names.len()).map_err(|_| too_long(format!("Flag name list of {}", entity.id)))?;
+            file.write_all(&[count])?;
+            for (flag, name) in entity.flag_names.iter() {
+                let len = u8::try_from(name.len()).map_err(|_| too_long(format!("Flag name {name}")))?;
+                file.write_all(&[*flag, len])?;
+                file.write_all(name.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    // Version 4, the one the game reads; names are left out
     pub fn compile(self: &StatsFile) -> Result<Vec<u8>, std::io::Error> {
+        self.compile_version(false)
+    }
+
+    // Version 5 with the names section, so the authoring JSON can be recovered; only proceliotool reads it
+    pub fn compile_with_names(self: &StatsFile) -> Result<Vec<u8>, std::io::Error> {
+        self.compile_version(true)
+    }
+
+    fn compile_version(self: &StatsFile, names: bool) -> Result<Vec<u8>, std::io::Error> {
         let mut file = Cursor::new(Vec::new());
         file.write_all(&u32::to_be_bytes(STATFILE_MAGIC_NUMBER))?; // "57A7F11E" STATFILE magic number
-        file.write_all(&u32::to_be_bytes(CURRENT_VERSION))?;
+        file.write_all(&u32::to_be_bytes(if names { NAMED_VERSION } else { CURRENT_VERSION }))?;
 
         StatsFile::compile_sub_flag(&self.blocks, &mut file)?;
         StatsFile::compile_sub_flag(&self.attacks, &mut file)?;
         StatsFile::compile_cosm_v4(&self.cosmetics, &self.cosmetics_bin, &mut file)?;
+        if names {
+            StatsFile::compile_names(&self.names.blocks, &mut file)?;
+            StatsFile::compile_names(&self.names.attacks, &mut file)?;
+            StatsFile::compile_names(&self.names.cosmetics, &mut file)?;
+        }
         Ok(file.into_inner())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> StatsFile {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/statfile.json")).unwrap();
+        StatsFile::from_json(serde_json::from_slice(&data).unwrap(), &FlagRegistry::default())
+    }
+
+    #[test]
+    fn cosmetic_configs_stay_with_their_cosmetic() {
+        let mut stats = StatsFile::new();
+        for id in [10, 11, 12, 13] {
+            stats.cosmetics.data.insert(id, [(COSMETIC_COMPLEXITY_FLAG, id as i32)].into_iter().collect());
+        }
+        stats.cosmetics_bin.data.insert(11, vec!(1, 2, 3));
+        stats.cosmetics_bin.data.insert(13, vec!(9));
+        let binary = stats.compile().unwrap();
+        let back = StatsFile::try_from(binary.as_slice()).unwrap();
+        assert_eq!(back.cosmetics.data, stats.cosmetics.data);
+        assert_eq!(back.cosmetics_bin.data, stats.cosmetics_bin.data);
+        assert_eq!(back.compile().unwrap(), binary);
+
+        stats.cosmetics_bin.data.insert(10, vec!(0; 256));
+        assert!(stats.compile().is_err());
+    }
+
+    #[test]
+    fn names_survive_version_5_only() {
+        let registry = FlagRegistry::default();
+        let stats = sample();
+        let json = serde_json::to_value(JsonStatsFile::from_stats(&stats, &registry)).unwrap();
+
+        let named = StatsFile::try_from(stats.compile_with_names().unwrap().as_slice()).unwrap();
+        assert!(!named.names.is_empty());
+        assert_eq!(serde_json::to_value(JsonStatsFile::from_stats(&named, &registry)).unwrap(), json);
+
+        let plain = StatsFile::try_from(stats.compile().unwrap().as_slice()).unwrap();
+        assert!(plain.names.is_empty());
+        assert_eq!(plain.blocks.data, stats.blocks.data);
+        assert_eq!(plain.cosmetics.data, stats.cosmetics.data);
+    }
+}
@@ -0,0 +1,355 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use super::flags::FlagRegistry;
+use super::statfile::JsonStatsFile;
+
+// Authoring extensions to statfile JSON, resolved into a plain JsonStatsFile before compiling:
+//   "templates": {"blade": {"cpuCost": 2, "mass": 3}, "heavyBlade": {"extends": "blade", "mass": 6}}
+//   {"id": 90, "name": "Saw", "extends": "heavyBlade", "cost": "mass * 20 + cpuCost"}
+// Flags are inherited from the template chain and overridden by flag id (so aliases override too);
+// null removes an inherited flag. String values are expressions over the entity's other flags.
+const TEMPLATES_KEY: &str = "templates";
+const EXTENDS_KEY: &str = "extends";
+const SECTIONS: [&str; 3] = ["blocks", "attacks", "cosmetics"];
+pub(crate) const ENTITY_KEYS: [&str; 4] = ["id", "name", "config", "config_fields"]; // everything else is a flag
+const MAX_NESTING: usize = 64; // parentheses and unary '-' deep enough to overflow the stack are an error
+
+fn invalid(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(f64),
+    Flag(String),
+    Negate(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>)
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char)
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut s = String::new();
+            while let Some(&d) = chars.peek().filter(|x| x.is_ascii_digit() || **x == '.') {
+                s.push(d);
+                chars.next();
+            }
+            tokens.push(Token::Number(s.parse().map_err(|_| format!("bad number '{s}'"))?));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut s = String::new();
+            while let Some(&d) = chars.peek().filter(|x| x.is_ascii_alphanumeric() || **x == '_') {
+                s.push(d);
+                chars.next();
+            }
+            tokens.push(Token::Ident(s));
+        } else if "+-*/%()".contains(c) {
+            tokens.push(Token::Op(c));
+            chars.next();
+        } else {
+            return Err(format!("unexpected '{c}'"));
+        }
+    }
+    Ok(tokens)
+}
+
+// expr := term (('+' | '-') term)*, term := unary (('*' | '/' | '%') unary)*, unary := '-' unary | atom
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize
+}
+
+impl Parser {
+    fn parse(text: &str) -> Result<Expr, String> {
+        let mut parser = Parser { tokens: tokenize(text)?, pos: 0, depth: 0 };
+        let expr = parser.expr()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(t) => Err(format!("unexpected {t:?} after the expression"))
+        }
+    }
+
+    fn eat(&mut self, ops: &str) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(c)) if ops.contains(*c) => { self.pos += 1; Some(*c) },
+            _ => None
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.term()?;
+        while let Some(op) = self.eat("+-") {
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.eat("*/%") {
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn nested(&mut self, parse: impl FnOnce(&mut Parser) -> Result<Expr, String>) -> Result<Expr, String> {
+        if self.depth >= MAX_NESTING {
+            return Err(format!("nested more than {MAX_NESTING} deep"));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("-").is_some() {
+            return self.nested(|p| Ok(Expr::Negate(Box::new(p.unary()?))));
+        }
+        if self.eat("(").is_some() {
+            let inner = self.nested(|p| p.expr())?;
+            return match self.eat(")") {
+                Some(_) => Ok(inner),
+                None => Err("missing ')'".to_owned())
+            };
+        }
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        match token {
+            Some(Token::Number(n)) => Ok(Expr::Number(*n)),
+            Some(Token::Ident(name)) => Ok(Expr::Flag(name.clone())),
+            Some(t) => Err(format!("unexpected {t:?}")),
+            None => Err("the expression ends early".to_owned())
+        }
+    }
+}
+
+// Flags compare by id where the registry knows the name, so "spec1_minlift" overrides "spec1"
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+enum Slot {
+    Id(u8),
+    Unknown(String)
+}
+
+fn slot(registry: &FlagRegistry, name: &str) -> Slot {
+    registry.id_of(name).map(Slot::Id).unwrap_or_else(|| Slot::Unknown(name.to_owned()))
+}
+
+// (spelling, value) per flag, in the order they were first set
+type Flags = Vec<(Slot, String, Value)>;
+
+fn set_flag(registry: &FlagRegistry, flags: &mut Flags, name: &str, value: &Value) {
+    let s = slot(registry, name);
+    let pos = flags.iter().position(|x| x.0 == s);
+    match (pos, value) {
+        (Some(i), Value::Null) => { flags.remove(i); },
+        (None, Value::Null) => {},
+        (Some(i), _) => flags[i] = (s, name.to_owned(), value.clone()),
+        (None, _) => flags.push((s, name.to_owned(), value.clone()))
+    }
+}
+
+fn template_flags(registry: &FlagRegistry, templates: &Map<String, Value>, name: &str, chain: &mut Vec<String>) -> Result<Flags, String> {
+    if chain.iter().any(|x| x == name) {
+        chain.push(name.to_owned());
+        return Err(format!("templates extend each other in a cycle: {}", chain.join(" -> ")));
+    }
+    let template = templates.get(name).and_then(|x| x.as_object()).ok_or_else(|| format!("no template named '{name}'"))?;
+    chain.push(name.to_owned());
+    let mut flags = match template.get(EXTENDS_KEY) {
+        None => Flags::new(),
+        Some(Value::String(parent)) => template_flags(registry, templates, parent, chain)?,
+        Some(_) => return Err(format!("template '{name}': extends must be a template name"))
+    };
+    chain.pop();
+    for (key, value) in template.iter().filter(|x| x.0 != EXTENDS_KEY) {
+        set_flag(registry, &mut flags, key, value);
+    }
+    Ok(flags)
+}
+
+struct Evaluator<'a> {
+    registry: &'a FlagRegistry,
+    flags: &'a Flags,
+    done: HashMap<Slot, i32>,
+    visiting: Vec<String>
+}
+
+impl Evaluator<'_> {
+    fn value(&mut self, index: usize) -> Result<i32, String> {
+        let (s, name, value) = &self.flags[index];
+        if let Some(v) = self.done.get(s) {
+            return Ok(*v);
+        }
+        if self.visiting.contains(name) {
+            self.visiting.push(name.clone());
+            return Err(format!("expressions refer to each other in a cycle: {}", self.visiting.join(" -> ")));
+        }
+        let result = match value {
+            Value::Number(n) => n.as_i64().and_then(|x| i32::try_from(x).ok())
+                .ok_or_else(|| format!("{name}: {n} is not a whole 32-bit number"))?,
+            Value::String(text) => {
+                let expr = Parser::parse(text).map_err(|e| format!("{name}: \"{text}\": {e}"))?;
+                self.visiting.push(name.clone());
+                let v = self.eval(&expr).map_err(|e| format!("{name}: {e}"))?;
+                self.visiting.pop();
+                if !v.is_finite() || v.round() < f64::from(i32::MIN) || v.round() > f64::from(i32::MAX) {
+                    return Err(format!("{name}: \"{text}\" gives {v}, which doesn't fit a flag"));
+                }
+                v.round() as i32
+            },
+            _ => return Err(format!("{name}: must be a number or an expression"))
+        };
+        self.done.insert(s.clone(), result);
+        Ok(result)
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<f64, String> {
+        Ok(match expr {
+            Expr::Number(n) => *n,
+            Expr::Flag(name) => {
+                let s = slot(self.registry, name);
+                let index = self.flags.iter().position(|x| x.0 == s).ok_or_else(|| format!("'{name}' isn't set"))?;
+                f64::from(self.value(index)?)
+            },
+            Expr::Negate(inner) => -self.eval(inner)?,
+            Expr::Binary(op, left, right) => {
+                let (l, r) = (self.eval(left)?, self.eval(right)?);
+                match op {
+                    '+' => l + r,
+                    '-' => l - r,
+                    '*' => l * r,
+                    _ if r == 0.0 => return Err("division by zero".to_owned()),
+                    '/' => l / r,
+                    _ => l % r
+                }
+            }
+        })
+    }
+}
+
+fn resolve_entity(registry: &FlagRegistry, templates: &Map<String, Value>, entity: &mut Map<String, Value>) -> Result<(), String> {
+    // entities without templates or expressions are left exactly as written
+    if !entity.contains_key(EXTENDS_KEY) && !entity.iter().any(|(k, v)| v.is_string() && !ENTITY_KEYS.contains(&k.as_str())) {
+        return Ok(());
+    }
+    let mut flags = match entity.remove(EXTENDS_KEY) {
+        None => Flags::new(),
+        Some(Value::String(name)) => template_flags(registry, templates, &name, &mut Vec::new())?,
+        Some(_) => return Err("extends must be a template name".to_owned())
+    };
+    let keys: Vec<String> = entity.keys().filter(|x| !ENTITY_KEYS.contains(&x.as_str())).cloned().collect();
+    for key in keys.iter() {
+        if let Some(value) = entity.remove(key) {
+            set_flag(registry, &mut flags, key, &value);
+        }
+    }
+    let mut evaluator = Evaluator { registry, flags: &flags, done: HashMap::new(), visiting: Vec::new() };
+    for (i, (_, name, _)) in flags.iter().enumerate() {
+        entity.insert(name.clone(), Value::from(evaluator.value(i)?));
+    }
+    Ok(())
+}
+
+// The same document with templates and expressions replaced by plain flag values
+pub fn resolve_templates(mut doc: Value, registry: &FlagRegistry) -> Result<Value, std::io::Error> {
+    let root = doc.as_object_mut().ok_or_else(|| invalid("a statfile must be a JSON object".to_owned()))?;
+    let templates = match root.remove(TEMPLATES_KEY) {
+        None => Map::new(),
+        Some(Value::Object(t)) => t,
+        Some(_) => return Err(invalid(format!("'{TEMPLATES_KEY}' must be an object of templates")))
+    };
+    for section in SECTIONS {
+        let Some(entities) = root.get_mut(section).and_then(|x| x.as_array_mut()) else { continue; };
+        for entity in entities.iter_mut() {
+            let Some(entity) = entity.as_object_mut() else { continue; };
+            let label = format!("{} {} ({})", section.trim_end_matches('s'), entity.get("id").unwrap_or(&Value::Null),
+                entity.get("name").and_then(|x| x.as_str()).unwrap_or(""));
+            resolve_entity(registry, &templates, entity).map_err(|e| invalid(format!("{label}: {e}")))?;
+        }
+    }
+    Ok(doc)
+}
+
+impl JsonStatsFile {
+    // Statfile JSON that may use templates and expressions; plain statfile JSON comes through unchanged
+    pub fn resolve(doc: Value, registry: &FlagRegistry) -> Result<JsonStatsFile, std::io::Error> {
+        serde_json::from_value(resolve_templates(doc, registry)?).map_err(|e| invalid(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval(text: &str) -> Result<f64, String> {
+        let flags = Flags::new();
+        let registry = FlagRegistry::default();
+        Evaluator { registry: &registry, flags: &flags, done: HashMap::new(), visiting: Vec::new() }.eval(&Parser::parse(text)?)
+    }
+
+    fn resolve(doc: Value) -> Result<Value, std::io::Error> {
+        resolve_templates(doc, &FlagRegistry::default())
+    }
+
+    #[test]
+    fn parser_precedence_and_errors() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), 7.0);
+        assert_eq!(eval("-(1 + 2) * 3 % 5").unwrap(), -4.0);
+        assert_eq!(eval("--2").unwrap(), 2.0);
+        assert!(eval("(1 + 2").is_err());
+        assert!(eval("1 +").is_err());
+        assert!(eval("1 2").is_err());
+        assert!(eval("1 / 0").is_err());
+        assert!(eval("1 $ 2").is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        assert!(eval(&format!("{}1{}", "(".repeat(MAX_NESTING), ")".repeat(MAX_NESTING))).is_ok());
+        assert!(eval(&format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000))).is_err());
+        assert!(eval(&format!("{}1", "-".repeat(100_000))).is_err());
+    }
+
+    #[test]
+    fn templates_and_expressions_resolve() {
+        let doc = resolve(json!({
+            "templates": { "blade": { "cpuCost": 2, "mass": 3, "health": 400 }, "heavyBlade": { "extends": "blade", "mass": 6, "health": null } },
+            "blocks": [ { "id": 90, "name": "Saw", "extends": "heavyBlade", "cost": "mass * 20 + cpuCost" } ]
+        })).unwrap();
+        assert_eq!(doc["blocks"][0], json!({ "id": 90, "name": "Saw", "cpuCost": 2, "mass": 6, "cost": 122 }));
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        assert!(resolve(json!({
+            "templates": { "a": { "extends": "b" }, "b": { "extends": "a" } },
+            "blocks": [ { "id": 1, "extends": "a" } ]
+        })).is_err());
+        assert!(resolve(json!({ "blocks": [ { "id": 1, "mass": "cost + 1", "cost": "mass * 2" } ] })).is_err());
+    }
+
+    #[test]
+    fn overrides_match_by_flag_id() {
+        let registry = FlagRegistry::with_definitions(serde_json::from_value(json!({
+            "flags": [ { "id": 0, "name": "health", "aliases": ["hp"] } ]
+        })).unwrap()).unwrap();
+        let doc = resolve_templates(json!({
+            "templates": { "base": { "health": 400, "spec1": 5 } },
+            "blocks": [ { "id": 1, "extends": "base", "hp": 500, "spec1_minlift": 7 } ]
+        }), &registry).unwrap();
+        assert_eq!(doc["blocks"][0], json!({ "id": 1, "hp": 500, "spec1_minlift": 7 }));
+    }
+}